@@ -0,0 +1,92 @@
+//! Compares a full decompression calculation with the cost of a single step of the incremental
+//! planner, which bounds how long one firmware tick can take.
+#![feature(test)]
+
+extern crate test;
+
+use capra_core_xtensa::common::dive_segment::{DiveSegment, SegmentType};
+use capra_core_xtensa::common::gas::Gas;
+use capra_core_xtensa::common::{DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE};
+use capra_core_xtensa::deco::tissue::Tissue;
+use capra_core_xtensa::deco::zhl16::variant::Variant;
+use capra_core_xtensa::deco::zhl16::{IncrementalPlanner, PlannerPhase, ZHL16};
+use capra_core_xtensa::gas;
+use core::time::Duration;
+use test::{black_box, Bencher};
+
+/// Returns a model after 25 minutes at 60m on trimix, and the gas breathed.
+fn deep_trimix() -> (ZHL16, Gas) {
+    let trimix = gas!(18, 45);
+    let mut deco = ZHL16::new_by_variant(Tissue::default(), 30, 70, Variant::C);
+    let segment = DiveSegment::new(
+        SegmentType::DiveSegment,
+        60,
+        60,
+        Duration::from_secs(25 * 60),
+        DEFAULT_ASCENT_RATE,
+        DEFAULT_DESCENT_RATE,
+    )
+    .unwrap();
+    deco.add_segment(&segment, &trimix, 10.0);
+    (deco, trimix)
+}
+
+/// Returns a planner that has started the calculation for a model.
+fn begin(deco: &ZHL16, gas: &Gas) -> IncrementalPlanner {
+    let mut planner = IncrementalPlanner::new(deco, gas);
+    planner
+        .begin(deco, gas, DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, 10.0)
+        .unwrap();
+    planner
+}
+
+#[bench]
+fn full_plan(b: &mut Bencher) {
+    let (deco, gas) = deep_trimix();
+    b.iter(|| {
+        let mut planner = begin(&deco, &gas);
+        assert!(planner.step(usize::MAX));
+        black_box(planner.result())
+    });
+}
+
+#[bench]
+fn first_stop_step(b: &mut Bencher) {
+    let (deco, gas) = deep_trimix();
+    let planner = begin(&deco, &gas);
+    b.iter(|| {
+        let mut planner = planner;
+        planner.step(1);
+        black_box(planner.phase())
+    });
+}
+
+#[bench]
+fn last_stop_step(b: &mut Bencher) {
+    let (deco, gas) = deep_trimix();
+    // Step to the last stop, which is the longest.
+    let mut planner = begin(&deco, &gas);
+    let mut last = planner;
+    while !planner.step(1) {
+        last = planner;
+    }
+    assert_eq!(last.phase(), PlannerPhase::Stops);
+    b.iter(|| {
+        let mut planner = last;
+        planner.step(1);
+        black_box(planner.phase())
+    });
+}
+
+#[bench]
+fn ndl_step(b: &mut Bencher) {
+    let air = gas!(21, 0);
+    let deco = ZHL16::new_by_variant(Tissue::default(), 30, 70, Variant::C);
+    let planner = begin(&deco, &air);
+    assert_eq!(planner.phase(), PlannerPhase::Ndl);
+    b.iter(|| {
+        let mut planner = planner;
+        planner.step(1);
+        black_box(planner.phase())
+    });
+}
@@ -5,23 +5,124 @@
 #include <cstdlib>
 #include <new>
 
+/// Version of the C API. This is increased whenever a function signature or the layout of a struct
+/// shared with C changes. The layouts of the deco model and planner are private to the library, so
+/// C code never needs recompiling when they change.
+static const uint32_t ABI_VERSION = 5;
+
+/// Maximum number of depth bands in [`AscentRates`].
+static const uintptr_t ASCENT_BAND_CAPACITY = 4;
+
+/// Maximum number of candidate gases [`select_deco_gases`] chooses from.
+static const uintptr_t CANDIDATE_CAPACITY = 8;
+
+/// Maximum number of depth or time offsets in a [`ContingencyTable`].
+static const uintptr_t CONTINGENCY_OFFSET_CAPACITY = 4;
+
+/// Maximum number of deco gases in a [`DivePlan`].
+static const uintptr_t DECO_GAS_CAPACITY = 4;
+
 /// A default, placeholder ascent rate (measured in m min^-1). This is the maximum rate recommended by major instruction agencies.
 static const intptr_t DEFAULT_ASCENT_RATE = -18;
 
 /// A default, placeholder descent rate (measured in m min^-1).
 static const intptr_t DEFAULT_DESCENT_RATE = 30;
 
-/// Density of fresh water (measured in kg m^-3).
-static const double DENSITY_FRESHWATER = 997.0;
+/// Default Surface Air Consumption (SAC) rate (measured in L min^-1).
+static const uintptr_t DEFAULT_SAC_RATE = 20;
 
-/// Average density of salt water (measured in kg m^-3).
-static const double DENSITY_SALTWATER = 1023.6;
+/// Maximum number of findings kept by a [`Lint`].
+static const uintptr_t FINDING_CAPACITY = 32;
+
+/// Maximum number of stops given their own gradient factor in a [`GradientFactorProfile::PerStop`].
+static const uintptr_t GF_STOP_CAPACITY = 16;
+
+/// Maximum number of points in a [`GradientFactorProfile::Table`].
+static const uintptr_t GF_TABLE_CAPACITY = 8;
+
+/// Maximum number of bottom levels in a [`DivePlan`].
+static const uintptr_t LEVEL_CAPACITY = 8;
+
+/// Highest altitude an [`NdlTable`] can be calculated for (measured in m).
+static const uintptr_t MAX_ALTITUDE = 7000;
+
+/// Deepest depth accepted by the C API (measured in metres).
+static const uintptr_t MAX_DEPTH = 1000;
+
+/// Fastest ascent or descent rate accepted by the C API (measured in m min^-1).
+static const intptr_t MAX_RATE = 100;
+
+/// Maximum number of entries in an [`NdlTable`].
+static const uintptr_t NDL_TABLE_CAPACITY = 48;
+
+/// Maximum number of segments in a [`DiveProfile`], including inserted depth changes.
+static const uintptr_t PROFILE_CAPACITY = 64;
+
+/// Maximum number of stops kept in a [`Schedule`].
+static const uintptr_t SCHEDULE_STOP_CAPACITY = 24;
+
+/// Deepest depth the solver searches down to (measured in m).
+static const uintptr_t SOLVER_DEPTH_LIMIT = 150;
+
+/// Longest bottom time the solver searches up to (measured in minutes).
+static const uintptr_t SOLVER_TIME_LIMIT = 300;
+
+/// Maximum number of tanks in a [`DivePlan`].
+static const uintptr_t TANK_CAPACITY = 6;
+
+/// Maximum no-decompression limit or stop time reported by the model (measured in minutes).
+static const uint64_t TIME_CAP = 999;
 
 /// Number of tissues in a typical decompression algorithm.
 static const uintptr_t TISSUE_COUNT = 16;
 
-/// Pressure of water vapour. (measured in bar)
-static const double WATER_VAPOUR_PRESSURE = 0.06257;
+/// Status returned by every function of the C API.
+enum class CStatus {
+  /// The function completed successfully.
+  Success,
+  /// A required pointer was null.
+  NullPointerError,
+  /// The percentage fractions of a gas do not add up to 100.
+  GasFractionError,
+  /// A depth exceeds [`MAX_DEPTH`].
+  DepthError,
+  /// An ascent rate is not negative, a descent rate is not positive, or either exceeds [`MAX_RATE`].
+  RateError,
+  /// A duration is zero.
+  DurationError,
+  /// Caller storage or an output buffer is too small.
+  SizeError,
+  /// Caller storage is not aligned as required.
+  AlignmentError,
+  /// The stop settings are invalid. See [`StopSettings::new`].
+  StopSettingsError,
+  /// The diver has exceeded the no-decompression limit.
+  NdlExceededError,
+  /// The diver is within the no-decompression limit, so has no deco stop to make.
+  NoStopError,
+  /// No calculation has completed yet.
+  NoResultError,
+  /// The type of a segment is inconsistent with its depths. See [`DiveSegment::new`].
+  SegmentError,
+  /// The depth of water inducing 1 bar of pressure is not a positive number.
+  MetresPerBarError,
+  /// A gradient factor is zero or above 100, or GF low is above GF high.
+  GradientFactorError,
+  /// An argument is not one of the values the function accepts.
+  InvalidArgument,
+  /// An altitude is above the highest an NDL table can be calculated for.
+  AltitudeError,
+};
+
+/// Preset conservatism levels, each mapping to a pair of gradient factors.
+enum class Conservatism {
+  /// GF 45/95.
+  Low = 0,
+  /// GF 40/85.
+  Medium = 1,
+  /// GF 35/75.
+  High = 2,
+};
 
 /// Represents different types of DiveSegments possible.
 enum class SegmentType {
@@ -33,6 +134,67 @@ enum class SegmentType {
   DiveSegment,
   /// Segment represents a change in depth.
   AscDesc,
+  /// Segment represents an optional deep stop below the first decompression stop.
+  DeepStop,
+};
+
+/// A decompression calculation that is performed over several calls, each doing a bounded amount of
+/// work. This allows firmware to calculate deco from a periodic task without missing deadlines.
+///
+/// Calling [`IncrementalPlanner::begin`] takes a snapshot of a model and starts a new calculation.
+/// Calling [`IncrementalPlanner::step`] with an iteration budget then advances it, where an iteration
+/// is either one compartment of the NDL or one stop of the ascent. The result of the last completed
+/// calculation stays available from [`IncrementalPlanner::result`] while the next one is in progress.
+///
+/// The layout of this struct is private, so C code only ever handles it through a pointer.
+struct IncrementalPlanner;
+
+/// A ZHL-16 decompression model of a diver.
+/// # Notes
+/// For now, each ZHL16 struct should only be used for one dive. This is because calculating decompression
+/// stops with Gradient Factors requires some side effects to be stored inside the struct.
+///
+/// The layout of this struct is private, so C code only ever handles it through a pointer.
+struct ZHL16;
+
+/// A depth band of [`AscentRates`]: from its depth up to the next shallower band, or the surface,
+/// the diver ascends at its rate.
+struct AscentBand {
+  /// Deepest depth of the band.
+  uintptr_t depth;
+  /// Ascent rate within the band (measured in m min^-1).
+  intptr_t rate;
+};
+
+#if !defined(CAPRA_SINGLE_PRECISION)
+/// Floating point type used by the library.
+using Float = double;
+#endif
+
+#if defined(CAPRA_SINGLE_PRECISION)
+/// Floating point type used by the library.
+using Float = float;
+#endif
+
+/// How close each compartment of a model is to its M-value, as used for a tissue bar graph.
+///
+/// Each value is the supersaturation of a compartment as a percentage of the supersaturation its
+/// M-value allows, i.e. the gradient factor the compartment is at. Zero means the compartment is
+/// at ambient pressure, 100 means it is at its M-value, and a negative value means it is still
+/// on-gassing.
+struct Supersaturation {
+  /// Percentage of the M-value of each compartment at the current ambient pressure.
+  Float current[TISSUE_COUNT];
+  /// Percentage of the M-value each compartment would be at on surfacing immediately.
+  Float surface[TISSUE_COUNT];
+  /// Highest percentage at the current ambient pressure (GF99). Zero if every compartment is
+  /// still on-gassing.
+  Float gf99;
+  /// Highest percentage at the surface (surface GF). Zero if every compartment would still be
+  /// on-gassing.
+  Float surface_gf;
+  /// Index of the compartment closest to its M-value at the current ambient pressure.
+  uintptr_t leading_compartment;
 };
 
 struct CDiveSegment {
@@ -50,42 +212,6 @@ struct CDiveSegment {
   intptr_t descent_rate;
 };
 
-/// A set of tissues for use in decompression models, comprising a set of tissues for nitrogen
-/// and another set for helium.
-struct Tissue {
-  double p_n2[TISSUE_COUNT];
-  double p_he[TISSUE_COUNT];
-  double p_t[TISSUE_COUNT];
-};
-
-/// A ZHL-16 decompression model of a diver.
-/// # Notes
-/// For now, each ZHL16 struct should only be used for one dive. This is because calculating decompression
-/// stops with Gradient Factors requires some side effects to be stored inside the struct.
-struct ZHL16 {
-  /// Current tissue model of the diver.
-  Tissue tissue;
-  /// Current depth of the diver.
-  uintptr_t diver_depth;
-  /// Nitrogen A-values.
-  double n2_a[TISSUE_COUNT];
-  /// Nitrogen B-values.
-  double n2_b[TISSUE_COUNT];
-  /// Nitrogen half-lives.
-  double n2_hl[TISSUE_COUNT];
-  /// Helium A-values.
-  double he_a[TISSUE_COUNT];
-  /// Helium B-values.
-  double he_b[TISSUE_COUNT];
-  /// Helium half-lives.
-  double he_hl[TISSUE_COUNT];
-  uintptr_t first_deco_depth;
-  /// GF Low value
-  double gf_low;
-  /// GF High value
-  double gf_high;
-};
-
 /// A gas mix used in a dive.
 struct Gas {
   /// Percentage fraction of oxygen in the mix.
@@ -96,15 +222,487 @@ struct Gas {
   uintptr_t n2;
 };
 
+/// The no-decompression limit at one depth on one gas, as written by [`ndl_table`].
+struct CNdlEntry {
+  /// Depth of the entry.
+  uintptr_t depth;
+  /// Gas breathed on the descent and at the depth.
+  Gas gas;
+  /// Time that can be spent at the depth once it is reached, or zero if the diver would already
+  /// need to decompress (milliseconds)
+  uint64_t ndl;
+};
+
+/// The result of a completed decompression calculation.
+struct DecoResult {
+  /// `NoDeco` if the diver can surface directly, otherwise `DecoStop`.
+  SegmentType segment_type;
+  /// Depth of the next stop, or the current depth if the diver is within the NDL.
+  uintptr_t depth;
+  /// Duration of the next stop, or the NDL (milliseconds)
+  uint64_t time;
+  /// Time to surface, including the ascent and every stop (milliseconds)
+  uint64_t tts;
+  /// Whether a stop lasted [`TIME_CAP`] minutes without clearing, so the ascent was abandoned
+  /// there and `tts` only counts up to the end of that stop.
+  bool capped;
+};
+
+/// Default highest ppO2 a deco gas is switched to at (measured in bar).
+static const Float DEFAULT_DECO_PP_O2 = 1.6;
+
+/// Default CNS oxygen toxicity limit of a dive (as a percentage of the NOAA limit).
+static const Float DEFAULT_MAX_CNS = 100.0;
+
+/// Default highest density a gas may be switched to at (measured in g L^-1).
+static const Float DEFAULT_MAX_DENSITY = 6.2;
+
+/// Default OTU limit of a dive.
+static const Float DEFAULT_MAX_OTU = 300.0;
+
+/// Default highest ppO2 of a gas on the way down and at the levels (measured in bar).
+static const Float DEFAULT_MAX_PP_O2 = 1.4;
+
+/// Default lowest ppO2 a deco gas may be breathed at (measured in bar).
+static const Float DEFAULT_MIN_PP_O2 = 0.16;
+
+/// Density of fresh water (measured in kg m^-3).
+static const Float DENSITY_FRESHWATER = 997.0;
+
+/// Average density of salt water (measured in kg m^-3).
+static const Float DENSITY_SALTWATER = 1023.6;
+
+/// Pressure of water vapour. (measured in bar)
+static const Float WATER_VAPOUR_PRESSURE = 0.06257;
+
 extern "C" {
 
-CDiveSegment get_next_stop(const ZHL16 *deco,
-                           const Gas *gas,
-                           intptr_t ascent_rate,
-                           intptr_t descent_rate);
+/// Returns the version of the C API the library was built with. Compare this to [`ABI_VERSION`]
+/// in the header to detect a mismatched library.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+uint32_t abi_version();
+
+/// Write the fastest ascent rate allowed at a depth to `rate`, such as to raise an ascent rate
+/// alarm.
+/// # Arguments
+/// * `ascent_rate` - Ascent rate below the deepest band (measured in m min^-1)
+/// * `bands` - Depth bands, each ascended at its rate from its depth up to the next shallower band,
+///   or the surface
+/// * `band_count` - Number of bands, at most [`ASCENT_BAND_CAPACITY`]. May be 0
+/// * `depth` - Depth of the diver
+/// * `rate` - Ascent rate allowed at `depth` (measured in m min^-1)
+/// # Safety
+/// `bands` must point to `band_count` `AscentBand`s unless `band_count` is 0, and `rate` must be
+/// null or point to a writable `intptr_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 320 bytes.
+CStatus ascent_rate_at(intptr_t ascent_rate,
+                       const AscentBand *bands,
+                       uintptr_t band_count,
+                       uintptr_t depth,
+                       intptr_t *rate);
+
+/// Write the depth of water that induces a pressure (measured in bar) to `mtr`.
+/// # Safety
+/// `mtr` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus bar_mtr(Float bar, Float metres_per_bar, Float *mtr);
+
+/// Returns the alignment (in bytes) the storage of a deco model needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+uintptr_t deco_align();
+
+/// Write the current depth of a deco model to `depth`.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `depth` must be null
+/// or point to a writable `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus deco_depth(const ZHL16 *deco, uintptr_t *depth);
+
+/// Write the depth of the first deco stop made to `depth`. The gradient factor curve is anchored
+/// at this depth once it is set. Returns `NoStopError` if no deco stop has been made yet.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `depth` must be null
+/// or point to a writable `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus deco_first_deco_depth(const ZHL16 *deco, uintptr_t *depth);
+
+/// Returns the number of bytes of storage a deco model needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+uintptr_t deco_size();
+
+/// Write how close each compartment of a deco model is to its M-value to `supersaturation`. See
+/// [`Supersaturation`].
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `supersaturation`
+/// must be null or point to writable memory for a `Supersaturation`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 336 bytes.
+CStatus deco_supersaturation(const ZHL16 *deco,
+                             Float metres_per_bar,
+                             Supersaturation *supersaturation);
+
+/// Copy the nitrogen and helium pressures (measured in bar) of every compartment of a deco model.
+/// # Arguments
+/// * `p_n2` - Buffer for the nitrogen pressures
+/// * `p_he` - Buffer for the helium pressures
+/// * `len` - Length of each buffer, which must be at least [`TISSUE_COUNT`]
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `p_n2` and `p_he` must
+/// be null or point to `len` writable `Float`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus deco_tissue(const ZHL16 *deco,
+                    Float *p_n2,
+                    Float *p_he,
+                    uintptr_t len);
+
+/// Write the quantity of gas a diver would consume in a segment to `consumed`.
+/// # Arguments
+/// * `segment` - Segment to calculate the gas consumed for
+/// * `sac_rate` - Surface Air Consumption (SAC) rate (measured in bar min^-1)
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// * `consumed` - Output for the gas consumed
+/// # Safety
+/// `segment` must be null or point to a `CDiveSegment`, and `consumed` must be null or point to a
+/// writable `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 8 bytes.
+CStatus gas_consumed(const CDiveSegment *segment,
+                     uintptr_t sac_rate,
+                     Float metres_per_bar,
+                     uintptr_t *consumed);
+
+/// Write the Equivalent Narcotic Depth (END) of a gas at a given depth to `end` (measured in m).
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `end` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus gas_end(const Gas *gas, uintptr_t depth, Float metres_per_bar, Float *end);
+
+/// Check the fractions of a gas mix and write it to `gas`.
+/// # Arguments
+/// * `o2` - Percentage fraction of oxygen in the mix
+/// * `he` - Percentage fraction of helium in the mix
+/// * `n2` - Percentage fraction of nitrogen in the mix
+/// # Safety
+/// `gas` must be null or point to writable memory for a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus gas_new(uintptr_t o2, uintptr_t he, uintptr_t n2, Gas *gas);
+
+/// Write the ppHe (measured in bar) of a gas at a given depth to `pp_he`.
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `pp_he` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus gas_pp_he(const Gas *gas, uintptr_t depth, Float metres_per_bar, Float *pp_he);
+
+/// Write the ppN2 (measured in bar) of a gas at a given depth to `pp_n2`.
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `pp_n2` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus gas_pp_n2(const Gas *gas, uintptr_t depth, Float metres_per_bar, Float *pp_n2);
+
+/// Write the ppO2 (measured in bar) of a gas at a given depth to `pp_o2`.
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `pp_o2` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus gas_pp_o2(const Gas *gas, uintptr_t depth, Float metres_per_bar, Float *pp_o2);
+
+/// Write the gradient factors (as percentages) of a deco model to `gf_low` and `gf_high`.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `gf_low` and `gf_high`
+/// must be null or point to writable `size_t`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus get_gradient_factors(const ZHL16 *deco,
+                             uintptr_t *gf_low,
+                             uintptr_t *gf_high);
+
+/// Write the no-decompression limit of the model to `ndl`, and the index of the compartment
+/// controlling it to `compartment`. See [`ZHL16::ndl_with_compartment`] for how the compartment is
+/// chosen.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// * `ndl` - No-decompression limit (milliseconds)
+/// * `compartment` - Index of the compartment controlling the limit, from 0 for the fastest
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], `gas` must be null or
+/// point to a `Gas`, and `ndl` and `compartment` must be null or point to a writable `uint64_t` and
+/// `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 608 bytes.
+CStatus get_ndl(const ZHL16 *deco,
+                const Gas *gas,
+                Float metres_per_bar,
+                uint64_t *ndl,
+                uintptr_t *compartment);
+
+/// Write the NDL (as a `NoDeco` segment) or the next deco stop (as a `DecoStop` segment) to `segment`.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], `gas` must be null or
+/// point to a `Gas`, and `segment` must be null or point to writable memory for a `CDiveSegment`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 1320 bytes. The ascent to the stop is simulated on a copy of the tissues only.
+CStatus get_next_stop(const ZHL16 *deco,
+                      const Gas *gas,
+                      intptr_t ascent_rate,
+                      intptr_t descent_rate,
+                      Float metres_per_bar,
+                      CDiveSegment *segment);
+
+/// Initialise a deco model in caller storage with ZHL-16C, gradient factors of 100/100 and
+/// air-saturated tissues.
+/// # Arguments
+/// * `deco` - Storage of at least [`deco_size`] bytes, aligned to [`deco_align`]
+/// * `size` - Size of the storage (measured in bytes)
+/// # Safety
+/// `deco` must be null or point to `size` writable bytes.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 8 bytes.
+CStatus initialise(ZHL16 *deco, uintptr_t size);
+
+/// Write the pressure (measured in bar) induced by a depth of water to `bar`.
+/// # Safety
+/// `bar` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus mtr_bar(Float mtr, Float metres_per_bar, Float *bar);
+
+/// Write the NDLs at each depth for each gas to `entries`, ordered by gas, then by depth. The deco
+/// model is not changed. See [`NdlTable::for_depths`] for how the surface interval and altitude
+/// are applied.
+/// # Arguments
+/// * `depths` - Depths to calculate NDLs at
+/// * `depth_count` - Number of depths
+/// * `gases` - Gases to calculate NDLs for
+/// * `gas_count` - Number of gases. There can be at most [`NDL_TABLE_CAPACITY`] entries in all
+/// * `surface_interval` - Time spent at the surface breathing air before the dive (seconds)
+/// * `altitude` - Altitude of the dive site above sea level (measured in m)
+/// * `descent_rate` - Descent rate (measured in m min^-1)
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// * `entries` - Buffer to write the table to
+/// * `capacity` - Number of entries `entries` can hold, at least `depth_count * gas_count`
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], `depths` must be null or
+/// point to `depth_count` `size_t`s, `gases` must be null or point to `gas_count` `Gas`es, and
+/// `entries` must be null or point to writable memory for `capacity` `CNdlEntry`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 11112 bytes.
+CStatus ndl_table(const ZHL16 *deco,
+                  const uintptr_t *depths,
+                  uintptr_t depth_count,
+                  const Gas *gases,
+                  uintptr_t gas_count,
+                  uint64_t surface_interval,
+                  uintptr_t altitude,
+                  intptr_t descent_rate,
+                  Float metres_per_bar,
+                  CNdlEntry *entries,
+                  uintptr_t capacity);
+
+/// Write the Oxygen Toxicity Units (OTU) accumulated during a segment to `otu`.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `segment` must be null or point to a `CDiveSegment`, `gas` must be null or point to a `Gas`, and
+/// `otu` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 112 bytes.
+CStatus otu(const CDiveSegment *segment, const Gas *gas, Float metres_per_bar, Float *otu);
+
+/// Returns the alignment (in bytes) the storage of an incremental planner needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+uintptr_t planner_align();
+
+/// Start a new calculation from a snapshot of a deco model.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], `deco` must be
+/// null or point to a deco model set up by [`initialise`], and `gas` must be null or point to a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 88 bytes.
+CStatus planner_begin(IncrementalPlanner *planner,
+                      const ZHL16 *deco,
+                      const Gas *gas,
+                      intptr_t ascent_rate,
+                      intptr_t descent_rate,
+                      Float metres_per_bar);
+
+/// Start a new calculation from a snapshot of a deco model, ascending at rates that depend on depth.
+/// # Arguments
+/// * `ascent_rate` - Ascent rate below the deepest band (measured in m min^-1)
+/// * `bands` - Depth bands, each ascended at its rate from its depth up to the next shallower band,
+///   or the surface
+/// * `band_count` - Number of bands, at most [`ASCENT_BAND_CAPACITY`]. May be 0
+/// * `descent_rate` - Descent rate (measured in m min^-1)
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], `deco` must be
+/// null or point to a deco model set up by [`initialise`], `gas` must be null or point to a `Gas`,
+/// and `bands` must point to `band_count` `AscentBand`s unless `band_count` is 0.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 384 bytes.
+CStatus planner_begin_with_rates(IncrementalPlanner *planner,
+                                 const ZHL16 *deco,
+                                 const Gas *gas,
+                                 intptr_t ascent_rate,
+                                 const AscentBand *bands,
+                                 uintptr_t band_count,
+                                 intptr_t descent_rate,
+                                 Float metres_per_bar);
+
+/// Initialise an incremental planner for a deco model in caller storage, with no calculation in progress.
+/// # Arguments
+/// * `planner` - Storage of at least [`planner_size`] bytes, aligned to [`planner_align`]
+/// * `size` - Size of the storage (measured in bytes)
+/// * `deco` - Deco model to plan for
+/// * `gas` - Gas to plan the ascent with
+/// # Safety
+/// `planner` must be null or point to `size` writable bytes, `deco` must be null or point to a deco
+/// model set up by [`initialise`], and `gas` must be null or point to a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 40 bytes.
+CStatus planner_initialise(IncrementalPlanner *planner,
+                           uintptr_t size,
+                           const ZHL16 *deco,
+                           const Gas *gas);
+
+/// Write the result of the last completed calculation to `result`.
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], and `result` must
+/// be null or point to writable memory for a `DecoResult`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus planner_result(const IncrementalPlanner *planner, DecoResult *result);
+
+/// Returns the number of bytes of storage an incremental planner needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+uintptr_t planner_size();
+
+/// Advance the calculation in progress by at most `budget` iterations, and write whether it is
+/// complete to `complete`. Returns the error that stopped the calculation if it failed.
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], and `complete`
+/// must be null or point to a writable `bool`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 1528 bytes, regardless of `budget`.
+CStatus planner_step(IncrementalPlanner *planner, uintptr_t budget, bool *complete);
+
+/// Change the gradient factors of a deco model to those of a preset conservatism level.
+/// # Arguments
+/// * `level` - One of the values of [`Conservatism`]. It is passed as an integer, as C may hold any
+///   value in an enum
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus set_conservatism(ZHL16 *deco, uint8_t level);
+
+/// Use GF high at every depth.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus set_gf_profile_high_only(ZHL16 *deco);
+
+/// Use the classic gradient factor line from GF low at the first stop to GF high at the surface.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus set_gf_profile_linear(ZHL16 *deco);
+
+/// Give each stop its own gradient factor, counted from the first stop. See
+/// [`GradientFactorProfile::per_stop`].
+/// # Arguments
+/// * `gfs` - Gradient factor (as a percentage) of each stop
+/// * `len` - Number of stops, at most [`GF_STOP_CAPACITY`]
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `gfs` must be null
+/// or point to `len` `size_t`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 328 bytes.
+CStatus set_gf_profile_per_stop(ZHL16 *deco, const uintptr_t *gfs, uintptr_t len);
+
+/// Use gradient factors interpolated between points at fixed depths. See
+/// [`GradientFactorProfile::table`].
+/// # Arguments
+/// * `depths` - Depth of each point, strictly increasing
+/// * `gfs` - Gradient factor (as a percentage) at each point
+/// * `len` - Number of points, at most [`GF_TABLE_CAPACITY`]
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `depths` and `gfs`
+/// must be null or point to `len` `size_t`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 440 bytes.
+CStatus set_gf_profile_table(ZHL16 *deco,
+                             const uintptr_t *depths,
+                             const uintptr_t *gfs,
+                             uintptr_t len);
+
+/// Change the gradient factors of a deco model. See [`ZHL16::set_gradient_factors`] for how this
+/// affects a dive in progress.
+/// # Arguments
+/// * `gf_low` - Gradient Factor low value (as a percentage)
+/// * `gf_high` - Gradient Factor high value (as a percentage)
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus set_gradient_factors(ZHL16 *deco, uintptr_t gf_low, uintptr_t gf_high);
+
+/// Change the stop settings of a deco model.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus set_stop_settings(ZHL16 *deco,
+                          uintptr_t stop_interval,
+                          uintptr_t last_stop_depth,
+                          uint64_t min_stop_time,
+                          uint64_t stop_resolution);
 
-void initialise(ZHL16 *deco);
+/// Load a deco model with a constant depth segment lasting `tick` seconds.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `gas` must be null or
+/// point to a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 672 bytes.
+CStatus tick_segment(ZHL16 *deco,
+                     const Gas *gas,
+                     uintptr_t depth,
+                     uint64_t tick,
+                     Float metres_per_bar);
 
-void tick_segment(ZHL16 *deco, const Gas *gas, uintptr_t depth, uint64_t tick);
+/// Write the time (measured in milliseconds) taken to change between two depths at a given rate to `time`.
+/// # Safety
+/// `time` must be null or point to a writable `uint64_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+CStatus time_taken(intptr_t rate,
+                   uintptr_t depth_1,
+                   uintptr_t depth_2,
+                   uint64_t *time);
 
 } // extern "C"
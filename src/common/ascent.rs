@@ -0,0 +1,280 @@
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::{time_taken, DEFAULT_ASCENT_RATE};
+use core::time::Duration;
+
+/// Maximum number of depth bands in [`AscentRates`].
+pub const ASCENT_BAND_CAPACITY: usize = 4;
+
+/// Represents errors that occur while building AscentRates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AscentRatesError {
+    /// An ascent rate is not negative.
+    RateError,
+    /// A band already starts at the depth.
+    DepthError,
+    /// There are already [`ASCENT_BAND_CAPACITY`] bands.
+    CapacityError,
+}
+
+/// A depth band of [`AscentRates`]: from its depth up to the next shallower band, or the surface,
+/// the diver ascends at its rate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct AscentBand {
+    /// Deepest depth of the band.
+    depth: usize,
+    /// Ascent rate within the band (measured in m min^-1).
+    rate: isize,
+}
+
+impl AscentBand {
+    /// Returns the deepest depth of the band.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the ascent rate within the band (measured in m min^-1).
+    pub fn rate(&self) -> isize {
+        self.rate
+    }
+}
+
+/// Ascent rates that depend on depth, such as 9 m/min up to 6 m then 1 m/min to the surface. Below
+/// the deepest band the diver ascends at the base rate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AscentRates {
+    /// Ascent rate below the deepest band (measured in m min^-1).
+    rate: isize,
+    /// Number of bands in use.
+    len: usize,
+    /// Bands, from the shallowest to the deepest.
+    bands: [AscentBand; ASCENT_BAND_CAPACITY],
+}
+
+impl AscentRates {
+    /// Returns AscentRates with a single rate at every depth.
+    /// # Arguments
+    /// * `rate` - Ascent rate (measured in m min^-1)
+    /// # Errors
+    /// This function will return an [`AscentRatesError`] if `rate` is not negative.
+    pub fn new(rate: isize) -> Result<Self, AscentRatesError> {
+        if rate >= 0 {
+            return Err(AscentRatesError::RateError);
+        }
+        Ok(Self {
+            rate,
+            len: 0,
+            bands: [AscentBand { depth: 0, rate }; ASCENT_BAND_CAPACITY],
+        })
+    }
+
+    /// Add a band to ascend at a different rate from a depth up to the next shallower band, or the
+    /// surface.
+    /// # Arguments
+    /// * `depth` - Deepest depth of the band
+    /// * `rate` - Ascent rate within the band (measured in m min^-1)
+    /// # Errors
+    /// This function will return an [`AscentRatesError`] if `rate` is not negative, if a band
+    /// already starts at `depth`, or if there are already [`ASCENT_BAND_CAPACITY`] bands.
+    pub fn add_band(&mut self, depth: usize, rate: isize) -> Result<(), AscentRatesError> {
+        if rate >= 0 {
+            return Err(AscentRatesError::RateError);
+        }
+        if self.bands().iter().any(|t| t.depth == depth) {
+            return Err(AscentRatesError::DepthError);
+        }
+        if self.len == ASCENT_BAND_CAPACITY {
+            return Err(AscentRatesError::CapacityError);
+        }
+
+        // Keep the bands sorted from the shallowest.
+        let idx = self
+            .bands()
+            .iter()
+            .position(|t| t.depth > depth)
+            .unwrap_or(self.len);
+        self.bands.copy_within(idx..self.len, idx + 1);
+        self.bands[idx] = AscentBand { depth, rate };
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the ascent rate below the deepest band (measured in m min^-1).
+    pub fn rate(&self) -> isize {
+        self.rate
+    }
+
+    /// Returns the bands, from the shallowest to the deepest.
+    pub fn bands(&self) -> &[AscentBand] {
+        &self.bands[..self.len]
+    }
+
+    /// Returns the ascent rate of the metre of water column ending at a depth (measured in
+    /// m min^-1).
+    /// # Arguments
+    /// * `depth` - Depth to ascend from
+    pub fn rate_at(&self, depth: usize) -> isize {
+        self.bands()
+            .iter()
+            .find(|t| depth <= t.depth)
+            .map_or(self.rate, |t| t.rate)
+    }
+
+    /// Returns the time taken to ascend between two depths, with each part at the rate of its band.
+    /// # Arguments
+    /// * `from` - Depth to ascend from
+    /// * `to` - Depth to ascend to
+    pub fn time_taken(&self, from: usize, to: usize) -> Duration {
+        self.segments(from, to, 1)
+            .fold(Duration::from_secs(0), |acc, t| acc + *t.time())
+    }
+
+    /// Returns the `AscDesc` segments of an ascent between two depths, one for each band it
+    /// crosses. There are none if `to` is not shallower than `from`.
+    /// # Arguments
+    /// * `from` - Depth to ascend from
+    /// * `to` - Depth to ascend to
+    /// * `descent_rate` - Descent rate of the diver (measured in m min^-1)
+    pub fn segments(&self, from: usize, to: usize, descent_rate: isize) -> AscentSegments {
+        AscentSegments {
+            rates: *self,
+            depth: from,
+            to,
+            descent_rate,
+        }
+    }
+}
+
+impl Default for AscentRates {
+    /// Returns AscentRates with [`DEFAULT_ASCENT_RATE`] at every depth.
+    fn default() -> Self {
+        Self {
+            rate: DEFAULT_ASCENT_RATE,
+            len: 0,
+            bands: [AscentBand {
+                depth: 0,
+                rate: DEFAULT_ASCENT_RATE,
+            }; ASCENT_BAND_CAPACITY],
+        }
+    }
+}
+
+/// Iterator over the segments of an ascent, returned by [`AscentRates::segments`].
+#[derive(Copy, Clone)]
+pub struct AscentSegments {
+    /// Rates of the ascent.
+    rates: AscentRates,
+    /// Depth the next segment starts at.
+    depth: usize,
+    /// Depth the ascent ends at.
+    to: usize,
+    /// Descent rate of the diver (measured in m min^-1).
+    descent_rate: isize,
+}
+
+impl Iterator for AscentSegments {
+    type Item = DiveSegment;
+
+    fn next(&mut self) -> Option<DiveSegment> {
+        if self.depth <= self.to {
+            return None;
+        }
+        // The segment ends where the band above the current depth starts.
+        let boundary = self
+            .rates
+            .bands()
+            .iter()
+            .rev()
+            .map(|t| t.depth)
+            .find(|t| *t < self.depth)
+            .unwrap_or(0);
+        let end = core::cmp::max(boundary, self.to);
+        let rate = self.rates.rate_at(self.depth);
+        let segment = DiveSegment::new(
+            SegmentType::AscDesc,
+            self.depth,
+            end,
+            time_taken(rate, self.depth, end),
+            rate,
+            self.descent_rate,
+        )
+        .ok()?;
+        self.depth = end;
+        Some(segment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns rates of 9 m min^-1 to 6 m, 3 m min^-1 to 3 m and 1 m min^-1 to the surface.
+    fn slowing() -> AscentRates {
+        let mut rates = AscentRates::new(-9).unwrap();
+        rates.add_band(6, -3).unwrap();
+        rates.add_band(3, -1).unwrap();
+        rates
+    }
+
+    #[test]
+    fn bands_are_kept_from_the_shallowest() {
+        let rates = slowing();
+        let depths: Vec<usize> = rates.bands().iter().map(|t| t.depth()).collect();
+        assert_eq!(depths, vec![3, 6]);
+        assert_eq!(rates.rate(), -9);
+        assert_eq!(rates.bands()[0].rate(), -1);
+        assert_eq!(rates.bands()[1].rate(), -3);
+    }
+
+    #[test]
+    fn rate_is_taken_from_the_band_containing_the_depth() {
+        let rates = slowing();
+        assert_eq!(rates.rate_at(30), -9);
+        assert_eq!(rates.rate_at(7), -9);
+        assert_eq!(rates.rate_at(6), -3);
+        assert_eq!(rates.rate_at(4), -3);
+        assert_eq!(rates.rate_at(3), -1);
+        assert_eq!(rates.rate_at(0), -1);
+        assert_eq!(AscentRates::default().rate_at(30), DEFAULT_ASCENT_RATE);
+    }
+
+    #[test]
+    fn ascent_is_split_at_each_band() {
+        let rates = slowing();
+        let segments: Vec<(usize, usize, u64)> = rates
+            .segments(30, 0, 20)
+            .map(|t| (t.start_depth(), t.end_depth(), t.time().as_secs()))
+            .collect();
+        // 24 m at 9 m min^-1, 3 m at 3 m min^-1 and 3 m at 1 m min^-1.
+        assert_eq!(segments, vec![(30, 6, 160), (6, 3, 60), (3, 0, 180)]);
+        assert_eq!(rates.time_taken(30, 0), Duration::from_secs(400));
+
+        // 2 m at 3 m min^-1 and 1 m at 1 m min^-1.
+        assert_eq!(rates.time_taken(5, 2), Duration::from_secs(100));
+        assert_eq!(rates.time_taken(20, 10), Duration::from_secs(66));
+    }
+
+    #[test]
+    fn no_segments_unless_ascending() {
+        let rates = slowing();
+        assert_eq!(rates.segments(6, 6, 20).count(), 0);
+        assert_eq!(rates.segments(3, 6, 20).count(), 0);
+        assert_eq!(rates.time_taken(3, 6), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn invalid_rates_and_bands_are_rejected() {
+        assert_eq!(AscentRates::new(0).err(), Some(AscentRatesError::RateError));
+        assert_eq!(AscentRates::new(9).err(), Some(AscentRatesError::RateError));
+
+        let mut rates = slowing();
+        assert_eq!(rates.add_band(9, 3), Err(AscentRatesError::RateError));
+        assert_eq!(rates.add_band(6, -2), Err(AscentRatesError::DepthError));
+        rates.add_band(9, -6).unwrap();
+        rates.add_band(12, -6).unwrap();
+        assert_eq!(rates.add_band(15, -6), Err(AscentRatesError::CapacityError));
+        assert_eq!(rates.bands().len(), ASCENT_BAND_CAPACITY);
+    }
+}
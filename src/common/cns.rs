@@ -0,0 +1,149 @@
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use core::time::Duration;
+
+/// ppO2 at or below which no CNS oxygen toxicity is accumulated (measured in bar).
+const CNS_THRESHOLD: Float = 0.5;
+
+/// NOAA single exposure limits, as pairs of ppO2 (measured in bar) and the time it can be breathed
+/// for (measured in minutes).
+const NOAA_LIMITS: [(Float, Float); 11] = [
+    (0.6, 720.0),
+    (0.7, 570.0),
+    (0.8, 450.0),
+    (0.9, 360.0),
+    (1.0, 300.0),
+    (1.1, 240.0),
+    (1.2, 210.0),
+    (1.3, 180.0),
+    (1.4, 150.0),
+    (1.5, 120.0),
+    (1.6, 45.0),
+];
+
+/// Shortest exposure limit used above the NOAA table (measured in minutes).
+const MIN_LIMIT: Float = 1.0;
+
+/// Number of slices an ascent or descent is split into.
+const DEPTH_CHANGE_SLICES: usize = 8;
+
+/// Returns the CNS oxygen toxicity (as a percentage of the NOAA limit) accumulated during a segment
+/// with a specified gas, in water that takes 10 m to induce 1 bar of pressure.
+/// # Arguments
+/// * `segment` - Segment to calculate CNS toxicity for
+/// * `gas` - Gas used in that segment
+pub fn cns(segment: &DiveSegment, gas: &Gas) -> Float {
+    cns_with_metres_per_bar(segment, gas, 10.0)
+}
+
+/// Returns the CNS oxygen toxicity (as a percentage of the NOAA limit) accumulated during a segment
+/// with a specified gas.
+/// # Arguments
+/// * `segment` - Segment to calculate CNS toxicity for
+/// * `gas` - Gas used in that segment
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+pub fn cns_with_metres_per_bar(segment: &DiveSegment, gas: &Gas, metres_per_bar: Float) -> Float {
+    match segment.segment_type() {
+        SegmentType::AscDesc => {
+            // ppO2 changes linearly with depth, so sample it at the middle of each slice.
+            let p_o2i = gas.pp_o2(segment.start_depth(), metres_per_bar);
+            let p_o2f = gas.pp_o2(segment.end_depth(), metres_per_bar);
+            let slice = *segment.time() / DEPTH_CHANGE_SLICES as u32;
+            (0..DEPTH_CHANGE_SLICES)
+                .map(|idx| {
+                    let x = (idx as Float + 0.5) / DEPTH_CHANGE_SLICES as Float;
+                    constant_depth(slice, p_o2i + (p_o2f - p_o2i) * x)
+                })
+                .sum()
+        }
+        _ => constant_depth(
+            *segment.time(),
+            gas.pp_o2(segment.start_depth(), metres_per_bar),
+        ),
+    }
+}
+
+/// CNS toxicity in constant depth
+/// # Arguments
+/// * `time` - Time spent at the depth
+/// * `p_o2` - ppO2 breathed at the depth
+pub fn constant_depth(time: Duration, p_o2: Float) -> Float {
+    if p_o2 <= CNS_THRESHOLD {
+        return 0.0;
+    }
+    100.0 * (time.as_secs_f64() as Float / 60.0) / exposure_limit(p_o2)
+}
+
+/// Returns the NOAA single exposure limit of a ppO2 (measured in minutes). Limits between points
+/// of the table are interpolated linearly. Below the table the limit at 0.6 bar is used, and above
+/// it the limit keeps falling at the rate between 1.5 and 1.6 bar.
+fn exposure_limit(p_o2: Float) -> Float {
+    let (first_p, first_limit) = NOAA_LIMITS[0];
+    if p_o2 <= first_p {
+        return first_limit;
+    }
+    for window in NOAA_LIMITS.windows(2) {
+        let (p_1, limit_1) = window[0];
+        let (p_2, limit_2) = window[1];
+        if p_o2 <= p_2 {
+            return limit_1 + (limit_2 - limit_1) * (p_o2 - p_1) / (p_2 - p_1);
+        }
+    }
+    let (p_1, limit_1) = NOAA_LIMITS[NOAA_LIMITS.len() - 2];
+    let (p_2, limit_2) = NOAA_LIMITS[NOAA_LIMITS.len() - 1];
+    let limit = limit_2 + (limit_2 - limit_1) * (p_o2 - p_2) / (p_2 - p_1);
+    if limit < MIN_LIMIT {
+        MIN_LIMIT
+    } else {
+        limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas;
+
+    #[test]
+    fn constant_depth_matches_noaa_limits() {
+        // Half an exposure limit from the NOAA table is half the CNS clock.
+        for &(p_o2, limit) in NOAA_LIMITS.iter() {
+            let half = Duration::from_secs((limit * 30.0) as u64);
+            assert!((constant_depth(half, p_o2) - 50.0).abs() < 0.01, "{}", p_o2);
+        }
+        // Between points of the table, the limit is interpolated: 135 minutes at 1.45 bar.
+        assert!((constant_depth(Duration::from_secs(27 * 60), 1.45) - 20.0).abs() < 0.01);
+        // No toxicity at or below 0.5 bar, and the 0.6 bar limit just above it.
+        assert_eq!(constant_depth(Duration::from_secs(3600), 0.5), 0.0);
+        assert!((constant_depth(Duration::from_secs(72 * 60), 0.55) - 10.0).abs() < 0.01);
+        // Far above the table, every minute exceeds the limit.
+        assert!((constant_depth(Duration::from_secs(60), 2.0) - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn segments_use_the_density_of_the_water() {
+        // EAN50 at 20 m is 1.5 bar (120 minutes) at 10 m per bar, or 1.0 bar (300 minutes) at 20.
+        let nitrox = gas!(50, 0);
+        let level = DiveSegment::new(
+            SegmentType::DiveSegment,
+            20,
+            20,
+            Duration::from_secs(30 * 60),
+            -9,
+            18,
+        )
+        .unwrap();
+        assert!((cns(&level, &nitrox) - 25.0).abs() < 0.01);
+        assert!((cns_with_metres_per_bar(&level, &nitrox, 20.0) - 10.0).abs() < 0.01);
+
+        // An ascent from 1.6 to 1.0 bar is close to the average of the limits it passes through.
+        let oxygen = gas!(100, 0);
+        let ascent =
+            DiveSegment::new(SegmentType::AscDesc, 6, 0, Duration::from_secs(60), -6, 18).unwrap();
+        let sampled: Float = (0..6)
+            .map(|t| constant_depth(Duration::from_secs(10), 1.55 - 0.1 * t as Float))
+            .sum();
+        assert!((cns(&ascent, &oxygen) - sampled).abs() < 0.05);
+    }
+}
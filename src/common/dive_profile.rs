@@ -0,0 +1,298 @@
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::time_taken;
+use core::time::Duration;
+
+/// Maximum number of segments in a [`DiveProfile`], including inserted depth changes.
+pub const PROFILE_CAPACITY: usize = 64;
+
+/// Represents errors that occur while building a DiveProfile.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiveProfileError {
+    /// The profile has no room left for the segment and any depth change needed to reach it.
+    CapacityError,
+    /// A depth change does not start at the depth the profile ends at.
+    ContinuityError,
+    /// A depth change takes a different time than its rate allows.
+    DurationError,
+    /// The ascent rate of a segment is not negative, or its descent rate is not positive.
+    RateError,
+}
+
+/// A whole dive as a list of contiguous segments, starting at the surface. Each segment starts at
+/// the depth the previous one ended at, and depth changes take the time their rates allow.
+#[derive(Copy, Clone)]
+pub struct DiveProfile {
+    /// Number of segments in use.
+    len: usize,
+    /// Segments of the dive, in order.
+    segments: [DiveSegment; PROFILE_CAPACITY],
+}
+
+impl DiveProfile {
+    /// Returns an empty profile with the diver at the surface.
+    pub fn new() -> Self {
+        Self {
+            len: 0,
+            segments: [DiveSegment::constant(
+                SegmentType::DiveSegment,
+                0,
+                Duration::from_secs(0),
+                0,
+                0,
+            ); PROFILE_CAPACITY],
+        }
+    }
+
+    /// Append a segment to the profile. If a segment at a constant depth starts somewhere other than
+    /// the end of the profile, an `AscDesc` segment is inserted before it to change depth at its
+    /// rates.
+    /// # Arguments
+    /// * `segment` - Segment to append
+    /// # Errors
+    /// This function will return a [`DiveProfileError`] if any of the following are true:
+    /// * The rates of `segment` are not an ascent and a descent.
+    /// * `segment` is an `AscDesc` that does not start at the end of the profile, or whose duration
+    ///   does not match [`time_taken`] at its rate.
+    /// * There is no room for `segment` and any depth change inserted before it.
+    ///
+    /// On error, the profile is left unchanged.
+    pub fn push(&mut self, segment: DiveSegment) -> Result<(), DiveProfileError> {
+        if segment.ascent_rate() >= 0 || segment.descent_rate() <= 0 {
+            return Err(DiveProfileError::RateError);
+        }
+
+        let depth = self.depth();
+        if segment.segment_type() == SegmentType::AscDesc {
+            if segment.start_depth() != depth {
+                return Err(DiveProfileError::ContinuityError);
+            }
+            let expected = time_taken(
+                rate_between(&segment, segment.start_depth(), segment.end_depth()),
+                segment.start_depth(),
+                segment.end_depth(),
+            );
+            if segment.time().as_secs() != expected.as_secs() {
+                return Err(DiveProfileError::DurationError);
+            }
+            return self.append(&[segment]);
+        }
+
+        if segment.start_depth() == depth {
+            return self.append(&[segment]);
+        }
+
+        let rate = rate_between(&segment, depth, segment.start_depth());
+        let change = DiveSegment::new(
+            SegmentType::AscDesc,
+            depth,
+            segment.start_depth(),
+            time_taken(rate, depth, segment.start_depth()),
+            segment.ascent_rate(),
+            segment.descent_rate(),
+        )
+        .map_err(|_| DiveProfileError::ContinuityError)?;
+        self.append(&[change, segment])
+    }
+
+    /// Append segments to the profile only if all of them fit.
+    fn append(&mut self, segments: &[DiveSegment]) -> Result<(), DiveProfileError> {
+        if self.len + segments.len() > PROFILE_CAPACITY {
+            return Err(DiveProfileError::CapacityError);
+        }
+        for segment in segments {
+            self.segments[self.len] = *segment;
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// Remove every segment, leaving the diver at the surface.
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Returns the segments of the profile, in order.
+    pub fn segments(&self) -> &[DiveSegment] {
+        &self.segments[..self.len]
+    }
+
+    /// Returns the number of segments in the profile.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the profile has no segments.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the depth the profile ends at.
+    pub fn depth(&self) -> usize {
+        self.segments().last().map_or(0, |t| t.end_depth())
+    }
+
+    /// Returns the total duration of the profile.
+    pub fn runtime(&self) -> Duration {
+        self.segments()
+            .iter()
+            .fold(Duration::from_secs(0), |acc, t| acc + *t.time())
+    }
+
+    /// Returns the deepest depth reached in the profile.
+    pub fn max_depth(&self) -> usize {
+        self.segments()
+            .iter()
+            .map(|t| t.start_depth().max(t.end_depth()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the time-weighted average depth of the profile. Depth changes count at the mean of
+    /// their start and end depths. Zero if the profile takes no time.
+    pub fn average_depth(&self) -> Float {
+        let runtime = self.runtime().as_secs_f64() as Float;
+        if runtime <= 0.0 {
+            return 0.0;
+        }
+        let weighted: Float = self
+            .segments()
+            .iter()
+            .map(|t| {
+                let depth = (t.start_depth() + t.end_depth()) as Float / 2.0;
+                depth * t.time().as_secs_f64() as Float
+            })
+            .sum();
+        weighted / runtime
+    }
+}
+
+impl Default for DiveProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the rate of a segment used to change depth from `from` to `to`.
+fn rate_between(segment: &DiveSegment, from: usize, to: usize) -> isize {
+    if to < from {
+        segment.ascent_rate()
+    } else {
+        segment.descent_rate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a segment at a constant depth, with rates of 10 m min^-1 up and 20 m min^-1 down.
+    fn level(depth: usize, seconds: u64) -> DiveSegment {
+        DiveSegment::new(
+            SegmentType::DiveSegment,
+            depth,
+            depth,
+            Duration::from_secs(seconds),
+            -10,
+            20,
+        )
+        .unwrap()
+    }
+
+    /// Returns a depth change, with rates of 10 m min^-1 up and 20 m min^-1 down.
+    fn change(from: usize, to: usize, seconds: u64) -> DiveSegment {
+        DiveSegment::new(
+            SegmentType::AscDesc,
+            from,
+            to,
+            Duration::from_secs(seconds),
+            -10,
+            20,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn depth_changes_are_inserted_at_segment_rates() {
+        let mut profile = DiveProfile::new();
+        assert!(profile.is_empty());
+        profile.push(level(30, 1200)).unwrap();
+        profile.push(level(5, 180)).unwrap();
+        profile.push(change(5, 0, 30)).unwrap();
+
+        // 30 m at 20 m min^-1 takes 90 s, and 25 m at 10 m min^-1 takes 150 s.
+        let expected = [
+            (0, 30, 90),
+            (30, 30, 1200),
+            (30, 5, 150),
+            (5, 5, 180),
+            (5, 0, 30),
+        ];
+        assert_eq!(profile.len(), expected.len());
+        for (segment, &(start, end, seconds)) in profile.segments().iter().zip(expected.iter()) {
+            assert_eq!(segment.start_depth(), start);
+            assert_eq!(segment.end_depth(), end);
+            assert_eq!(segment.time().as_secs(), seconds);
+        }
+        assert_eq!(profile.depth(), 0);
+        assert_eq!(profile.runtime(), Duration::from_secs(1650));
+        assert_eq!(profile.max_depth(), 30);
+        // (15 * 90 + 30 * 1200 + 17.5 * 150 + 5 * 180 + 2.5 * 30) / 1650
+        assert!((profile.average_depth() - 24.818).abs() < 1e-3);
+    }
+
+    #[test]
+    fn empty_profile_is_at_the_surface() {
+        let profile = DiveProfile::default();
+        assert_eq!(profile.depth(), 0);
+        assert_eq!(profile.runtime(), Duration::from_secs(0));
+        assert_eq!(profile.max_depth(), 0);
+        assert_eq!(profile.average_depth(), 0.0);
+    }
+
+    #[test]
+    fn invalid_segments_leave_the_profile_unchanged() {
+        let mut profile = DiveProfile::new();
+        profile.push(change(0, 20, 60)).unwrap();
+
+        let wrong_rate = DiveSegment::new(
+            SegmentType::DiveSegment,
+            20,
+            20,
+            Duration::from_secs(60),
+            10,
+            20,
+        )
+        .unwrap();
+        assert_eq!(profile.push(wrong_rate), Err(DiveProfileError::RateError));
+        assert_eq!(
+            profile.push(change(10, 0, 60)),
+            Err(DiveProfileError::ContinuityError)
+        );
+        assert_eq!(
+            profile.push(change(20, 0, 60)),
+            Err(DiveProfileError::DurationError)
+        );
+        assert_eq!(profile.len(), 1);
+        assert_eq!(profile.depth(), 20);
+    }
+
+    #[test]
+    fn capacity_includes_inserted_depth_changes() {
+        let mut profile = DiveProfile::new();
+        for _ in 0..PROFILE_CAPACITY - 1 {
+            profile.push(level(0, 60)).unwrap();
+        }
+        // Reaching 10 m needs a depth change as well, which would not fit.
+        assert_eq!(
+            profile.push(level(10, 60)),
+            Err(DiveProfileError::CapacityError)
+        );
+        assert_eq!(profile.len(), PROFILE_CAPACITY - 1);
+        profile.push(level(0, 60)).unwrap();
+        assert_eq!(
+            profile.push(level(0, 60)),
+            Err(DiveProfileError::CapacityError)
+        );
+    }
+}
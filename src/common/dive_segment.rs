@@ -1,5 +1,6 @@
 use crate::common::dive_segment::DiveSegmentError::IncorrectSegmentTypeError;
 use crate::common::dive_segment::SegmentType::AscDesc;
+use crate::common::float::Float;
 use crate::common::mtr_bar;
 use core::result::Result;
 use core::time::Duration;
@@ -24,6 +25,8 @@ pub enum SegmentType {
     DiveSegment,
     /// Segment represents a change in depth.
     AscDesc,
+    /// Segment represents an optional deep stop below the first decompression stop.
+    DeepStop,
 }
 
 /// The atomic unit of a dive. Every dive can be represented by a list of DiveSegments.
@@ -85,6 +88,25 @@ impl DiveSegment {
         })
     }
 
+    /// Returns a new DiveSegment that stays at one depth. Unlike [`DiveSegment::new`] this cannot
+    /// fail, so it is used where the segment type is known not to be `AscDesc`.
+    pub(crate) fn constant(
+        segment_type: SegmentType,
+        depth: usize,
+        time: Duration,
+        ascent_rate: isize,
+        descent_rate: isize,
+    ) -> Self {
+        Self {
+            segment_type,
+            start_depth: depth,
+            end_depth: depth,
+            time,
+            ascent_rate,
+            descent_rate,
+        }
+    }
+
     /// Returns the type of the segment.
     pub fn segment_type(&self) -> SegmentType {
         self.segment_type
@@ -119,15 +141,15 @@ impl DiveSegment {
     /// # Arguments
     /// * `sac_rate` - Surface Air Consumption (SAC) rate (measured in bar min^-1).
     /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
-    pub fn gas_consumed(&self, sac_rate: usize, metres_per_bar: f64) -> usize {
+    pub fn gas_consumed(&self, sac_rate: usize, metres_per_bar: Float) -> usize {
         let pressure = match self.segment_type() {
             AscDesc => mtr_bar(
-                ((self.end_depth() + self.start_depth()) / 2) as f64,
+                ((self.end_depth() + self.start_depth()) / 2) as Float,
                 metres_per_bar,
             ),
-            _ => mtr_bar(self.end_depth() as f64, metres_per_bar),
+            _ => mtr_bar(self.end_depth() as Float, metres_per_bar),
         };
 
-        (pressure * (self.time().as_secs_f64() / 60.0) * sac_rate as f64) as usize
+        (pressure * (self.time().as_secs_f64() as Float / 60.0) * sac_rate as Float) as usize
     }
 }
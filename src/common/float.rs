@@ -0,0 +1,75 @@
+//! Floating point type used throughout the library. Double precision is used by default. Enabling the
+//! `single-precision` feature switches every calculation to `f32` instead, which targets with only a
+//! single precision FPU (such as the ESP32) can perform in hardware.
+//!
+//! Against double precision, single precision gives the same NDLs, first stops and number of stops
+//! on the reference profiles in the ZHL-16 tests, with the TTS within 5 seconds.
+
+/// Floating point type used by the library.
+#[cfg(not(feature = "single-precision"))]
+pub type Float = f64;
+
+/// Floating point type used by the library.
+#[cfg(feature = "single-precision")]
+pub type Float = f32;
+
+// The intrinsics are safe on newer compilers.
+#[cfg(not(feature = "single-precision"))]
+#[allow(unused_unsafe)]
+mod precision {
+    use super::Float;
+    use core::intrinsics;
+
+    pub use core::f64::consts;
+
+    /// Raises a number to a floating point power.
+    pub fn pow(x: Float, y: Float) -> Float {
+        unsafe { intrinsics::powf64(x, y) }
+    }
+
+    /// Returns the base 2 logarithm of a number.
+    pub fn log2(x: Float) -> Float {
+        unsafe { intrinsics::log2f64(x) }
+    }
+
+    /// Returns the smallest integer greater than or equal to a number.
+    pub fn ceil(x: Float) -> Float {
+        unsafe { intrinsics::ceilf64(x) }
+    }
+
+    /// Returns the largest integer less than or equal to a number.
+    pub fn floor(x: Float) -> Float {
+        unsafe { intrinsics::floorf64(x) }
+    }
+}
+
+#[cfg(feature = "single-precision")]
+#[allow(unused_unsafe)]
+mod precision {
+    use super::Float;
+    use core::intrinsics;
+
+    pub use core::f32::consts;
+
+    /// Raises a number to a floating point power.
+    pub fn pow(x: Float, y: Float) -> Float {
+        unsafe { intrinsics::powf32(x, y) }
+    }
+
+    /// Returns the base 2 logarithm of a number.
+    pub fn log2(x: Float) -> Float {
+        unsafe { intrinsics::log2f32(x) }
+    }
+
+    /// Returns the smallest integer greater than or equal to a number.
+    pub fn ceil(x: Float) -> Float {
+        unsafe { intrinsics::ceilf32(x) }
+    }
+
+    /// Returns the largest integer less than or equal to a number.
+    pub fn floor(x: Float) -> Float {
+        unsafe { intrinsics::floorf32(x) }
+    }
+}
+
+pub use precision::*;
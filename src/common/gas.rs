@@ -1,5 +1,13 @@
+use crate::common::float::Float;
 use crate::common::mtr_bar;
 
+/// Density of oxygen at 1 bar (measured in g L^-1).
+const DENSITY_O2: Float = 1.429;
+/// Density of helium at 1 bar (measured in g L^-1).
+const DENSITY_HE: Float = 0.179;
+/// Density of nitrogen at 1 bar (measured in g L^-1).
+const DENSITY_N2: Float = 1.251;
+
 /// Represents errors that occur while working with Gases.
 #[derive(Debug)]
 pub enum GasError {
@@ -39,7 +47,7 @@ impl Gas {
     /// # Errors
     /// This function will return a [`GasError`] if the percentage fractions do not add up to 100.
     pub fn new(o2: usize, he: usize, n2: usize) -> Result<Self, GasError> {
-        if o2 + he + n2 != 100 {
+        if o2.checked_add(he).and_then(|t| t.checked_add(n2)) != Some(100) {
             return Err(GasError::FractionError);
         }
 
@@ -47,18 +55,18 @@ impl Gas {
     }
 
     /// Returns the **fraction** of nitrogen in the mix.
-    pub fn fr_n2(&self) -> f64 {
-        self.n2 as f64 / 100.0
+    pub fn fr_n2(&self) -> Float {
+        self.n2 as Float / 100.0
     }
 
     /// Returns the **fraction** of oxygen in the mix.
-    pub fn fr_o2(&self) -> f64 {
-        self.o2 as f64 / 100.0
+    pub fn fr_o2(&self) -> Float {
+        self.o2 as Float / 100.0
     }
 
     /// Returns the **fraction** of helium in the mix.
-    pub fn fr_he(&self) -> f64 {
-        self.he as f64 / 100.0
+    pub fn fr_he(&self) -> Float {
+        self.he as Float / 100.0
     }
     /// Returns the percentage fraction of oxygen in the mix.
     pub fn o2(&self) -> usize {
@@ -75,11 +83,30 @@ impl Gas {
         self.n2
     }
 
-    /// Returns the Equivalent Narcotic Depth (END) of the mix at a given depth.
+    /// Returns the Equivalent Narcotic Depth (END) of the mix at a given depth, in water that takes
+    /// 10 m to induce 1 bar of pressure.
     /// # Arguments
     /// * `depth` - The depth the mix is being breathed at.
     pub fn equivalent_narcotic_depth(&self, depth: usize) -> usize {
-        (((depth + 10) as f64 * (1.0 - self.fr_he())) - 10.0) as usize
+        self.equivalent_narcotic_depth_with_metres_per_bar(depth, 10.0) as usize
+    }
+
+    /// Returns the Equivalent Narcotic Depth (END) of the mix at a given depth. Only helium is
+    /// treated as non-narcotic.
+    /// # Arguments
+    /// * `depth` - The depth the mix is being breathed at.
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
+    pub fn equivalent_narcotic_depth_with_metres_per_bar(
+        &self,
+        depth: usize,
+        metres_per_bar: Float,
+    ) -> Float {
+        let end = (depth as Float + metres_per_bar) * (1.0 - self.fr_he()) - metres_per_bar;
+        if end > 0.0 {
+            end
+        } else {
+            0.0
+        }
     }
 
     /// Helper function to check whether the mix is in an acceptable ppO2 range at a given depth.
@@ -87,7 +114,7 @@ impl Gas {
     /// * `depth` -Depth the mix is being breathed at.
     /// * `min` - Minimum tolerable ppO2.
     /// * `max` - Maximum tolerable ppO2.
-    pub fn in_ppo2_range(&self, depth: usize, min: f64, max: f64) -> bool {
+    pub fn in_ppo2_range(&self, depth: usize, min: Float, max: Float) -> bool {
         let ppo2 = self.pp_o2(depth, 10.0);
         ppo2 >= min && ppo2 <= max
     }
@@ -96,23 +123,32 @@ impl Gas {
     /// # Arguments
     /// * `depth` - Depth the mix is being breathed at.
     /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
-    pub fn pp_o2(&self, depth: usize, metres_per_bar: f64) -> f64 {
-        mtr_bar(depth as f64, metres_per_bar) * self.fr_o2()
+    pub fn pp_o2(&self, depth: usize, metres_per_bar: Float) -> Float {
+        mtr_bar(depth as Float, metres_per_bar) * self.fr_o2()
     }
 
     /// Returns the ppHe of the mix at a given depth.
     /// # Arguments
     /// * `depth` - Depth the mix is being breathed at.
     /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
-    pub fn pp_he(&self, depth: usize, metres_per_bar: f64) -> f64 {
-        mtr_bar(depth as f64, metres_per_bar) * self.fr_he()
+    pub fn pp_he(&self, depth: usize, metres_per_bar: Float) -> Float {
+        mtr_bar(depth as Float, metres_per_bar) * self.fr_he()
     }
 
     /// Returns the ppN2 of the mix at a given depth.
     /// # Arguments
     /// * `depth` - Depth the mix is being breathed at.
     /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
-    pub fn pp_n2(&self, depth: usize, metre_per_bar: f64) -> f64 {
-        mtr_bar(depth as f64, metre_per_bar) * self.fr_n2()
+    pub fn pp_n2(&self, depth: usize, metre_per_bar: Float) -> Float {
+        mtr_bar(depth as Float, metre_per_bar) * self.fr_n2()
+    }
+
+    /// Returns the density of the mix at a given depth (measured in g L^-1).
+    /// # Arguments
+    /// * `depth` - Depth the mix is being breathed at.
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
+    pub fn density(&self, depth: usize, metres_per_bar: Float) -> Float {
+        mtr_bar(depth as Float, metres_per_bar)
+            * (self.fr_o2() * DENSITY_O2 + self.fr_he() * DENSITY_HE + self.fr_n2() * DENSITY_N2)
     }
 }
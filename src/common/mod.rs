@@ -1,8 +1,8 @@
 //! Commonly used items for decompression models and dive planning
 
 // use num_traits::cast::FromPrimitive;
-use core::isize;
 use core::time::Duration;
+use crate::common::float::pow;
 
 /// A default, placeholder descent rate (measured in m min^-1).
 pub const DEFAULT_DESCENT_RATE: isize = 30;
@@ -11,30 +11,47 @@ pub const DEFAULT_DESCENT_RATE: isize = 30;
 pub const DEFAULT_ASCENT_RATE: isize = -18;
 
 /// Density of fresh water (measured in kg m^-3).
-pub const DENSITY_FRESHWATER: f64 = 997.0;
+pub const DENSITY_FRESHWATER: Float = 997.0;
 
 /// Average density of salt water (measured in kg m^-3).
-pub const DENSITY_SALTWATER: f64 = 1023.6;
+pub const DENSITY_SALTWATER: Float = 1023.6;
 
+pub mod ascent;
+pub mod cns;
+pub mod dive_profile;
 pub mod dive_segment;
+pub mod float;
 pub mod gas;
 pub mod otu;
 pub mod tank;
+pub mod water;
+
+pub use ascent::AscentRates;
+pub use ascent::AscentRatesError;
+pub use ascent::ASCENT_BAND_CAPACITY;
+
+pub use dive_profile::DiveProfile;
+pub use dive_profile::DiveProfileError;
+pub use dive_profile::PROFILE_CAPACITY;
 
 pub use dive_segment::DiveSegment;
 pub use dive_segment::DiveSegmentError;
 pub use dive_segment::SegmentType;
 
+pub use float::Float;
+
 pub use gas::Gas;
 pub use gas::GasError;
 
 pub use tank::Tank;
 
+pub use water::WaterType;
+
 /// Helper function to convert pressure to the equivalent depth of water that would induce it.
 /// # Arguments
 /// * `bar` - Pressure measured in bars
 /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
-pub fn bar_mtr(bar: f64, metres_per_bar: f64) -> f64 {
+pub fn bar_mtr(bar: Float, metres_per_bar: Float) -> Float {
     (bar - 1.0) * metres_per_bar
 }
 
@@ -42,10 +59,18 @@ pub fn bar_mtr(bar: f64, metres_per_bar: f64) -> f64 {
 /// # Arguments
 /// * `mtr` - Depth of water.
 /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure.
-pub fn mtr_bar(mtr: f64, metres_per_bar: f64) -> f64 {
+pub fn mtr_bar(mtr: Float, metres_per_bar: Float) -> Float {
     (mtr / metres_per_bar) + 1.0
 }
 
+/// Helper function to calculate the atmospheric pressure at an altitude with the barometric
+/// formula. The pressure is relative to the 1 bar the library takes for sea level.
+/// # Arguments
+/// * `altitude` - Altitude above sea level (measured in m)
+pub fn altitude_pressure(altitude: usize) -> Float {
+    pow(1.0 - 2.25577e-5 * altitude as Float, 5.25588)
+}
+
 /// Helper function to calculate the time taken to change depths, given a rate.
 /// # Arguments
 /// * `rate` - Rate of depth change
@@ -54,7 +79,10 @@ pub fn mtr_bar(mtr: f64, metres_per_bar: f64) -> f64 {
 /// # Panics
 /// Function will panic if the time taken exceeds [`i64::MAX`].
 pub fn time_taken(rate: isize, depth_1: usize, depth_2: usize) -> Duration {
-    let delta_depth = ((depth_1 as isize) - (depth_2 as isize)).abs();
-    let rate_seconds = rate.abs() as f64 / 60.0;
-    Duration::from_secs((delta_depth as f64 / rate_seconds) as u64)
+    // Whole numbers keep this exact in single precision, where 30m at 9m/min would take 199.99s.
+    let delta_depth = (core::cmp::max(depth_1, depth_2) - core::cmp::min(depth_1, depth_2)) as u64;
+    // Negating isize::MIN wraps back to itself, which is still the right magnitude as a u64.
+    let speed = if rate < 0 { rate.wrapping_neg() } else { rate } as u64;
+    let seconds = (delta_depth * 60).checked_div(speed);
+    Duration::from_secs(seconds.unwrap_or(u64::MAX))
 }
@@ -1,35 +1,158 @@
 use crate::common::dive_segment::{DiveSegment, SegmentType};
 use crate::common::gas::Gas;
-use core::intrinsics::powf64;
+use crate::common::float::{pow, Float};
+use core::time::Duration;
+
+/// ppO2 below which no oxygen toxicity is accumulated (measured in bar).
+const OTU_THRESHOLD: Float = 0.5;
+
+/// Returns the Oxygen Toxicity Units (OTU) accumulated during a segment with a specified gas, in
+/// water that takes 10 m to induce 1 bar of pressure.
+/// # Arguments
+/// * `segment` - Segment to calculate OTUs for
+/// * `gas` - Gas used in that segment
+pub fn otu(segment: &DiveSegment, gas: &Gas) -> Float {
+    otu_with_metres_per_bar(segment, gas, 10.0)
+}
 
 /// Returns the Oxygen Toxicity Units (OTU) accumulated during a segment with a specified gas.
 /// # Arguments
 /// * `segment` - Segment to calculate OTUs for
 /// * `gas` - Gas used in that segment
-pub fn otu(segment: &DiveSegment, gas: &Gas) -> f64 {
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+pub fn otu_with_metres_per_bar(segment: &DiveSegment, gas: &Gas, metres_per_bar: Float) -> Float {
     match segment.segment_type() {
         SegmentType::AscDesc => ascent_descent_constant(
-            segment.time().as_secs() as usize,
-            gas.pp_o2(segment.start_depth(), 10.0),
-            gas.pp_o2(segment.end_depth(), 10.0),
+            *segment.time(),
+            gas.pp_o2(segment.start_depth(), metres_per_bar),
+            gas.pp_o2(segment.end_depth(), metres_per_bar),
         ),
         _ => constant_depth(
-            segment.time().as_secs() as usize,
-            gas.pp_o2(segment.start_depth(), 10.0),
+            *segment.time(),
+            gas.pp_o2(segment.start_depth(), metres_per_bar),
         ),
     }
 }
 
 /// OTU in constant depth
-pub fn constant_depth(time: usize, p_o2: f64) -> f64 {
-    // (time as f64) * (0.5 / (p_o2 - 0.5)).powf(-5.0 / 6.0)
-    unsafe { powf64(time as f64 * (0.5 / (p_o2 - 0.5)), -5.0 / 6.0) }
+/// # Arguments
+/// * `time` - Time spent at the depth
+/// * `p_o2` - ppO2 breathed at the depth
+pub fn constant_depth(time: Duration, p_o2: Float) -> Float {
+    if p_o2 <= OTU_THRESHOLD {
+        return 0.0;
+    }
+    minutes(time) * pow((p_o2 - OTU_THRESHOLD) / OTU_THRESHOLD, 5.0 / 6.0)
 }
 
 /// OTU in changing depth (constant a/descent rate)
-fn ascent_descent_constant(time: usize, p_o2i: f64, p_o2f: f64) -> f64 {
-    unsafe {
-        ((3.0 / 11.0) * (time as f64) / (p_o2f - p_o2i)) * powf64((p_o2f - 0.5) / 0.5, 11.0 / 6.0)
-            - powf64((p_o2i - 0.5) / 0.5, 11.0 / 6.0)
+fn ascent_descent_constant(time: Duration, p_o2i: Float, p_o2f: Float) -> Float {
+    if p_o2i == p_o2f {
+        return constant_depth(time, p_o2i);
+    }
+
+    // Only the part of the segment spent above the threshold counts.
+    let (low, high) = if p_o2i < p_o2f { (p_o2i, p_o2f) } else { (p_o2f, p_o2i) };
+    if high <= OTU_THRESHOLD {
+        return 0.0;
+    }
+    let low_clamped = if low < OTU_THRESHOLD { OTU_THRESHOLD } else { low };
+    let t = minutes(time) * (high - low_clamped) / (high - low);
+
+    (3.0 / 11.0) * t / (high - low_clamped)
+        * (pow((high - OTU_THRESHOLD) / OTU_THRESHOLD, 11.0 / 6.0)
+            - pow((low_clamped - OTU_THRESHOLD) / OTU_THRESHOLD, 11.0 / 6.0))
+}
+
+/// Convert a duration to minutes.
+fn minutes(time: Duration) -> Float {
+    time.as_secs_f64() as Float / 60.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas;
+
+    /// Returns the OTUs of an ascent or descent by summing many short constant depth slices.
+    fn integrate(minutes: u64, p_o2i: Float, p_o2f: Float) -> Float {
+        let slices = 10_000;
+        let slice = Duration::from_secs(minutes * 60) / slices;
+        (0..slices)
+            .map(|t| {
+                let p_o2 = p_o2i + (p_o2f - p_o2i) * (t as Float + 0.5) / slices as Float;
+                constant_depth(slice, p_o2)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn constant_depth_matches_noaa_table() {
+        // OTUs per minute from the NOAA Diving Manual.
+        for &(p_o2, otu) in [
+            (0.5, 0.0),
+            (0.8, 0.65),
+            (1.0, 1.0),
+            (1.2, 1.32),
+            (1.4, 1.63),
+            (1.6, 1.92),
+        ]
+        .iter()
+        {
+            let per_minute = constant_depth(Duration::from_secs(60), p_o2);
+            assert!((per_minute - otu).abs() < 0.01, "{}: {}", p_o2, per_minute);
+        }
+        assert_eq!(constant_depth(Duration::from_secs(3600), 0.21), 0.0);
+        let hour = constant_depth(Duration::from_secs(3600), 1.4);
+        assert!((hour - 97.9).abs() < 0.1);
+    }
+
+    #[test]
+    fn depth_change_matches_integral() {
+        for &(minutes, p_o2i, p_o2f) in [
+            (5, 0.6, 1.6),
+            (5, 1.6, 0.6),
+            (10, 0.21, 1.4),
+            (3, 0.3, 0.45),
+        ]
+        .iter()
+        {
+            let otu = ascent_descent_constant(Duration::from_secs(minutes * 60), p_o2i, p_o2f);
+            let expected = integrate(minutes, p_o2i, p_o2f);
+            assert!(
+                (otu - expected).abs() < 0.01,
+                "{} -> {}: {} {}",
+                p_o2i,
+                p_o2f,
+                otu,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn segments_use_their_depths() {
+        let oxygen = gas!(100, 0);
+        let stop = DiveSegment::new(
+            SegmentType::DecoStop,
+            6,
+            6,
+            Duration::from_secs(600),
+            -9,
+            18,
+        )
+        .unwrap();
+        assert!((otu(&stop, &oxygen) - 10.0 * pow(2.2, 5.0 / 6.0)).abs() < 0.01);
+
+        let ascent =
+            DiveSegment::new(SegmentType::AscDesc, 6, 0, Duration::from_secs(60), -6, 18).unwrap();
+        let expected = integrate(1, 1.6, 1.0);
+        assert!((otu(&ascent, &oxygen) - expected).abs() < 0.01);
+
+        // At 20 m per bar, the stop is at 1.3 bar instead.
+        let otu_fresh = otu_with_metres_per_bar(&stop, &oxygen, 20.0);
+        assert!((otu_fresh - 10.0 * pow(1.6, 5.0 / 6.0)).abs() < 0.01);
+        let expected = integrate(1, 1.3, 1.0);
+        assert!((otu_with_metres_per_bar(&ascent, &oxygen, 20.0) - expected).abs() < 0.01);
     }
 }
@@ -25,4 +25,24 @@ impl Tank {
             service_pressure,
         }
     }
+
+    /// Returns the gas mix inside the tank.
+    pub fn gas(&self) -> &Gas {
+        &self.gas
+    }
+
+    /// Returns the physical volume inside the tank.
+    pub fn raw_volume(&self) -> usize {
+        self.raw_volume
+    }
+
+    /// Returns the manufacturer specified service pressure of the tank.
+    pub fn service_pressure(&self) -> usize {
+        self.service_pressure
+    }
+
+    /// Returns the volume of gas the tank holds when filled to its service pressure.
+    pub fn capacity(&self) -> usize {
+        self.raw_volume * self.service_pressure
+    }
 }
@@ -0,0 +1,34 @@
+use crate::common::float::Float;
+use crate::common::{DENSITY_FRESHWATER, DENSITY_SALTWATER};
+
+/// Standard gravity (measured in m s^-2).
+const GRAVITY: Float = 9.80665;
+
+/// Pascals in a bar.
+const PASCALS_PER_BAR: Float = 100_000.0;
+
+/// Types of water a dive can take place in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum WaterType {
+    /// Fresh water, such as lakes and quarries.
+    Fresh,
+    /// Sea water.
+    Salt,
+}
+
+impl WaterType {
+    /// Returns the density of the water (measured in kg m^-3).
+    pub fn density(&self) -> Float {
+        match self {
+            WaterType::Fresh => DENSITY_FRESHWATER,
+            WaterType::Salt => DENSITY_SALTWATER,
+        }
+    }
+
+    /// Returns the depth of the water required to induce 1 bar of pressure.
+    pub fn metres_per_bar(&self) -> Float {
+        PASCALS_PER_BAR / (self.density() * GRAVITY)
+    }
+}
@@ -1,11 +1,16 @@
 //! Decompression models
 
+use crate::common::float::Float;
+
+pub mod settings;
 pub mod tissue;
 pub mod zhl16;
 
+pub use settings::StopSettings;
+pub use settings::StopSettingsError;
 pub use tissue::Tissue;
 
 /// Number of tissues in a typical decompression algorithm.
 pub const TISSUE_COUNT: usize = 16;
 /// Pressure of water vapour. (measured in bar)
-pub const WATER_VAPOUR_PRESSURE: f64 = 0.06257;
+pub const WATER_VAPOUR_PRESSURE: Float = 0.06257;
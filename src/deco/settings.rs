@@ -0,0 +1,197 @@
+use core::time::Duration;
+use crate::common::float::{ceil, Float};
+
+/// Represents errors that occur while working with StopSettings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopSettingsError {
+    /// The interval between two consecutive stops is zero.
+    IntervalError,
+    /// The depth of the last stop is zero.
+    LastStopError,
+    /// The resolution of stop times is less than a second.
+    ResolutionError,
+}
+
+/// Settings controlling where decompression stops are placed and how long they last. Depths are
+/// whole metres.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct StopSettings {
+    /// Depth between two consecutive stops (measured in m).
+    stop_interval: usize,
+    /// Depth of the last stop before surfacing (measured in m).
+    last_stop_depth: usize,
+    /// Minimum duration of a stop (measured in seconds).
+    min_stop_time: u64,
+    /// Stop times are rounded up to a multiple of this duration (measured in seconds).
+    stop_resolution: u64,
+}
+
+impl StopSettings {
+    /// Returns a new StopSettings with the given parameters.
+    /// # Arguments
+    /// * `stop_interval` - Depth between two consecutive stops in whole metres (e.g. 3m or 1m).
+    /// * `last_stop_depth` - Depth of the last stop before surfacing in whole metres (e.g. 3m or 6m).
+    /// * `min_stop_time` - Minimum duration of a stop.
+    /// * `stop_resolution` - Stop times are rounded up to a multiple of this duration (e.g. 1 minute, 1 second).
+    /// # Errors
+    /// This function will return a [`StopSettingsError`] if `stop_interval`, `last_stop_depth` or
+    /// `stop_resolution` is zero.
+    pub fn new(
+        stop_interval: usize,
+        last_stop_depth: usize,
+        min_stop_time: Duration,
+        stop_resolution: Duration,
+    ) -> Result<Self, StopSettingsError> {
+        if stop_interval == 0 {
+            return Err(StopSettingsError::IntervalError);
+        }
+        if last_stop_depth == 0 {
+            return Err(StopSettingsError::LastStopError);
+        }
+        if stop_resolution.as_secs() == 0 {
+            return Err(StopSettingsError::ResolutionError);
+        }
+
+        Ok(Self {
+            stop_interval,
+            last_stop_depth,
+            min_stop_time: min_stop_time.as_secs(),
+            stop_resolution: stop_resolution.as_secs(),
+        })
+    }
+
+    /// Returns the depth between two consecutive stops.
+    pub fn stop_interval(&self) -> usize {
+        self.stop_interval
+    }
+
+    /// Returns the depth of the last stop before surfacing.
+    pub fn last_stop_depth(&self) -> usize {
+        self.last_stop_depth
+    }
+
+    /// Returns the minimum duration of a stop.
+    pub fn min_stop_time(&self) -> Duration {
+        Duration::from_secs(self.min_stop_time)
+    }
+
+    /// Returns the duration stop times are rounded up to.
+    pub fn stop_resolution(&self) -> Duration {
+        Duration::from_secs(self.stop_resolution)
+    }
+
+    /// Returns the stop depth required to stay below a given ceiling. The ceiling is rounded up
+    /// to the next stop interval, and any stop shallower than the last stop is moved to the last stop.
+    /// # Arguments
+    /// * `ceiling` - Ascent ceiling (measured in m).
+    pub fn stop_depth(&self, ceiling: Float) -> usize {
+        let interval = self.stop_interval as Float;
+        let depth = (interval * ceil(ceiling / interval)) as usize;
+        if depth > 0 && depth < self.last_stop_depth {
+            return self.last_stop_depth;
+        }
+        depth
+    }
+
+    /// Returns the stop following a stop at a given depth. Once the diver is at or above the last
+    /// stop, the next stop is the surface.
+    /// # Arguments
+    /// * `depth` - Depth of the current stop.
+    pub fn next_stop_depth(&self, depth: usize) -> usize {
+        if depth <= self.last_stop_depth {
+            return 0;
+        }
+        core::cmp::max(depth.saturating_sub(self.stop_interval), self.last_stop_depth)
+    }
+}
+
+impl Default for StopSettings {
+    /// Stops every 3m, with the last stop at 3m and stop times in whole minutes.
+    fn default() -> Self {
+        Self {
+            stop_interval: 3,
+            last_stop_depth: 3,
+            min_stop_time: 60,
+            stop_resolution: 60,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(interval: usize, last_stop: usize) -> StopSettings {
+        StopSettings::new(
+            interval,
+            last_stop,
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_zero_settings() {
+        let minute = Duration::from_secs(60);
+        let zero = Duration::from_secs(0);
+        assert_eq!(
+            StopSettings::new(0, 3, minute, minute),
+            Err(StopSettingsError::IntervalError)
+        );
+        assert_eq!(
+            StopSettings::new(3, 0, minute, minute),
+            Err(StopSettingsError::LastStopError)
+        );
+        assert_eq!(
+            StopSettings::new(3, 3, minute, zero),
+            Err(StopSettingsError::ResolutionError)
+        );
+        assert!(StopSettings::new(3, 3, zero, minute).is_ok());
+    }
+
+    #[test]
+    fn default_is_three_metres_in_whole_minutes() {
+        let default = StopSettings::default();
+        assert_eq!(default, settings(3, 3));
+        assert_eq!(default.min_stop_time(), Duration::from_secs(60));
+        assert_eq!(default.stop_resolution(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn stop_depth_rounds_up_to_interval() {
+        let three = settings(3, 3);
+        assert_eq!(three.stop_depth(0.0), 0);
+        assert_eq!(three.stop_depth(0.1), 3);
+        assert_eq!(three.stop_depth(3.0), 3);
+        assert_eq!(three.stop_depth(7.2), 9);
+        assert_eq!(three.stop_depth(21.0), 21);
+
+        let one = settings(1, 3);
+        assert_eq!(one.stop_depth(7.2), 8);
+        // Ceilings shallower than the last stop are held at the last stop.
+        assert_eq!(one.stop_depth(0.5), 3);
+    }
+
+    #[test]
+    fn six_metre_last_stop_absorbs_three_metre_stop() {
+        let six = settings(3, 6);
+        assert_eq!(six.stop_depth(2.0), 6);
+        assert_eq!(six.stop_depth(4.0), 6);
+        assert_eq!(six.stop_depth(6.5), 9);
+        assert_eq!(six.next_stop_depth(12), 9);
+        assert_eq!(six.next_stop_depth(9), 6);
+        assert_eq!(six.next_stop_depth(6), 0);
+    }
+
+    #[test]
+    fn next_stop_depth_does_not_skip_last_stop() {
+        let four = settings(4, 3);
+        assert_eq!(four.next_stop_depth(7), 3);
+        assert_eq!(four.next_stop_depth(5), 3);
+        assert_eq!(four.next_stop_depth(3), 0);
+        assert_eq!(four.next_stop_depth(2), 0);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::common::float::Float;
 use crate::common::gas::Gas;
 use crate::deco::{TISSUE_COUNT, WATER_VAPOUR_PRESSURE};
 use crate::gas;
@@ -9,31 +10,38 @@ use crate::gas;
 #[repr(C)]
 pub struct Tissue {
     // Tissue states
-    pub(crate) p_n2: [f64; TISSUE_COUNT],
-    pub(crate) p_he: [f64; TISSUE_COUNT],
-    pub(crate) p_t: [f64; TISSUE_COUNT],
+    pub(crate) p_n2: [Float; TISSUE_COUNT],
+    pub(crate) p_he: [Float; TISSUE_COUNT],
+    pub(crate) p_t: [Float; TISSUE_COUNT],
 }
 
 impl Tissue {
     /// Returns a new Tissue with the given parameters.
     /// # Arguments
     /// * `p_n2` - Set of tissues for nitrogen
-    /// * `p_he` - Set of tissues for nitrogen
+    /// * `p_he` - Set of tissues for helium
     /// * `p_t` - Total pressure of all tissues
     pub fn new(
-        p_n2: [f64; TISSUE_COUNT],
-        p_he: [f64; TISSUE_COUNT],
-        p_t: [f64; TISSUE_COUNT],
+        p_n2: [Float; TISSUE_COUNT],
+        p_he: [Float; TISSUE_COUNT],
+        p_t: [Float; TISSUE_COUNT],
     ) -> Self {
         Self { p_n2, p_he, p_t }
     }
 
-    pub fn p_n2(&self) -> [f64; TISSUE_COUNT] {
+    /// Returns the nitrogen pressure of each tissue (measured in bar).
+    pub fn p_n2(&self) -> [Float; TISSUE_COUNT] {
         self.p_n2
     }
 
-    pub fn p_he(&self) -> [f64; TISSUE_COUNT] {
-        self.p_n2
+    /// Returns the helium pressure of each tissue (measured in bar).
+    pub fn p_he(&self) -> [Float; TISSUE_COUNT] {
+        self.p_he
+    }
+
+    /// Returns the total inert gas pressure of each tissue (measured in bar).
+    pub fn p_t(&self) -> [Float; TISSUE_COUNT] {
+        self.p_t
     }
 }
 
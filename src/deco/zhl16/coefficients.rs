@@ -0,0 +1,141 @@
+use crate::common::float::Float;
+use crate::deco::zhl16::util::{
+    ZHL16B_HE_A, ZHL16B_HE_B, ZHL16B_HE_HALFLIFE, ZHL16B_N2_A, ZHL16B_N2_B, ZHL16B_N2_HALFLIFE,
+    ZHL16C_HE_A, ZHL16C_HE_B, ZHL16C_HE_HALFLIFE, ZHL16C_N2_A, ZHL16C_N2_B, ZHL16C_N2_HALFLIFE,
+};
+use crate::deco::TISSUE_COUNT;
+
+/// Tissue loading constants of a ZHL-16 model. Models only hold a reference to a shared, static set
+/// of these, so copying a model (or simulating ahead on a virtual one) never copies the tables.
+#[derive(Debug, PartialEq)]
+#[repr(C)]
+pub struct ZHL16Coefficients {
+    /// Nitrogen A-values.
+    pub n2_a: [Float; TISSUE_COUNT],
+    /// Nitrogen B-values.
+    pub n2_b: [Float; TISSUE_COUNT],
+    /// Nitrogen half-lives.
+    pub n2_hl: [Float; TISSUE_COUNT],
+    /// Helium A-values.
+    pub he_a: [Float; TISSUE_COUNT],
+    /// Helium B-values.
+    pub he_b: [Float; TISSUE_COUNT],
+    /// Helium half-lives.
+    pub he_hl: [Float; TISSUE_COUNT],
+}
+
+/// Tissue loading constants of the ZHL-16B deco algorithm.
+pub static ZHL16B_COEFFICIENTS: ZHL16Coefficients = ZHL16Coefficients {
+    n2_a: ZHL16B_N2_A,
+    n2_b: ZHL16B_N2_B,
+    n2_hl: ZHL16B_N2_HALFLIFE,
+    he_a: ZHL16B_HE_A,
+    he_b: ZHL16B_HE_B,
+    he_hl: ZHL16B_HE_HALFLIFE,
+};
+
+/// Tissue loading constants of the ZHL-16C deco algorithm.
+pub static ZHL16C_COEFFICIENTS: ZHL16Coefficients = ZHL16Coefficients {
+    n2_a: ZHL16C_N2_A,
+    n2_b: ZHL16C_N2_B,
+    n2_hl: ZHL16C_N2_HALFLIFE,
+    he_a: ZHL16C_HE_A,
+    he_b: ZHL16C_HE_B,
+    he_hl: ZHL16C_HE_HALFLIFE,
+};
+
+/// Serializes a reference to static coefficients as the [`Variant`](super::Variant) they belong to.
+/// Custom coefficients cannot be serialized, since they could not be referenced again when deserializing.
+#[cfg(feature = "use-serde")]
+pub(crate) mod serde_variant {
+    use super::ZHL16Coefficients;
+    use crate::deco::zhl16::Variant;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        coefficients: &&'static ZHL16Coefficients,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match Variant::of(coefficients) {
+            Some(variant) => variant.serialize(serializer),
+            None => Err(serde::ser::Error::custom(
+                "custom coefficients cannot be serialized",
+            )),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'static ZHL16Coefficients, D::Error> {
+        Variant::deserialize(deserializer).map(|variant| variant.coefficients())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::dive_segment::{DiveSegment, SegmentType};
+    use crate::common::gas::Gas;
+    use crate::deco::tissue::Tissue;
+    use crate::deco::zhl16::{Variant, ZHL16};
+    use crate::gas;
+    use core::time::Duration;
+
+    /// ZHL-16C with a more conservative A-value in every nitrogen compartment.
+    static CONSERVATIVE: ZHL16Coefficients = ZHL16Coefficients {
+        n2_a: [
+            1.1, 0.9, 0.8, 0.7, 0.55, 0.45, 0.4, 0.35, 0.33, 0.3, 0.28, 0.26, 0.24, 0.22, 0.21, 0.2,
+        ],
+        n2_b: ZHL16C_N2_B,
+        n2_hl: ZHL16C_N2_HALFLIFE,
+        he_a: ZHL16C_HE_A,
+        he_b: ZHL16C_HE_B,
+        he_hl: ZHL16C_HE_HALFLIFE,
+    };
+
+    /// Returns the NDL (measured in minutes) at 15m on air for a set of coefficients.
+    fn ndl(coefficients: &'static ZHL16Coefficients) -> u64 {
+        let air = gas!(21, 0);
+        let mut deco = ZHL16::new_by_variant(Tissue::default(), 100, 100, Variant::C);
+        deco.set_coefficients(coefficients);
+        let segment = DiveSegment::constant(
+            SegmentType::DiveSegment,
+            15,
+            Duration::from_secs(5 * 60),
+            -10,
+            20,
+        );
+        deco.add_segment(&segment, &air, 10.0);
+        deco.ndl(&air, 10.0).unwrap().time().as_secs() / 60
+    }
+
+    #[test]
+    fn models_share_the_static_tables() {
+        let deco = ZHL16::new_by_variant(Tissue::default(), 30, 70, Variant::B);
+        let copy = deco;
+        assert!(core::ptr::eq(copy.coefficients(), &ZHL16B_COEFFICIENTS));
+        assert!(core::ptr::eq(copy.coefficients(), deco.coefficients()));
+    }
+
+    #[test]
+    fn variants_round_trip_through_their_tables() {
+        for variant in [Variant::B, Variant::C].iter() {
+            let coefficients = variant.coefficients();
+            assert!(core::ptr::eq(
+                Variant::of(coefficients).unwrap().coefficients(),
+                coefficients
+            ));
+        }
+        // A table that is not one of the library's own has no variant.
+        assert!(Variant::of(&CONSERVATIVE).is_none());
+        assert_ne!(ZHL16B_COEFFICIENTS, ZHL16C_COEFFICIENTS);
+    }
+
+    #[test]
+    fn custom_tables_change_the_model() {
+        // ZHL-16C lowered the A-values of the middle compartments of ZHL-16B, which control shallow
+        // dives.
+        assert!(ndl(&ZHL16B_COEFFICIENTS) > ndl(&ZHL16C_COEFFICIENTS));
+        assert!(ndl(&CONSERVATIVE) < ndl(&ZHL16C_COEFFICIENTS));
+    }
+}
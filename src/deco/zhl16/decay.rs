@@ -0,0 +1,197 @@
+use crate::common::float::consts::LN_2;
+use crate::common::float::{pow, Float};
+use crate::deco::TISSUE_COUNT;
+use core::time::Duration;
+
+/// Exponential decay factors of every compartment over a fixed tick duration, along with the time
+/// constants (`1/k` in the Schreiner equation) of every compartment. Loading a compartment over a
+/// tick of that duration then only needs a few multiply-adds instead of an exponential, and a depth
+/// change of any duration needs no divisions by the half-lives.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct DecayCache {
+    /// Duration the factors were calculated for (milliseconds). Zero if nothing is cached.
+    tick: u64,
+    /// Nitrogen decay factors.
+    n2: [Float; TISSUE_COUNT],
+    /// Helium decay factors.
+    he: [Float; TISSUE_COUNT],
+    /// Nitrogen time constants (measured in minutes).
+    n2_time_constant: [Float; TISSUE_COUNT],
+    /// Helium time constants (measured in minutes).
+    he_time_constant: [Float; TISSUE_COUNT],
+}
+
+impl DecayCache {
+    /// Returns a cache holding no factors.
+    pub(crate) fn empty() -> Self {
+        Self {
+            tick: 0,
+            n2: [0.0; TISSUE_COUNT],
+            he: [0.0; TISSUE_COUNT],
+            n2_time_constant: [0.0; TISSUE_COUNT],
+            he_time_constant: [0.0; TISSUE_COUNT],
+        }
+    }
+
+    /// Returns a cache holding the factors for a tick duration, and the time constants of the
+    /// half-lives. The cache must be rebuilt whenever the half-lives change.
+    /// # Arguments
+    /// * `tick` - Duration of a tick
+    /// * `n2_hl` - Nitrogen half-lives
+    /// * `he_hl` - Helium half-lives
+    pub(crate) fn new(
+        tick: Duration,
+        n2_hl: &[Float; TISSUE_COUNT],
+        he_hl: &[Float; TISSUE_COUNT],
+    ) -> Self {
+        let t = tick.as_secs_f64() as Float / 60.0;
+        let mut cache = Self::empty();
+        cache.tick = tick.as_millis() as u64;
+        for idx in 0..TISSUE_COUNT {
+            cache.n2[idx] = decay_factor(t, n2_hl[idx]);
+            cache.he[idx] = decay_factor(t, he_hl[idx]);
+            cache.n2_time_constant[idx] = time_constant(n2_hl[idx]);
+            cache.he_time_constant[idx] = time_constant(he_hl[idx]);
+        }
+        cache
+    }
+
+    /// Returns the tick duration the factors were calculated for.
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick)
+    }
+
+    /// Returns whether the cache holds the factors for a duration.
+    pub(crate) fn matches(&self, time: &Duration) -> bool {
+        self.tick != 0 && self.tick == time.as_millis() as u64
+    }
+
+    /// Returns the nitrogen decay factor of a compartment.
+    pub(crate) fn n2(&self, x: usize) -> Float {
+        self.n2[x]
+    }
+
+    /// Returns the helium decay factor of a compartment.
+    pub(crate) fn he(&self, x: usize) -> Float {
+        self.he[x]
+    }
+
+    /// Returns whether the cache holds the time constants of the half-lives it was built for.
+    pub(crate) fn has_time_constants(&self) -> bool {
+        self.tick != 0
+    }
+
+    /// Returns the nitrogen time constant of a compartment.
+    pub(crate) fn n2_time_constant(&self, x: usize) -> Float {
+        self.n2_time_constant[x]
+    }
+
+    /// Returns the helium time constant of a compartment.
+    pub(crate) fn he_time_constant(&self, x: usize) -> Float {
+        self.he_time_constant[x]
+    }
+}
+
+/// Returns the fraction of the difference between tissue and inspired pressure that remains after
+/// some time, i.e. `2^(-t / half-life)`, which is also `e^(-kt)` in the Schreiner equation.
+/// # Arguments
+/// * `time` - Elapsed time (measured in minutes)
+/// * `half_life` - Half-life of the compartment (measured in minutes)
+pub(crate) fn decay_factor(time: Float, half_life: Float) -> Float {
+    pow(2.0, -time / half_life)
+}
+
+/// Returns the time constant of a compartment, i.e. `1/k` in the Schreiner equation where
+/// `k = ln(2) / half-life`.
+/// # Arguments
+/// * `half_life` - Half-life of the compartment (measured in minutes)
+pub(crate) fn time_constant(half_life: Float) -> Float {
+    half_life / LN_2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::dive_segment::{DiveSegment, SegmentType};
+    use crate::common::gas::Gas;
+    use crate::deco::tissue::Tissue;
+    use crate::deco::zhl16::variant::Variant;
+    use crate::deco::zhl16::{ZHL16, ZHL16B_COEFFICIENTS, ZHL16C_COEFFICIENTS};
+    use crate::gas;
+
+    /// Load a model with a descent, a bottom segment and an ascent made of 6 second ticks.
+    fn load_ticks(deco: &mut ZHL16) {
+        let trimix = gas!(21, 35);
+        let tick = Duration::from_secs(6);
+        // Descend at 20m/min, stay for 20 minutes, then ascend at 10m/min.
+        for &(count, step) in [(20, 2), (200, 0), (30, -1)].iter() {
+            for _ in 0..count {
+                let depth = deco.depth();
+                let next = (depth as isize + step) as usize;
+                let segment_type = if step == 0 {
+                    SegmentType::DiveSegment
+                } else {
+                    SegmentType::AscDesc
+                };
+                let segment = DiveSegment::new(segment_type, depth, next, tick, -10, 20).unwrap();
+                deco.add_segment(&segment, &trimix, 10.0);
+            }
+        }
+    }
+
+    /// Assert that two models have the same tissue, to within rounding.
+    fn assert_same_tissue(a: &ZHL16, b: &ZHL16) {
+        let (a, b) = (a.tissue(), b.tissue());
+        for idx in 0..TISSUE_COUNT {
+            assert!((a.p_n2[idx] - b.p_n2[idx]).abs() < 1e-4);
+            assert!((a.p_he[idx] - b.p_he[idx]).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn factors_match_the_exponential() {
+        let cache = DecayCache::new(
+            Duration::from_secs(6),
+            &ZHL16C_COEFFICIENTS.n2_hl,
+            &ZHL16C_COEFFICIENTS.he_hl,
+        );
+        assert!(cache.matches(&Duration::from_secs(6)));
+        assert!(!cache.matches(&Duration::from_secs(5)));
+        assert!(!DecayCache::empty().matches(&Duration::from_secs(0)));
+        for idx in 0..TISSUE_COUNT {
+            let hl = ZHL16C_COEFFICIENTS.n2_hl[idx];
+            assert!((cache.n2(idx) - pow(2.0, -0.1 / hl)).abs() < 1e-6);
+            assert!((cache.n2_time_constant(idx) * LN_2 - hl).abs() < 1e-3);
+        }
+        // After one half-life, half of the difference remains.
+        assert!((decay_factor(5.0, 5.0) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cached_loading_matches_uncached() {
+        let mut uncached = ZHL16::new_by_variant(Tissue::default(), 30, 70, Variant::C);
+        let mut cached = uncached;
+        cached.set_tick(Duration::from_secs(6));
+        assert_eq!(cached.tick(), Some(Duration::from_secs(6)));
+        assert_eq!(uncached.tick(), None);
+
+        load_ticks(&mut uncached);
+        load_ticks(&mut cached);
+        assert_same_tissue(&cached, &uncached);
+    }
+
+    #[test]
+    fn changing_half_lives_rebuilds_the_cache() {
+        let mut cached = ZHL16::new_by_variant(Tissue::default(), 30, 70, Variant::C);
+        cached.set_tick(Duration::from_secs(6));
+        cached.set_coefficients(&ZHL16B_COEFFICIENTS);
+        assert_eq!(cached.tick(), Some(Duration::from_secs(6)));
+
+        let mut uncached = ZHL16::new_by_variant(Tissue::default(), 30, 70, Variant::B);
+        load_ticks(&mut cached);
+        load_ticks(&mut uncached);
+        assert_same_tissue(&cached, &uncached);
+    }
+}
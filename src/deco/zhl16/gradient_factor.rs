@@ -0,0 +1,311 @@
+use crate::common::float::Float;
+
+/// Maximum number of points in a [`GradientFactorProfile::Table`].
+pub const GF_TABLE_CAPACITY: usize = 8;
+
+/// Maximum number of stops given their own gradient factor in a [`GradientFactorProfile::PerStop`].
+pub const GF_STOP_CAPACITY: usize = 16;
+
+/// Represents errors that occur while working with gradient factors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GradientFactorError {
+    /// A gradient factor is zero or above 100.
+    RangeError,
+    /// GF low is above GF high.
+    OrderError,
+    /// A profile has no points, or more than it can hold.
+    CapacityError,
+    /// The depths of a table are not strictly increasing.
+    DepthOrderError,
+}
+
+/// Preset conservatism levels, each mapping to a pair of gradient factors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub enum Conservatism {
+    /// GF 45/95.
+    Low = 0,
+    /// GF 40/85.
+    Medium = 1,
+    /// GF 35/75.
+    High = 2,
+}
+
+impl Conservatism {
+    /// Returns the level with a given value, if there is one.
+    /// # Arguments
+    /// * `value` - Value of the level, as given by `level as u8`
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Conservatism::Low),
+            1 => Some(Conservatism::Medium),
+            2 => Some(Conservatism::High),
+            _ => None,
+        }
+    }
+
+    /// Returns the GF low and GF high of the level (as percentages).
+    pub fn gradient_factors(&self) -> (usize, usize) {
+        match self {
+            Conservatism::Low => (45, 95),
+            Conservatism::Medium => (40, 85),
+            Conservatism::High => (35, 75),
+        }
+    }
+}
+
+/// Check that a pair of gradient factors (as percentages) can be used by a deco model.
+/// # Errors
+/// This function will return a [`GradientFactorError`] if either value is zero or above 100, or if
+/// `gf_low` is above `gf_high`.
+pub(crate) fn validate(gf_low: usize, gf_high: usize) -> Result<(), GradientFactorError> {
+    check_range(gf_low)?;
+    check_range(gf_high)?;
+    if gf_low > gf_high {
+        return Err(GradientFactorError::OrderError);
+    }
+    Ok(())
+}
+
+/// Check that a gradient factor (as a percentage) is between 1 and 100.
+fn check_range(gf: usize) -> Result<(), GradientFactorError> {
+    if gf == 0 || gf > 100 {
+        return Err(GradientFactorError::RangeError);
+    }
+    Ok(())
+}
+
+/// Shape of the gradient factor applied at each depth during decompression.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GradientFactorProfile {
+    /// GF low at the first stop, rising linearly to GF high at the surface.
+    Linear,
+    /// GF high at every depth, so there is no deep stop shaping at all.
+    HighOnly,
+    /// Gradient factors interpolated linearly between points at fixed depths, regardless of where
+    /// the first stop is.
+    Table(GradientFactorTable),
+    /// A gradient factor for each stop, counted from the first stop.
+    PerStop(StopGradientFactors),
+}
+
+impl GradientFactorProfile {
+    /// Returns a piecewise linear profile by depth. Between two points the gradient factor is
+    /// interpolated linearly. Shallower than the first point or deeper than the last, the nearest
+    /// point is used.
+    /// # Arguments
+    /// * `points` - Depths and gradient factors (as percentages), ordered by increasing depth
+    /// # Errors
+    /// This function will return a [`GradientFactorError`] if there are no points or more than
+    /// [`GF_TABLE_CAPACITY`], if the depths are not strictly increasing, or if a gradient factor is
+    /// zero or above 100.
+    pub fn table(points: &[(usize, usize)]) -> Result<Self, GradientFactorError> {
+        if points.is_empty() || points.len() > GF_TABLE_CAPACITY {
+            return Err(GradientFactorError::CapacityError);
+        }
+
+        let mut table = GradientFactorTable {
+            len: points.len(),
+            depths: [0; GF_TABLE_CAPACITY],
+            gfs: [0.0; GF_TABLE_CAPACITY],
+        };
+        for (idx, &(depth, gf)) in points.iter().enumerate() {
+            check_range(gf)?;
+            if idx > 0 && depth <= table.depths[idx - 1] {
+                return Err(GradientFactorError::DepthOrderError);
+            }
+            table.depths[idx] = depth;
+            table.gfs[idx] = gf as Float / 100.0;
+        }
+        Ok(GradientFactorProfile::Table(table))
+    }
+
+    /// Returns a profile giving each stop its own gradient factor. The first stop made has index 0,
+    /// and every stop interval ascended from it adds one. Leaving the last stop for the surface
+    /// counts as one more stop. Stops past the end of the list use its last value.
+    /// # Arguments
+    /// * `gfs` - Gradient factors (as percentages) of each stop, starting from the first stop
+    /// # Errors
+    /// This function will return a [`GradientFactorError`] if there are no gradient factors or
+    /// more than [`GF_STOP_CAPACITY`], or if one is zero or above 100.
+    pub fn per_stop(gfs: &[usize]) -> Result<Self, GradientFactorError> {
+        if gfs.is_empty() || gfs.len() > GF_STOP_CAPACITY {
+            return Err(GradientFactorError::CapacityError);
+        }
+
+        let mut stops = StopGradientFactors {
+            len: gfs.len(),
+            gfs: [0.0; GF_STOP_CAPACITY],
+        };
+        for (idx, &gf) in gfs.iter().enumerate() {
+            check_range(gf)?;
+            stops.gfs[idx] = gf as Float / 100.0;
+        }
+        Ok(GradientFactorProfile::PerStop(stops))
+    }
+}
+
+impl Default for GradientFactorProfile {
+    /// The classic gradient factor line from GF low to GF high.
+    fn default() -> Self {
+        GradientFactorProfile::Linear
+    }
+}
+
+/// Points of a [`GradientFactorProfile::Table`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GradientFactorTable {
+    /// Number of points in use.
+    len: usize,
+    /// Depth of each point.
+    depths: [usize; GF_TABLE_CAPACITY],
+    /// Gradient factor at each point.
+    gfs: [Float; GF_TABLE_CAPACITY],
+}
+
+impl GradientFactorTable {
+    /// Returns the gradient factor at a depth.
+    pub(crate) fn gf_at_depth(&self, depth: usize) -> Float {
+        if depth <= self.depths[0] {
+            return self.gfs[0];
+        }
+        for idx in 1..self.len {
+            if depth <= self.depths[idx] {
+                let (d0, d1) = (self.depths[idx - 1] as Float, self.depths[idx] as Float);
+                let (g0, g1) = (self.gfs[idx - 1], self.gfs[idx]);
+                return g0 + (g1 - g0) * (depth as Float - d0) / (d1 - d0);
+            }
+        }
+        self.gfs[self.len - 1]
+    }
+}
+
+/// Gradient factors of a [`GradientFactorProfile::PerStop`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StopGradientFactors {
+    /// Number of stops in use.
+    len: usize,
+    /// Gradient factor of each stop.
+    gfs: [Float; GF_STOP_CAPACITY],
+}
+
+impl StopGradientFactors {
+    /// Returns the gradient factor of the stop with a given index.
+    pub(crate) fn gf(&self, index: usize) -> Float {
+        if index < self.len {
+            self.gfs[index]
+        } else {
+            self.gfs[self.len - 1]
+        }
+    }
+
+    /// Returns the gradient factor of the last stop in the list.
+    pub(crate) fn last(&self) -> Float {
+        self.gfs[self.len - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the gradient factor of a table profile at a depth, as a percentage.
+    fn table_gf(profile: &GradientFactorProfile, depth: usize) -> Float {
+        match profile {
+            GradientFactorProfile::Table(table) => table.gf_at_depth(depth) * 100.0,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn presets_match_their_gradient_factors() {
+        for value in 0..3 {
+            let level = Conservatism::from_u8(value).unwrap();
+            assert_eq!(level as u8, value);
+            let (low, high) = level.gradient_factors();
+            assert!(validate(low, high).is_ok());
+        }
+        assert_eq!(Conservatism::from_u8(3), None);
+    }
+
+    #[test]
+    fn gradient_factors_are_validated() {
+        assert_eq!(validate(30, 70), Ok(()));
+        assert_eq!(validate(100, 100), Ok(()));
+        assert_eq!(validate(0, 70), Err(GradientFactorError::RangeError));
+        assert_eq!(validate(30, 101), Err(GradientFactorError::RangeError));
+        assert_eq!(validate(80, 70), Err(GradientFactorError::OrderError));
+    }
+
+    #[test]
+    fn table_interpolates_between_points() {
+        let profile = GradientFactorProfile::table(&[(0, 80), (10, 60), (30, 20)]).unwrap();
+        for &(depth, gf) in [
+            (0, 80.0),
+            (5, 70.0),
+            (10, 60.0),
+            (20, 40.0),
+            (30, 20.0),
+            (60, 20.0),
+        ]
+        .iter()
+        {
+            assert!((table_gf(&profile, depth) - gf).abs() < 1e-4, "{}m", depth);
+        }
+        // Shallower than the first point uses the first point.
+        let deep = GradientFactorProfile::table(&[(9, 50), (30, 30)]).unwrap();
+        assert!((table_gf(&deep, 3) - 50.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn table_is_validated() {
+        let full = [(1, 50); GF_TABLE_CAPACITY + 1];
+        assert_eq!(
+            GradientFactorProfile::table(&[]),
+            Err(GradientFactorError::CapacityError)
+        );
+        assert_eq!(
+            GradientFactorProfile::table(&full),
+            Err(GradientFactorError::CapacityError)
+        );
+        assert_eq!(
+            GradientFactorProfile::table(&[(10, 50), (10, 40)]),
+            Err(GradientFactorError::DepthOrderError)
+        );
+        assert_eq!(
+            GradientFactorProfile::table(&[(10, 50), (20, 0)]),
+            Err(GradientFactorError::RangeError)
+        );
+    }
+
+    #[test]
+    fn per_stop_repeats_its_last_value() {
+        let profile = GradientFactorProfile::per_stop(&[30, 50, 70]).unwrap();
+        let stops = match profile {
+            GradientFactorProfile::PerStop(stops) => stops,
+            _ => unreachable!(),
+        };
+        for &(index, gf) in [(0, 0.3), (1, 0.5), (2, 0.7), (3, 0.7), (10, 0.7)].iter() {
+            assert!((stops.gf(index) - gf).abs() < 1e-6);
+        }
+        assert!((stops.last() - 0.7).abs() < 1e-6);
+
+        let full = [50; GF_STOP_CAPACITY + 1];
+        assert_eq!(
+            GradientFactorProfile::per_stop(&[]),
+            Err(GradientFactorError::CapacityError)
+        );
+        assert_eq!(
+            GradientFactorProfile::per_stop(&full),
+            Err(GradientFactorError::CapacityError)
+        );
+        assert_eq!(
+            GradientFactorProfile::per_stop(&[50, 101]),
+            Err(GradientFactorError::RangeError)
+        );
+    }
+}
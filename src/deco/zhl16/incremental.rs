@@ -0,0 +1,312 @@
+use crate::common;
+use crate::common::ascent::AscentRates;
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::gas::Gas;
+use crate::deco::zhl16::{DecoError, TIME_CAP, ZHL16};
+use crate::deco::TISSUE_COUNT;
+use crate::common::float::{floor, Float};
+use core::time::Duration;
+
+/// The result of a completed decompression calculation.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct DecoResult {
+    /// `NoDeco` if the diver can surface directly, otherwise `DecoStop`.
+    segment_type: SegmentType,
+    /// Depth of the next stop, or the current depth if the diver is within the NDL.
+    depth: usize,
+    /// Duration of the next stop, or the NDL (milliseconds)
+    time: u64,
+    /// Time to surface, including the ascent and every stop (milliseconds)
+    tts: u64,
+    /// Whether a stop lasted [`TIME_CAP`] minutes without clearing, so the ascent was abandoned
+    /// there and `tts` only counts up to the end of that stop.
+    capped: bool,
+}
+
+impl DecoResult {
+    /// Returns the NDL (as a `NoDeco` segment) or the next deco stop (as a `DecoStop` segment).
+    pub fn next_stop(&self) -> DiveSegment {
+        DiveSegment::constant(
+            self.segment_type,
+            self.depth,
+            Duration::from_millis(self.time),
+            0,
+            0,
+        )
+    }
+
+    /// Returns the time to surface, including the ascent and every stop.
+    pub fn tts(&self) -> Duration {
+        Duration::from_millis(self.tts)
+    }
+
+    /// Returns whether a stop lasted [`TIME_CAP`] minutes without clearing. The diver cannot
+    /// decompress on the gas at that stop, and the time to surface is only a lower bound.
+    pub fn capped(&self) -> bool {
+        self.capped
+    }
+}
+
+/// Represents the stages of an [`IncrementalPlanner`] calculation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum PlannerPhase {
+    /// No calculation has been started.
+    Idle,
+    /// The NDL is being calculated, one compartment per iteration.
+    Ndl,
+    /// The ascent is being calculated, one stop per iteration.
+    Stops,
+    /// The calculation is complete.
+    Done,
+    /// The calculation stopped with an error. See [`IncrementalPlanner::error`].
+    Failed,
+}
+
+/// A decompression calculation that is performed over several calls, each doing a bounded amount of
+/// work. This allows firmware to calculate deco from a periodic task without missing deadlines.
+///
+/// Calling [`IncrementalPlanner::begin`] takes a snapshot of a model and starts a new calculation.
+/// Calling [`IncrementalPlanner::step`] with an iteration budget then advances it, where an iteration
+/// is either one compartment of the NDL or one stop of the ascent. The result of the last completed
+/// calculation stays available from [`IncrementalPlanner::result`] while the next one is in progress.
+///
+/// The layout of this struct is private, so C code only ever handles it through a pointer.
+#[derive(Copy, Clone)]
+pub struct IncrementalPlanner {
+    /// Virtual model of the diver, walked through the ascent.
+    model: ZHL16,
+    /// Gas to plan the ascent with.
+    gas: Gas,
+    /// Ascent rates, which may depend on depth.
+    ascent_rates: AscentRates,
+    /// Descent rate (measured in m min^-1)
+    descent_rate: isize,
+    /// Depth of water required to induce 1 bar of pressure.
+    metres_per_bar: Float,
+
+    /// Stage of the calculation in progress.
+    phase: PlannerPhase,
+    /// Next compartment to evaluate while calculating the NDL.
+    compartment: usize,
+    /// Shortest compartment NDL found so far (measured in minutes).
+    ndl: Float,
+    /// Result of the calculation in progress.
+    pending: DecoResult,
+    /// Error that stopped the calculation in progress, if any.
+    error: Option<DecoError>,
+
+    /// Whether `result` holds a completed calculation.
+    has_result: bool,
+    /// Result of the last completed calculation.
+    result: DecoResult,
+}
+
+impl IncrementalPlanner {
+    /// Returns a new IncrementalPlanner for a model, with no calculation in progress.
+    /// # Arguments
+    /// * `deco` - Model of the diver to calculate deco for
+    /// * `gas` - Gas to plan the ascent with
+    pub fn new(deco: &ZHL16, gas: &Gas) -> Self {
+        let empty = DecoResult {
+            segment_type: SegmentType::NoDeco,
+            depth: 0,
+            time: 0,
+            tts: 0,
+            capped: false,
+        };
+        Self {
+            model: *deco,
+            gas: *gas,
+            ascent_rates: AscentRates::default(),
+            descent_rate: common::DEFAULT_DESCENT_RATE,
+            metres_per_bar: 10.0,
+            phase: PlannerPhase::Idle,
+            compartment: 0,
+            ndl: TIME_CAP as Float,
+            pending: empty,
+            error: None,
+            has_result: false,
+            result: empty,
+        }
+    }
+
+    /// Start a new calculation from a snapshot of a model. Any calculation in progress is abandoned,
+    /// but the result of the last completed one is kept.
+    /// # Arguments
+    /// * `deco` - Model of the diver to calculate deco for
+    /// * `gas` - Gas to plan the ascent with
+    /// * `ascent_rate` - Ascent rate (measured in m min^-1)
+    /// * `descent_rate` - Descent rate (measured in m min^-1)
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    /// # Errors
+    /// This function will return a [`DecoError`] if `ascent_rate` is not negative. The calculation
+    /// in progress is left untouched.
+    pub fn begin(
+        &mut self,
+        deco: &ZHL16,
+        gas: &Gas,
+        ascent_rate: isize,
+        descent_rate: isize,
+        metres_per_bar: Float,
+    ) -> Result<(), DecoError> {
+        let ascent_rates = AscentRates::new(ascent_rate).map_err(|_| DecoError::AscentRateError)?;
+        self.begin_with_rates(deco, gas, &ascent_rates, descent_rate, metres_per_bar);
+        Ok(())
+    }
+
+    /// Start a new calculation from a snapshot of a model, ascending at rates that depend on depth.
+    /// Any calculation in progress is abandoned, but the result of the last completed one is kept.
+    /// # Arguments
+    /// * `deco` - Model of the diver to calculate deco for
+    /// * `gas` - Gas to plan the ascent with
+    /// * `ascent_rates` - Ascent rates, used for every ascent and the time to surface
+    /// * `descent_rate` - Descent rate (measured in m min^-1)
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn begin_with_rates(
+        &mut self,
+        deco: &ZHL16,
+        gas: &Gas,
+        ascent_rates: &AscentRates,
+        descent_rate: isize,
+        metres_per_bar: Float,
+    ) {
+        self.model = *deco;
+        self.gas = *gas;
+        self.ascent_rates = *ascent_rates;
+        self.descent_rate = descent_rate;
+        self.metres_per_bar = metres_per_bar;
+
+        self.compartment = 0;
+        self.ndl = TIME_CAP as Float;
+        self.pending = DecoResult {
+            segment_type: SegmentType::NoDeco,
+            depth: deco.diver_depth,
+            time: 0,
+            tts: 0,
+            capped: false,
+        };
+        self.error = None;
+
+        self.phase = if deco.find_ascent_ceiling(Some(deco.surface_gf())) < 1.0 {
+            PlannerPhase::Ndl
+        } else {
+            PlannerPhase::Stops
+        };
+    }
+
+    /// Advance the calculation in progress by at most `budget` iterations. Returns whether the
+    /// calculation is complete. A calculation that fails is never complete; check
+    /// [`IncrementalPlanner::error`].
+    pub fn step(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            match self.phase {
+                PlannerPhase::Idle | PlannerPhase::Done | PlannerPhase::Failed => break,
+                PlannerPhase::Ndl => self.step_ndl(),
+                PlannerPhase::Stops => self.step_stops(),
+            }
+        }
+        self.phase == PlannerPhase::Done
+    }
+
+    /// Returns the result of the last completed calculation, if there is one.
+    pub fn result(&self) -> Option<DecoResult> {
+        if self.has_result {
+            Some(self.result)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the stage of the calculation in progress.
+    pub fn phase(&self) -> PlannerPhase {
+        self.phase
+    }
+
+    /// Returns the error that stopped the calculation in progress, if it failed. The result of the
+    /// last completed calculation is kept.
+    pub fn error(&self) -> Option<DecoError> {
+        self.error
+    }
+
+    /// Evaluate the NDL of one compartment.
+    fn step_ndl(&mut self) {
+        let inspired =
+            ZHL16::inspired_pressures(self.model.diver_depth, &self.gas, self.metres_per_bar);
+        let t = self.model.compartment_time_to_m_value(
+            &self.model.tissue,
+            self.compartment,
+            inspired,
+            1.0,
+            self.model.surface_gf(),
+            true,
+        );
+        if t < self.ndl {
+            self.ndl = t;
+        }
+
+        self.compartment += 1;
+        if self.compartment == TISSUE_COUNT {
+            let depth = self.model.diver_depth;
+            self.pending.time = floor(self.ndl) as u64 * 60 * 1000;
+            self.pending.tts = self.ascent_rates.time_taken(depth, 0).as_millis() as u64;
+            self.finish();
+        }
+    }
+
+    /// Calculate one stop of the ascent and move the virtual model past it.
+    fn step_stops(&mut self) {
+        let depth = self.model.diver_depth;
+        let stop = match self.model.next_stop_with_rates(
+            &self.ascent_rates,
+            self.descent_rate,
+            &self.gas,
+            self.metres_per_bar,
+        ) {
+            Ok(t) => t,
+            Err(DecoError::NoStopError) => {
+                // The diver is clear to surface.
+                self.pending.tts += self.ascent_rates.time_taken(depth, 0).as_millis() as u64;
+                self.finish();
+                return;
+            }
+            Err(e) => {
+                self.error = Some(e);
+                self.phase = PlannerPhase::Failed;
+                return;
+            }
+        };
+        let stop_depth = stop.start_depth();
+        for ascent in self
+            .ascent_rates
+            .segments(depth, stop_depth, self.descent_rate)
+        {
+            self.model.add_segment(&ascent, &self.gas, self.metres_per_bar);
+            self.pending.tts += ascent.time().as_millis() as u64;
+        }
+        self.model.add_segment(&stop, &self.gas, self.metres_per_bar);
+        self.pending.tts += stop.time().as_millis() as u64;
+
+        // Only the first stop is reported.
+        if self.pending.segment_type == SegmentType::NoDeco {
+            self.pending.segment_type = SegmentType::DecoStop;
+            self.pending.depth = stop_depth;
+            self.pending.time = stop.time().as_millis() as u64;
+        }
+
+        // A stop this long never clears, so the ascent would repeat it forever.
+        if stop.time().as_secs() >= TIME_CAP * 60 {
+            self.pending.capped = true;
+            self.finish();
+        }
+    }
+
+    /// Publish the calculation in progress as the latest result.
+    fn finish(&mut self) {
+        self.result = self.pending;
+        self.has_result = true;
+        self.phase = PlannerPhase::Done;
+    }
+}
@@ -1,56 +1,87 @@
 use crate::common;
+use crate::common::ascent::AscentRates;
 use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::{ceil, floor, log2, pow, Float};
 use crate::common::gas::Gas;
-use crate::common::time_taken;
+use crate::deco::settings::StopSettings;
 use crate::deco::tissue::Tissue;
-use crate::deco::zhl16::util::{
-    ZHL16B_HE_A, ZHL16B_HE_B, ZHL16B_HE_HALFLIFE, ZHL16B_N2_A, ZHL16B_N2_B, ZHL16B_N2_HALFLIFE,
-    ZHL16C_HE_A, ZHL16C_HE_B, ZHL16C_HE_HALFLIFE, ZHL16C_N2_A, ZHL16C_N2_B, ZHL16C_N2_HALFLIFE,
-};
+use crate::deco::zhl16::decay::{decay_factor, time_constant};
 use crate::deco::{TISSUE_COUNT, WATER_VAPOUR_PRESSURE};
-use core::f64::consts::{E, LN_2};
-use core::intrinsics::ceilf64;
-use core::intrinsics::powf64;
 use core::time::Duration;
 
+pub mod coefficients;
+pub mod decay;
+pub mod gradient_factor;
+pub mod incremental;
+pub mod supersaturation;
 pub mod util;
 pub mod variant;
 
+#[cfg(test)]
+mod tests;
+
+pub use coefficients::{ZHL16Coefficients, ZHL16B_COEFFICIENTS, ZHL16C_COEFFICIENTS};
+pub use decay::DecayCache;
+pub use gradient_factor::{
+    Conservatism, GradientFactorError, GradientFactorProfile, GF_STOP_CAPACITY, GF_TABLE_CAPACITY,
+};
+pub use incremental::{DecoResult, IncrementalPlanner, PlannerPhase};
+pub use supersaturation::Supersaturation;
 pub use util::*;
 
 pub use variant::Variant;
 
+/// Maximum no-decompression limit or stop time reported by the model (measured in minutes).
+pub const TIME_CAP: u64 = 999;
+
+/// Represents errors that occur while calculating decompression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecoError {
+    /// The ascent rate is not negative, so the diver would never reach the surface.
+    AscentRateError,
+    /// The diver has exceeded the no-decompression limit, so has to make deco stops.
+    NdlExceededError,
+    /// The diver is within the no-decompression limit, so has no deco stop to make.
+    NoStopError,
+}
+
+/// Number of bisection steps used when a compartment's NDL or stop time has no closed-form solution.
+/// This bounds the search to a resolution of about one second, so finding a stop evaluates the
+/// Haldane equation at most `2 * TISSUE_COUNT * (SEARCH_ITERATIONS + 2)` times.
+const SEARCH_ITERATIONS: usize = 16;
+
 /// A ZHL-16 decompression model of a diver.
 /// # Notes
 /// For now, each ZHL16 struct should only be used for one dive. This is because calculating decompression
 /// stops with Gradient Factors requires some side effects to be stored inside the struct.
+///
+/// The layout of this struct is private, so C code only ever handles it through a pointer.
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
-#[repr(C)]
 pub struct ZHL16 {
     /// Current tissue model of the diver.
     pub(crate) tissue: Tissue,
     /// Current depth of the diver.
     pub(crate) diver_depth: usize,
-    /// Nitrogen A-values.
-    pub(crate) n2_a: [f64; TISSUE_COUNT],
-    /// Nitrogen B-values.
-    pub(crate) n2_b: [f64; TISSUE_COUNT],
-    /// Nitrogen half-lives.
-    pub(crate) n2_hl: [f64; TISSUE_COUNT],
-    /// Helium A-values.
-    pub(crate) he_a: [f64; TISSUE_COUNT],
-    /// Helium B-values.
-    pub(crate) he_b: [f64; TISSUE_COUNT],
-    /// Helium half-lives.
-    pub(crate) he_hl: [f64; TISSUE_COUNT],
+    /// Tissue loading constants.
+    #[cfg_attr(feature = "use-serde", serde(with = "coefficients::serde_variant"))]
+    pub(crate) coefficients: &'static ZHL16Coefficients,
 
+    /// Depth of the first deco stop made, or `usize::MAX` before then.
     pub(crate) first_deco_depth: usize,
 
     /// GF Low value
-    pub(crate) gf_low: f64,
+    pub(crate) gf_low: Float,
     /// GF High value
-    pub(crate) gf_high: f64,
+    pub(crate) gf_high: Float,
+    /// Shape of the gradient factor between the first stop and the surface.
+    pub(crate) gf_profile: GradientFactorProfile,
+
+    /// Placement and duration of decompression stops.
+    pub(crate) stop_settings: StopSettings,
+
+    /// Decay factors for the configured tick duration.
+    pub(crate) decay_cache: DecayCache,
 }
 
 impl ZHL16 {
@@ -58,38 +89,28 @@ impl ZHL16 {
     /// loading constants by yourself. Otherwise, use [`ZHL16::new_by_variant`] instead.
     /// # Arguments
     /// * `tissue` - Tissue model of the diver before the dive
-    /// * `n2_a` - Nitrogen A-values to use
-    /// * `n2_b` - Nitrogen B-values to use
-    /// * `n2_hl` - Nitrogen half-lives to use
-    /// * `he_a` - Helium A-values to use
-    /// * `he_b` - Helium B-values to use
-    /// * `he_hl` - Helium half-lives to use
+    /// * `coefficients` - Tissue loading constants to use
     /// * `gf_low` - Gradient Factor low value to use when calculating deco stops
     /// * `gf_high` - Gradient Factor high value to use when calculating deco stops
     pub fn new(
         tissue: Tissue,
-        n2_a: [f64; TISSUE_COUNT],
-        n2_b: [f64; TISSUE_COUNT],
-        n2_hl: [f64; TISSUE_COUNT],
-        he_a: [f64; TISSUE_COUNT],
-        he_b: [f64; TISSUE_COUNT],
-        he_hl: [f64; TISSUE_COUNT],
+        coefficients: &'static ZHL16Coefficients,
         gf_low: usize,
         gf_high: usize,
     ) -> Self {
         Self {
             tissue,
             diver_depth: 0,
-            n2_a,
-            n2_b,
-            n2_hl,
-            he_a,
-            he_b,
-            he_hl,
+            coefficients,
 
             first_deco_depth: usize::MAX,
-            gf_low: gf_low as f64 / 100.0,
-            gf_high: gf_high as f64 / 100.0,
+            gf_low: gf_low as Float / 100.0,
+            gf_high: gf_high as Float / 100.0,
+            gf_profile: GradientFactorProfile::Linear,
+
+            stop_settings: StopSettings::default(),
+
+            decay_cache: DecayCache::empty(),
         }
     }
 
@@ -100,30 +121,7 @@ impl ZHL16 {
     /// * `gf_high` - Gradient Factor high value to use when calculating deco stops
     /// * `variant` - Variant to use
     pub fn new_by_variant(tissue: Tissue, gfl: usize, gfh: usize, variant: Variant) -> Self {
-        match variant {
-            Variant::B => Self::new(
-                tissue,
-                ZHL16B_N2_A,
-                ZHL16B_N2_B,
-                ZHL16B_N2_HALFLIFE,
-                ZHL16B_HE_A,
-                ZHL16B_HE_B,
-                ZHL16B_HE_HALFLIFE,
-                gfl,
-                gfh,
-            ),
-            Variant::C => Self::new(
-                tissue,
-                ZHL16C_N2_A,
-                ZHL16C_N2_B,
-                ZHL16C_N2_HALFLIFE,
-                ZHL16C_HE_A,
-                ZHL16C_HE_B,
-                ZHL16C_HE_HALFLIFE,
-                gfl,
-                gfh,
-            ),
-        }
+        Self::new(tissue, variant.coefficients(), gfl, gfh)
     }
 
     /// Update the first deco depth of the diver. This is used to calculate the GF any given point
@@ -135,12 +133,64 @@ impl ZHL16 {
     }
 
     /// Find the gradient factor to use at a given depth during decompression
-    fn gf_at_depth(&self, depth: usize) -> f64 {
-        if self.first_deco_depth != usize::MAX {
+    fn gf_at_depth(&self, depth: usize) -> Float {
+        self.gf_from_first_stop(self.first_deco_depth, depth)
+    }
+
+    /// Find the gradient factor to use at a given depth, had decompression started at a given depth.
+    fn gf_from_first_stop(&self, first_deco_depth: usize, depth: usize) -> Float {
+        match &self.gf_profile {
+            GradientFactorProfile::Linear => self.linear_gf(first_deco_depth, depth),
+            GradientFactorProfile::HighOnly => self.gf_high,
+            GradientFactorProfile::Table(table) => table.gf_at_depth(depth),
+            GradientFactorProfile::PerStop(stops) => {
+                if first_deco_depth == usize::MAX {
+                    return stops.last();
+                }
+                stops.gf(self.stop_index(first_deco_depth, depth))
+            }
+        }
+    }
+
+    /// Find the gradient factor to use before the first stop is known, to place it.
+    fn first_stop_gf(&self) -> Float {
+        match &self.gf_profile {
+            GradientFactorProfile::Linear => self.gf_low,
+            GradientFactorProfile::HighOnly => self.gf_high,
+            // The first stop is placed with the gradient factor at the diver's depth.
+            GradientFactorProfile::Table(table) => table.gf_at_depth(self.diver_depth),
+            GradientFactorProfile::PerStop(stops) => stops.gf(0),
+        }
+    }
+
+    /// Find the gradient factor that applies on surfacing without any deco stops.
+    pub(crate) fn surface_gf(&self) -> Float {
+        match &self.gf_profile {
+            GradientFactorProfile::Linear | GradientFactorProfile::HighOnly => self.gf_high,
+            GradientFactorProfile::Table(table) => table.gf_at_depth(0),
+            GradientFactorProfile::PerStop(stops) => stops.last(),
+        }
+    }
+
+    /// Count the stops between the first stop and a depth. The surface counts as the stop after
+    /// the last stop.
+    fn stop_index(&self, first_deco_depth: usize, depth: usize) -> usize {
+        let mut index = 0;
+        let mut stop = first_deco_depth;
+        while stop > depth {
+            stop = self.stop_settings.next_stop_depth(stop);
+            index += 1;
+        }
+        index
+    }
+
+    /// Find the gradient factor on the line from GF low at the first stop to GF high at the surface.
+    fn linear_gf(&self, first_deco_depth: usize, depth: usize) -> Float {
+        if first_deco_depth != usize::MAX {
             // Only calculate the gradient factor if we're below the surface.
             if depth > 0 {
                 return self.gf_high
-                    + ((self.gf_high - self.gf_low) / (0.0 - self.first_deco_depth as f64)) * (depth as f64);
+                    + ((self.gf_high - self.gf_low) / (0.0 - first_deco_depth as Float)) * (depth as Float);
             }
             self.gf_high // We must be on the surface, by definition use gf_high
         }
@@ -150,100 +200,187 @@ impl ZHL16 {
     }
 
     /// Add a segment that has a depth change according to the Schreiner Equation.
-    fn add_depth_change(&mut self, segment: &DiveSegment, gas: &Gas, metres_per_bar: f64) {
+    fn add_depth_change(&mut self, segment: &DiveSegment, gas: &Gas, metres_per_bar: Float) {
+        ZHL16::load_depth_change(
+            &mut self.tissue,
+            self.coefficients,
+            &self.decay_cache,
+            segment,
+            gas,
+            metres_per_bar,
+        );
+        self.diver_depth = segment.end_depth(); // Update diver depth
+    }
+
+    /// Load a tissue with a segment that has a depth change according to the Schreiner Equation.
+    fn load_depth_change(
+        tissue: &mut Tissue,
+        coefficients: &ZHL16Coefficients,
+        decay_cache: &DecayCache,
+        segment: &DiveSegment,
+        gas: &Gas,
+        metres_per_bar: Float,
+    ) {
         let delta_depth = (segment.end_depth() as isize) - (segment.start_depth() as isize);
-        let rate;
-        if delta_depth > 0 {
-            rate = segment.descent_rate()
+        let rate = if delta_depth > 0 {
+            segment.descent_rate()
         } else {
-            rate = segment.ascent_rate()
-        }
+            segment.ascent_rate()
+        };
 
-        let t = segment.time().as_secs() as f64 / 60.0;
+        let t = segment.time().as_secs() as Float / 60.0;
+        let cached = decay_cache.matches(segment.time());
+        let constants = decay_cache.has_time_constants();
+        let pressure = ZHL16::compensated_pressure(segment.start_depth(), metres_per_bar);
 
         // Load nitrogen tissue compartments
-        for (idx, val) in self.tissue.p_n2.iter_mut().enumerate() {
+        let pio: Float = pressure * gas.fr_n2();
+        let r = (rate as Float / 10.0) * gas.fr_n2();
+        for (idx, val) in tissue.p_n2.iter_mut().enumerate() {
             let po = *val;
-            let pio: f64 =
-                ZHL16::compensated_pressure(segment.start_depth(), metres_per_bar) * gas.fr_n2();
-            let r = (rate as f64 / 10.0) * gas.fr_n2();
-            let k = LN_2 / self.n2_hl[idx];
-            let pn: f64 = ZHL16::depth_change_loading(t, po, pio, r, k);
+            let tau = if constants {
+                decay_cache.n2_time_constant(idx)
+            } else {
+                time_constant(coefficients.n2_hl[idx])
+            };
+            let decay = if cached {
+                decay_cache.n2(idx)
+            } else {
+                decay_factor(t, coefficients.n2_hl[idx])
+            };
+            let pn: Float = ZHL16::depth_change_loading(t, po, pio, r, tau, decay);
             *val = pn;
-            self.tissue.p_t[idx] = pn;
+            tissue.p_t[idx] = pn;
         }
 
         // Load helium tissue compartments
-        for (idx, val) in self.tissue.p_he.iter_mut().enumerate() {
+        let pio: Float = pressure * gas.fr_he();
+        let r = (rate as Float / 10.0) * gas.fr_he();
+        for (idx, val) in tissue.p_he.iter_mut().enumerate() {
             let po = *val;
-            let pio: f64 =
-                ZHL16::compensated_pressure(segment.start_depth(), metres_per_bar) * gas.fr_he();
-            let r = (rate as f64 / 10.0) * gas.fr_he();
-            let k = LN_2 / self.he_hl[idx];
-            let ph: f64 = ZHL16::depth_change_loading(t, po, pio, r, k);
+            let tau = if constants {
+                decay_cache.he_time_constant(idx)
+            } else {
+                time_constant(coefficients.he_hl[idx])
+            };
+            let decay = if cached {
+                decay_cache.he(idx)
+            } else {
+                decay_factor(t, coefficients.he_hl[idx])
+            };
+            let ph: Float = ZHL16::depth_change_loading(t, po, pio, r, tau, decay);
             *val = ph;
-            self.tissue.p_t[idx] += ph;
+            tissue.p_t[idx] += ph;
         }
-        self.diver_depth = segment.end_depth(); // Update diver depth
     }
 
     /// Calculate the pressure at a given depth minus the ambient water vapour pressure in the lungs.
-    fn compensated_pressure(depth: usize, metres_per_bar: f64) -> f64 {
-        common::mtr_bar(depth as f64, metres_per_bar) - WATER_VAPOUR_PRESSURE
+    fn compensated_pressure(depth: usize, metres_per_bar: Float) -> Float {
+        common::mtr_bar(depth as Float, metres_per_bar) - WATER_VAPOUR_PRESSURE
+    }
+
+    /// Calculate the inspired nitrogen and helium pressures of a gas breathed at a given depth.
+    fn inspired_pressures(depth: usize, gas: &Gas, metres_per_bar: Float) -> (Float, Float) {
+        let pressure = ZHL16::compensated_pressure(depth, metres_per_bar);
+        (pressure * gas.fr_n2(), pressure * gas.fr_he())
     }
 
-    /// Calculate the gas loading with a depth change.
+    /// Calculate the gas loading with a depth change. `time_constant` is `1/k` and `decay` is
+    /// `e^(-kt)`.
     fn depth_change_loading(
-        time: f64,
-        initial_pressure: f64,
-        initial_ambient_pressure: f64,
-        r: f64,
-        k: f64,
-    ) -> f64 {
-        unsafe {
-            initial_ambient_pressure + r * (time - (1.0 / k))
-                - ((initial_ambient_pressure - initial_pressure - (r / k))
-                    * powf64(E, -1.0 * k * time))
-        }
+        time: Float,
+        initial_pressure: Float,
+        initial_ambient_pressure: Float,
+        r: Float,
+        time_constant: Float,
+        decay: Float,
+    ) -> Float {
+        initial_ambient_pressure + r * (time - time_constant)
+            - ((initial_ambient_pressure - initial_pressure - (r * time_constant)) * decay)
     }
 
     /// Add a segment without depth change according to the Schreiner Equation.
-    fn add_bottom_segment(&mut self, segment: &DiveSegment, gas: &Gas, metres_per_bar: f64) {
-        for (idx, val) in self.tissue.p_n2.iter_mut().enumerate() {
+    fn add_bottom_segment(&mut self, segment: &DiveSegment, gas: &Gas, metres_per_bar: Float) {
+        ZHL16::load_bottom_segment(
+            &mut self.tissue,
+            self.coefficients,
+            &self.decay_cache,
+            segment,
+            gas,
+            metres_per_bar,
+        );
+        self.diver_depth = segment.end_depth();
+    }
+
+    /// Load a tissue with a segment without depth change according to the Schreiner Equation.
+    fn load_bottom_segment(
+        tissue: &mut Tissue,
+        coefficients: &ZHL16Coefficients,
+        decay_cache: &DecayCache,
+        segment: &DiveSegment,
+        gas: &Gas,
+        metres_per_bar: Float,
+    ) {
+        let t = segment.time().as_secs_f64() as Float / 60.0;
+        let cached = decay_cache.matches(segment.time());
+        let pressure = ZHL16::compensated_pressure(segment.end_depth(), metres_per_bar);
+
+        let pi = pressure * gas.fr_n2();
+        for (idx, val) in tissue.p_n2.iter_mut().enumerate() {
             let po = *val;
-            let pi = ZHL16::compensated_pressure(segment.end_depth(), metres_per_bar) * gas.fr_n2();
-            let p = unsafe {
-                po + (pi - po)
-                    * (1.0
-                        - (powf64(
-                            2.0_f64,
-                            -1.0 * (segment.time().as_secs() / 60) as f64 / self.n2_hl[idx],
-                        )))
+            let decay = if cached {
+                decay_cache.n2(idx)
+            } else {
+                decay_factor(t, coefficients.n2_hl[idx])
             };
+            let p = po + (pi - po) * (1.0 - decay);
             *val = p;
-            self.tissue.p_t[idx] = p;
+            tissue.p_t[idx] = p;
         }
 
-        for (idx, val) in self.tissue.p_he.iter_mut().enumerate() {
+        let pi = pressure * gas.fr_he();
+        for (idx, val) in tissue.p_he.iter_mut().enumerate() {
             let po = *val;
-            let pi = ZHL16::compensated_pressure(segment.end_depth(), metres_per_bar) * gas.fr_he();
-            let p = unsafe {
-                po + (pi - po)
-                    * (1.0
-                        - (powf64(
-                            2.0_f64,
-                            -1.0 * (segment.time().as_secs() / 60) as f64 / self.he_hl[idx],
-                        )))
+            let decay = if cached {
+                decay_cache.he(idx)
+            } else {
+                decay_factor(t, coefficients.he_hl[idx])
             };
+            let p = po + (pi - po) * (1.0 - decay);
             *val = p;
-            self.tissue.p_t[idx] += p;
+            tissue.p_t[idx] += p;
+        }
+    }
+
+    /// Returns the ascent ceiling of the model (measured in bar) at a given gradient factor (as a
+    /// percentage).
+    /// # Arguments
+    /// * `gf` - Gradient factor to use, or `None` for the one the model places its next stop with
+    pub fn ceiling(&self, gf: Option<usize>) -> Float {
+        self.find_ascent_ceiling(gf.map(|t| t as Float / 100.0))
+    }
+
+    /// Returns the ascent ceiling of the model as a depth at a given gradient factor (as a
+    /// percentage). The ceiling is zero if the diver can ascend directly to the surface.
+    /// # Arguments
+    /// * `gf` - Gradient factor to use, or `None` for the one the model places its next stop with
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn ceiling_depth(&self, gf: Option<usize>, metres_per_bar: Float) -> Float {
+        let depth = common::bar_mtr(self.ceiling(gf), metres_per_bar);
+        if depth > 0.0 {
+            depth
+        } else {
+            0.0
         }
-        self.diver_depth = segment.end_depth();
+    }
+
+    /// Returns the current depth of the diver.
+    pub fn depth(&self) -> usize {
+        self.diver_depth
     }
 
     /// Returns the ascent ceiling of the model.
-    pub(crate) fn find_ascent_ceiling(&self, gf_override: Option<f64>) -> f64 {
-        let mut ceilings: [f64; TISSUE_COUNT] = [0.0; TISSUE_COUNT];
+    pub(crate) fn find_ascent_ceiling(&self, gf_override: Option<Float>) -> Float {
         let gf = match gf_override {
             Some(t) => t,
             None => {
@@ -251,141 +388,342 @@ impl ZHL16 {
                     self.gf_at_depth(self.first_deco_depth)
                 }
                 else {
-                    self.gf_low
+                    self.first_stop_gf()
                 }
             },
         };
 
+        self.ascent_ceiling(&self.tissue, gf)
+    }
+
+    /// Returns the ascent ceiling of a tissue at a given gradient factor.
+    fn ascent_ceiling(&self, tissue: &Tissue, gf: Float) -> Float {
+        let mut ceilings: [Float; TISSUE_COUNT] = [0.0; TISSUE_COUNT];
         for (idx, val) in ceilings.iter_mut().enumerate() {
-            let a = self.tissue_a_value(idx);
-            let b = self.tissue_b_value(idx);
-            *val = self.tissue_ceiling(gf, idx, a, b)
+            let a = self.tissue_a_value(tissue, idx);
+            let b = self.tissue_b_value(tissue, idx);
+            *val = self.tissue_ceiling(tissue, gf, idx, a, b)
         }
 
-        ceilings.iter().cloned().fold(core::f64::NAN, f64::max)
+        ceilings.iter().cloned().fold(Float::NAN, Float::max)
     }
 
     /// Calculate the tissue ceiling of a compartment.
-    fn tissue_ceiling(&self, gf: f64, x: usize, a: f64, b: f64) -> f64 {
-        ((self.tissue.p_n2[x] + self.tissue.p_he[x]) - (a * gf)) / (gf / b + 1.0 - gf)
+    fn tissue_ceiling(&self, tissue: &Tissue, gf: Float, x: usize, a: Float, b: Float) -> Float {
+        ((tissue.p_n2[x] + tissue.p_he[x]) - (a * gf)) / (gf / b + 1.0 - gf)
     }
 
     /// Calculate the B-value of a compartment.
-    fn tissue_b_value(&self, x: usize) -> f64 {
-        (self.n2_b[x] * self.tissue.p_n2[x] + self.he_b[x] * self.tissue.p_he[x])
-            / (self.tissue.p_n2[x] + self.tissue.p_he[x])
+    fn tissue_b_value(&self, tissue: &Tissue, x: usize) -> Float {
+        (self.coefficients.n2_b[x] * tissue.p_n2[x] + self.coefficients.he_b[x] * tissue.p_he[x])
+            / (tissue.p_n2[x] + tissue.p_he[x])
     }
 
     /// Calculate the A-value of a compartment.
-    fn tissue_a_value(&self, x: usize) -> f64 {
-        (self.n2_a[x] * self.tissue.p_n2[x] + self.he_a[x] * self.tissue.p_he[x])
-            / (self.tissue.p_n2[x] + self.tissue.p_he[x])
+    fn tissue_a_value(&self, tissue: &Tissue, x: usize) -> Float {
+        (self.coefficients.n2_a[x] * tissue.p_n2[x] + self.coefficients.he_a[x] * tissue.p_he[x])
+            / (tissue.p_n2[x] + tissue.p_he[x])
+    }
+
+    /// Returns the depth decompression started at, or would start at if it began at a given stop.
+    fn first_deco_depth_or(&self, stop_depth: usize) -> usize {
+        if self.first_deco_depth == usize::MAX {
+            stop_depth
+        } else {
+            self.first_deco_depth
+        }
+    }
+
+    /// Return the depth of the next deco stop of the model, rounded to the stop interval. Zero if the
+    /// diver can ascend directly to the surface.
+    /// # Arguments
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn stop_depth(&self, metres_per_bar: Float) -> usize {
+        let mut stop_depth = self.stop_settings.stop_depth(common::bar_mtr(
+            self.find_ascent_ceiling(None),
+            metres_per_bar,
+        ));
+
+        // Within the NDL there is no stop. Otherwise, the first stop is the ceiling at its gradient
+        // factor (GF low for a linear profile) rounded deeper, and once decompression has started,
+        // the gradient factor allows progressively shallower stops.
+        if self.first_deco_depth == usize::MAX {
+            if self.ascent_ceiling(&self.tissue, self.surface_gf())
+                < common::mtr_bar(0.0, metres_per_bar)
+            {
+                return 0;
+            }
+            return stop_depth;
+        }
+        while stop_depth > 0 {
+            let next_stop_depth = self.stop_settings.next_stop_depth(stop_depth);
+            let gf = self.gf_at_depth(next_stop_depth);
+            if self.ascent_ceiling(&self.tissue, gf)
+                >= common::mtr_bar(next_stop_depth as Float, metres_per_bar)
+            {
+                break;
+            }
+            stop_depth = next_stop_depth;
+        }
+        stop_depth
     }
 
     /// Return the next deco stop of the model.
-    pub(crate) fn next_stop(
+    /// # Arguments
+    /// * `ascent_rate` - Ascent rate to the stop (measured in m min^-1)
+    /// * `descent_rate` - Descent rate of the diver (measured in m min^-1)
+    /// * `gas` - Gas breathed on the ascent and at the stop
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    /// # Errors
+    /// This function will return a [`DecoError`] if `ascent_rate` is not negative, or if the diver
+    /// can ascend directly to the surface.
+    pub fn next_stop(
         &self,
         ascent_rate: isize,
         descent_rate: isize,
         gas: &Gas,
-        metres_per_bar: f64,
-    ) -> DiveSegment {
-        let stop_depth = unsafe {
-            (3.0 * (ceilf64(common::bar_mtr(self.find_ascent_ceiling(None), metres_per_bar) / 3.0)))
-                as usize
-        };
-        // Find the next stop depth rounded to 3m
-        let mut stop_time: usize = 0;
-        let mut in_limit: bool = false;
-        while !in_limit {
-            let mut virtual_zhl16 = *self;
-            // This is done for the exact same reason as the check in the surface implementation.
-            if virtual_zhl16.diver_depth != stop_depth {
-                let depth_change_segment = DiveSegment::new(
-                    SegmentType::AscDesc,
-                    virtual_zhl16.diver_depth,
-                    stop_depth,
-                    time_taken(ascent_rate, virtual_zhl16.diver_depth, stop_depth),
-                    ascent_rate,
-                    descent_rate,
-                )
-                .unwrap();
-                virtual_zhl16.add_segment(&depth_change_segment, gas, metres_per_bar);
+        metres_per_bar: Float,
+    ) -> Result<DiveSegment, DecoError> {
+        let ascent_rates = AscentRates::new(ascent_rate).map_err(|_| DecoError::AscentRateError)?;
+        self.next_stop_with_rates(&ascent_rates, descent_rate, gas, metres_per_bar)
+    }
+
+    /// Return the next deco stop of the model, ascending to it at rates that depend on depth.
+    /// # Arguments
+    /// * `ascent_rates` - Ascent rates to the stop
+    /// * `descent_rate` - Descent rate of the diver (measured in m min^-1)
+    /// * `gas` - Gas breathed on the ascent and at the stop
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    /// # Errors
+    /// This function will return a [`DecoError`] if the diver can ascend directly to the surface.
+    pub fn next_stop_with_rates(
+        &self,
+        ascent_rates: &AscentRates,
+        descent_rate: isize,
+        gas: &Gas,
+        metres_per_bar: Float,
+    ) -> Result<DiveSegment, DecoError> {
+        let stop_depth = self.stop_depth(metres_per_bar);
+        if stop_depth == 0 {
+            return Err(DecoError::NoStopError);
+        }
+        // The diver must be able to reach the following stop (or the surface) once this one is done
+        let next_stop_depth = self.stop_settings.next_stop_depth(stop_depth);
+        // Only the tissue is simulated ahead, the rest of the model is shared.
+        let mut virtual_tissue = self.tissue;
+        // This is done for the exact same reason as the check in the surface implementation.
+        for depth_change_segment in
+            ascent_rates.segments(self.diver_depth, stop_depth, descent_rate)
+        {
+            ZHL16::load_depth_change(
+                &mut virtual_tissue,
+                self.coefficients,
+                &self.decay_cache,
+                &depth_change_segment,
+                gas,
+                metres_per_bar,
+            );
+        }
+
+        // Every compartment must be within the gradient factor the diver will be allowed at the
+        // following stop. The stop lasts as long as the slowest compartment takes to get there.
+        let gf = self.gf_from_first_stop(self.first_deco_depth_or(stop_depth), next_stop_depth);
+        let ambient_pressure = common::mtr_bar(next_stop_depth as Float, metres_per_bar);
+        let inspired = ZHL16::inspired_pressures(stop_depth, gas, metres_per_bar);
+        let mut stop_time: Float = 0.0;
+        for idx in 0..TISSUE_COUNT {
+            let t = self.compartment_time_to_m_value(
+                &virtual_tissue,
+                idx,
+                inspired,
+                ambient_pressure,
+                gf,
+                false,
+            );
+            if t > stop_time {
+                stop_time = t;
             }
-            let segment = DiveSegment::new(
-                SegmentType::DecoStop,
-                stop_depth,
-                stop_depth,
-                Duration::from_secs((stop_time * 60) as u64),
-                ascent_rate,
-                descent_rate,
-            )
-            .unwrap();
-
-            virtual_zhl16.add_segment(&segment, gas, metres_per_bar);
-            virtual_zhl16.update_first_deco_depth(segment.end_depth());
-
-            in_limit = virtual_zhl16.find_ascent_ceiling(None)
-                < common::mtr_bar(stop_depth as f64, metres_per_bar)
-                    - (common::mtr_bar(3.0, metres_per_bar) - 1.0);
-            stop_time += 1;
         }
-        DiveSegment::new(
+
+        // Round up to the stop resolution
+        let resolution = self.stop_settings.stop_resolution().as_secs() as Float;
+        let stop_time = (ceil(stop_time * 60.0 / resolution) * resolution) as u64;
+
+        Ok(DiveSegment::constant(
             SegmentType::DecoStop,
             stop_depth,
-            stop_depth,
-            Duration::from_secs((stop_time * 60) as u64),
-            ascent_rate,
+            core::cmp::max(
+                Duration::from_secs(stop_time),
+                self.stop_settings.min_stop_time(),
+            ),
+            ascent_rates.rate_at(stop_depth),
             descent_rate,
-        )
-        .unwrap()
-    }
-
-    /// Return the no-decompression limit of the model, if it exists.
-    pub(crate) fn ndl(&self, gas: &Gas, metres_per_bar: f64) -> Option<DiveSegment> {
-        let mut ndl = 0;
-        let mut in_ndl = true;
-        while in_ndl {
-            let mut virtual_zhl16 = *self;
-            let virtual_segment = DiveSegment::new(
-                SegmentType::NoDeco,
-                virtual_zhl16.diver_depth,
-                virtual_zhl16.diver_depth,
-                Duration::from_secs(ndl * 60),
-                0,
-                0,
-            )
-            .unwrap();
-            virtual_zhl16.add_bottom_segment(&virtual_segment, gas, metres_per_bar);
-            in_ndl = virtual_zhl16.find_ascent_ceiling(Some(self.gf_high)) < 1.0;
-            if in_ndl {
-                ndl += 1;
+        ))
+    }
+
+    /// Return the no-decompression limit of the model.
+    /// # Arguments
+    /// * `gas` - Gas breathed at the current depth
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    /// # Errors
+    /// This function will return a [`DecoError`] if the diver has exceeded the no-decompression limit.
+    pub fn ndl(&self, gas: &Gas, metres_per_bar: Float) -> Result<DiveSegment, DecoError> {
+        self.ndl_with_compartment(gas, metres_per_bar, 1.0)
+            .map(|(segment, _)| segment)
+    }
+
+    /// Return the no-decompression limit of the model and the index of the compartment controlling
+    /// it, the one that reaches its M-value first. Compartments are indexed from 0, the fastest. The
+    /// limit is capped at [`TIME_CAP`] minutes, in which case no compartment controls it and the
+    /// index is 0.
+    /// # Arguments
+    /// * `gas` - Gas breathed at the current depth
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    /// * `surface_pressure` - Pressure at the surface the diver ascends to (measured in bar), 1.0 at
+    ///   sea level
+    /// # Errors
+    /// This function will return a [`DecoError`] if the diver has exceeded the no-decompression limit.
+    pub fn ndl_with_compartment(
+        &self,
+        gas: &Gas,
+        metres_per_bar: Float,
+        surface_pressure: Float,
+    ) -> Result<(DiveSegment, usize), DecoError> {
+        // The diver is already past the no-decompression limit.
+        if self.find_ascent_ceiling(Some(self.surface_gf())) >= surface_pressure {
+            return Err(DecoError::NdlExceededError);
+        }
+
+        let inspired = ZHL16::inspired_pressures(self.diver_depth, gas, metres_per_bar);
+        let mut ndl = TIME_CAP as Float;
+        let mut compartment = 0;
+        for idx in 0..TISSUE_COUNT {
+            let t = self.compartment_time_to_m_value(
+                &self.tissue,
+                idx,
+                inspired,
+                surface_pressure,
+                self.surface_gf(),
+                true,
+            );
+            if t < ndl {
+                ndl = t;
+                compartment = idx;
             }
-            if ndl > 999 {
-                return Some(
-                    DiveSegment::new(
-                        SegmentType::NoDeco,
-                        self.diver_depth,
-                        self.diver_depth,
-                        Duration::from_secs(999), // Use 999 here, don't want number to get too large
-                        0,
-                        0,
-                    )
-                    .unwrap(),
-                );
+        }
+
+        let segment = DiveSegment::constant(
+            SegmentType::NoDeco,
+            self.diver_depth,
+            Duration::from_secs(floor(ndl) as u64 * 60),
+            0,
+            0,
+        );
+        Ok((segment, compartment))
+    }
+
+    /// Return the time (measured in minutes) a compartment held at a constant depth takes to cross
+    /// its M-value, capped at [`TIME_CAP`] minutes.
+    /// # Arguments
+    /// * `tissue` - Tissue to take the compartment from
+    /// * `x` - Index of the compartment
+    /// * `inspired` - Inspired nitrogen and helium pressures at the depth the compartment is held at
+    /// * `ambient_pressure` - Ambient pressure the M-value is calculated for
+    /// * `gf` - Gradient factor applied to the M-value
+    /// * `on_gassing` - Whether the compartment is expected to rise above the M-value (as for an NDL)
+    ///   or fall below it (as for a deco stop)
+    fn compartment_time_to_m_value(
+        &self,
+        tissue: &Tissue,
+        x: usize,
+        inspired: (Float, Float),
+        ambient_pressure: Float,
+        gf: Float,
+        on_gassing: bool,
+    ) -> Float {
+        let c = self.coefficients;
+        let (pi_n2, pi_he) = inspired;
+        let po_n2 = tissue.p_n2[x];
+        let po_he = tissue.p_he[x];
+
+        // Haldane equation at constant depth
+        let p_n2 = |t: Float| pi_n2 + (po_n2 - pi_n2) * pow(2.0, -t / c.n2_hl[x]);
+        let p_he = |t: Float| pi_he + (po_he - pi_he) * pow(2.0, -t / c.he_hl[x]);
+
+        // M-value adjusted by the gradient factor, weighted by the inert gases in the compartment
+        let m_value = |p_n2: Float, p_he: Float| {
+            let a = (c.n2_a[x] * p_n2 + c.he_a[x] * p_he) / (p_n2 + p_he);
+            let b = (c.n2_b[x] * p_n2 + c.he_b[x] * p_he) / (p_n2 + p_he);
+            a * gf + ambient_pressure * (gf / b + 1.0 - gf)
+        };
+        let reached = |t: Float| {
+            let (n2, he) = (p_n2(t), p_he(t));
+            (n2 + he >= m_value(n2, he)) == on_gassing
+        };
+
+        if reached(0.0) {
+            return 0.0;
+        }
+
+        if po_he == 0.0 && pi_he == 0.0 {
+            // Only nitrogen is present, so the M-value is constant and the Haldane equation can be
+            // solved directly.
+            let ratio = (m_value(po_n2, 0.0) - pi_n2) / (po_n2 - pi_n2);
+            if ratio <= 0.0 || ratio >= 1.0 {
+                return TIME_CAP as Float;
             }
+            let t = -c.n2_hl[x] * log2(ratio);
+            return if t < TIME_CAP as Float { t } else { TIME_CAP as Float };
         }
-        Some(
-            DiveSegment::new(
-                SegmentType::NoDeco,
-                self.diver_depth,
-                self.diver_depth,
-                Duration::from_secs(ndl * 60),
-                0,
-                0,
-            )
-            .unwrap(),
-        )
+
+        // The M-value shifts as the helium/nitrogen ratio changes. Bisect for the crossing instead.
+        if !reached(TIME_CAP as Float) {
+            return TIME_CAP as Float;
+        }
+        let (mut low, mut high) = (0.0, TIME_CAP as Float);
+        for _ in 0..SEARCH_ITERATIONS {
+            let mid = (low + high) / 2.0;
+            if reached(mid) {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        if on_gassing { low } else { high }
+    }
+
+    /// Returns how close each compartment is to its M-value, both at the current depth and at the
+    /// surface, along with GF99, surface GF and the leading compartment.
+    /// # Arguments
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn supersaturation(&self, metres_per_bar: Float) -> Supersaturation {
+        let ambient_pressure = common::mtr_bar(self.diver_depth as Float, metres_per_bar);
+        let mut result = Supersaturation {
+            current: [0.0; TISSUE_COUNT],
+            surface: [0.0; TISSUE_COUNT],
+            gf99: 0.0,
+            surface_gf: 0.0,
+            leading_compartment: 0,
+        };
+
+        for idx in 0..TISSUE_COUNT {
+            let a = self.tissue_a_value(&self.tissue, idx);
+            let b = self.tissue_b_value(&self.tissue, idx);
+            let pressure = self.tissue.p_n2[idx] + self.tissue.p_he[idx];
+            result.current[idx] = Supersaturation::percentage(pressure, ambient_pressure, a, b);
+            result.surface[idx] = Supersaturation::percentage(pressure, 1.0, a, b);
+
+            if result.current[idx] > result.current[result.leading_compartment] {
+                result.leading_compartment = idx;
+            }
+            if result.current[idx] > result.gf99 {
+                result.gf99 = result.current[idx];
+            }
+            if result.surface[idx] > result.surface_gf {
+                result.surface_gf = result.surface[idx];
+            }
+        }
+        result
     }
 
     /// Returns the tissue of the deco model.
@@ -393,12 +731,34 @@ impl ZHL16 {
         self.tissue
     }
 
-    pub(crate) fn add_segment(&mut self, segment: &DiveSegment, gas: &Gas, metres_per_bar: f64) {
+    /// Replace the tissue of the deco model, such as with the residual loading left by an earlier
+    /// dive.
+    pub fn set_tissue(&mut self, tissue: Tissue) {
+        self.tissue = tissue;
+    }
+
+    /// Load the tissues of the deco model with a segment of the dive.
+    /// # Arguments
+    /// * `segment` - Segment to add. Deco stops latch the first stop depth of the model
+    /// * `gas` - Gas breathed during the segment
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn add_segment(&mut self, segment: &DiveSegment, gas: &Gas, metres_per_bar: Float) {
         match segment.segment_type() {
             SegmentType::AscDesc => self.add_depth_change(segment, gas, metres_per_bar),
             SegmentType::DecoStop => {
+                if self.first_deco_depth == usize::MAX {
+                    // A stop made shallower than the ceiling still measures deco from the ceiling,
+                    // so later stops agree with the one stop_depth() would have placed.
+                    let ceiling_depth = self.stop_settings.stop_depth(common::bar_mtr(
+                        self.find_ascent_ceiling(None),
+                        metres_per_bar,
+                    ));
+                    self.update_first_deco_depth(core::cmp::max(
+                        ceiling_depth,
+                        segment.start_depth(),
+                    ));
+                }
                 self.add_bottom_segment(segment, gas, metres_per_bar);
-                self.update_first_deco_depth(segment.start_depth());
             }
             _ => {
                 self.add_bottom_segment(segment, gas, metres_per_bar);
@@ -406,21 +766,109 @@ impl ZHL16 {
         }
     }
 
-    // Special functions
-    pub(crate) fn change_gfl(&mut self, new: usize) {
-        self.gf_low = (new as f64) / 100.0
+    /// Returns the tick duration the deco model has cached decay factors for, if any.
+    pub fn tick(&self) -> Option<Duration> {
+        if self.decay_cache.tick() == Duration::from_secs(0) {
+            None
+        } else {
+            Some(self.decay_cache.tick())
+        }
+    }
+
+    /// Cache the decay factors of every compartment for a tick duration, along with their time
+    /// constants. Segments of exactly that duration then load tissues without calculating any
+    /// exponentials, and depth changes of any duration without dividing by the half-lives.
+    pub fn set_tick(&mut self, tick: Duration) {
+        self.decay_cache = DecayCache::new(tick, &self.coefficients.n2_hl, &self.coefficients.he_hl);
+    }
+
+    /// Returns the tissue loading constants of the deco model.
+    pub fn coefficients(&self) -> &'static ZHL16Coefficients {
+        self.coefficients
+    }
+
+    /// Change the tissue loading constants of the deco model. Any cached decay factors are
+    /// recalculated for the new half-lives.
+    pub fn set_coefficients(&mut self, coefficients: &'static ZHL16Coefficients) {
+        self.coefficients = coefficients;
+        if let Some(tick) = self.tick() {
+            self.set_tick(tick);
+        }
+    }
+
+    /// Returns the stop settings of the deco model.
+    pub fn stop_settings(&self) -> StopSettings {
+        self.stop_settings
+    }
+
+    /// Change the stop settings used when calculating deco stops.
+    pub fn set_stop_settings(&mut self, stop_settings: StopSettings) {
+        self.stop_settings = stop_settings;
     }
 
-    pub(crate) fn change_gfh(&mut self, new: usize) {
-        self.gf_high = (new as f64) / 100.0
+    /// Returns the GF low and GF high of the deco model (as percentages).
+    pub fn gradient_factors(&self) -> (usize, usize) {
+        (
+            (self.gf_low * 100.0 + 0.5) as usize,
+            (self.gf_high * 100.0 + 0.5) as usize,
+        )
     }
 
-    pub(crate) fn gfl(&self) -> f64 {
-        self.gf_low
+    /// Change the gradient factors used when calculating deco stops and the NDL.
+    /// # Arguments
+    /// * `gf_low` - Gradient Factor low value (as a percentage)
+    /// * `gf_high` - Gradient Factor high value (as a percentage)
+    /// # Mid-dive changes
+    /// The depth of the first deco stop is latched when that stop is added to the model, and a
+    /// change never moves it. Before then, the new values decide where the first stop will be.
+    /// Afterwards, the gradient factor still runs linearly from the new GF low at the latched depth
+    /// to the new GF high at the surface, so raising GF high shortens the remaining stops straight
+    /// away while the stops already made are unaffected. The NDL uses GF high.
+    ///
+    /// GF low and GF high only shape the [`GradientFactorProfile::Linear`] profile, and GF high the
+    /// [`GradientFactorProfile::HighOnly`] profile. Other profiles carry their own values.
+    /// # Errors
+    /// This function will return a [`GradientFactorError`] if either value is zero or above 100, or
+    /// if `gf_low` is above `gf_high`. The model is left unchanged.
+    pub fn set_gradient_factors(
+        &mut self,
+        gf_low: usize,
+        gf_high: usize,
+    ) -> Result<(), GradientFactorError> {
+        gradient_factor::validate(gf_low, gf_high)?;
+        self.gf_low = gf_low as Float / 100.0;
+        self.gf_high = gf_high as Float / 100.0;
+        Ok(())
     }
 
-    pub(crate) fn gfh(&self) -> f64 {
-        self.gf_high
+    /// Change the gradient factors to those of a preset conservatism level. See
+    /// [`ZHL16::set_gradient_factors`] for how this affects a dive in progress.
+    pub fn set_conservatism(&mut self, level: Conservatism) {
+        let (gf_low, gf_high) = level.gradient_factors();
+        self.gf_low = gf_low as Float / 100.0;
+        self.gf_high = gf_high as Float / 100.0;
+    }
+
+    /// Returns the gradient factor profile of the deco model.
+    pub fn gf_profile(&self) -> GradientFactorProfile {
+        self.gf_profile
+    }
+
+    /// Change the shape of the gradient factor used when calculating deco stops. The same rules as
+    /// [`ZHL16::set_gradient_factors`] apply to a dive in progress: the first stop depth stays
+    /// latched, and the per-stop profile counts stops from it. The NDL uses the gradient factor of
+    /// the profile at the surface.
+    pub fn set_gf_profile(&mut self, profile: GradientFactorProfile) {
+        self.gf_profile = profile;
+    }
+
+    /// Returns the depth of the first deco stop made, if one has been made.
+    pub fn first_deco_depth(&self) -> Option<usize> {
+        if self.first_deco_depth == usize::MAX {
+            None
+        } else {
+            Some(self.first_deco_depth)
+        }
     }
 
 }
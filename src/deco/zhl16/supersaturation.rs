@@ -0,0 +1,39 @@
+use crate::common::float::Float;
+use crate::deco::TISSUE_COUNT;
+
+/// How close each compartment of a model is to its M-value, as used for a tissue bar graph.
+///
+/// Each value is the supersaturation of a compartment as a percentage of the supersaturation its
+/// M-value allows, i.e. the gradient factor the compartment is at. Zero means the compartment is
+/// at ambient pressure, 100 means it is at its M-value, and a negative value means it is still
+/// on-gassing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Supersaturation {
+    /// Percentage of the M-value of each compartment at the current ambient pressure.
+    pub current: [Float; TISSUE_COUNT],
+    /// Percentage of the M-value each compartment would be at on surfacing immediately.
+    pub surface: [Float; TISSUE_COUNT],
+    /// Highest percentage at the current ambient pressure (GF99). Zero if every compartment is
+    /// still on-gassing.
+    pub gf99: Float,
+    /// Highest percentage at the surface (surface GF). Zero if every compartment would still be
+    /// on-gassing.
+    pub surface_gf: Float,
+    /// Index of the compartment closest to its M-value at the current ambient pressure.
+    pub leading_compartment: usize,
+}
+
+impl Supersaturation {
+    /// Returns the percentage of an M-value a compartment is at.
+    /// # Arguments
+    /// * `tissue_pressure` - Inert gas pressure of the compartment
+    /// * `ambient_pressure` - Ambient pressure
+    /// * `a` - A-value of the compartment
+    /// * `b` - B-value of the compartment
+    pub(crate) fn percentage(tissue_pressure: Float, ambient_pressure: Float, a: Float, b: Float) -> Float {
+        let m_value = a + ambient_pressure / b;
+        (tissue_pressure - ambient_pressure) / (m_value - ambient_pressure) * 100.0
+    }
+}
@@ -0,0 +1,917 @@
+use crate::common::ascent::AscentRates;
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use crate::common::{time_taken, DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE};
+use crate::deco::settings::StopSettings;
+use crate::deco::tissue::Tissue;
+use crate::deco::zhl16::variant::Variant;
+use crate::deco::zhl16::{
+    DecoError, GradientFactorProfile, IncrementalPlanner, PlannerPhase, Supersaturation, TIME_CAP,
+    ZHL16,
+};
+use crate::deco::TISSUE_COUNT;
+use crate::gas;
+use core::time::Duration;
+
+/// Returns a model that has descended to a depth and stayed there, with the descent counted in the
+/// bottom time.
+fn dive(gf_low: usize, gf_high: usize, depth: usize, minutes: u64, gas: &Gas) -> ZHL16 {
+    let mut deco = ZHL16::new_by_variant(Tissue::default(), gf_low, gf_high, Variant::C);
+    load(&mut deco, depth, minutes, gas);
+    deco
+}
+
+/// Descend a model to a depth and stay there, with the descent counted in the bottom time.
+fn load(deco: &mut ZHL16, depth: usize, minutes: u64, gas: &Gas) {
+    let descent = time_taken(DEFAULT_DESCENT_RATE, 0, depth);
+    let bottom = Duration::from_secs(minutes * 60)
+        .checked_sub(descent)
+        .unwrap_or_default();
+    deco.add_segment(
+        &DiveSegment::new(
+            SegmentType::AscDesc,
+            0,
+            depth,
+            descent,
+            DEFAULT_ASCENT_RATE,
+            DEFAULT_DESCENT_RATE,
+        )
+        .unwrap(),
+        gas,
+        10.0,
+    );
+    deco.add_segment(
+        &DiveSegment::new(
+            SegmentType::DiveSegment,
+            depth,
+            depth,
+            bottom,
+            DEFAULT_ASCENT_RATE,
+            DEFAULT_DESCENT_RATE,
+        )
+        .unwrap(),
+        gas,
+        10.0,
+    );
+}
+
+/// Returns a copy of a model that has stayed at its current depth for a number of minutes.
+fn stay(deco: &ZHL16, minutes: u64, gas: &Gas) -> ZHL16 {
+    let mut deco = *deco;
+    let depth = deco.depth();
+    let segment = DiveSegment::constant(
+        SegmentType::DiveSegment,
+        depth,
+        Duration::from_secs(minutes * 60),
+        DEFAULT_ASCENT_RATE,
+        DEFAULT_DESCENT_RATE,
+    );
+    deco.add_segment(&segment, gas, 10.0);
+    deco
+}
+
+/// Returns the NDL of a model in whole minutes, with the compartment controlling it.
+fn ndl_minutes(deco: &ZHL16, gas: &Gas) -> (u64, usize) {
+    let (ndl, compartment) = deco.ndl_with_compartment(gas, 10.0, 1.0).unwrap();
+    (ndl.time().as_secs() / 60, compartment)
+}
+
+/// Ascend a model to the surface the way the planner does, and return the stops made.
+fn ascend(deco: &mut ZHL16, gas: &Gas) -> Vec<DiveSegment> {
+    let mut stops = Vec::new();
+    loop {
+        let depth = deco.depth();
+        let stop_depth = deco.stop_depth(10.0);
+        if stop_depth < depth {
+            let ascent = DiveSegment::new(
+                SegmentType::AscDesc,
+                depth,
+                stop_depth,
+                time_taken(DEFAULT_ASCENT_RATE, depth, stop_depth),
+                DEFAULT_ASCENT_RATE,
+                DEFAULT_DESCENT_RATE,
+            )
+            .unwrap();
+            deco.add_segment(&ascent, gas, 10.0);
+            continue;
+        }
+        if stop_depth == 0 {
+            return stops;
+        }
+        let stop = deco
+            .next_stop(DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, gas, 10.0)
+            .unwrap();
+        deco.add_segment(&stop, gas, 10.0);
+        stops.push(stop);
+    }
+}
+
+/// Returns the total time of some segments.
+fn total(segments: &[DiveSegment]) -> Duration {
+    segments.iter().map(|t| *t.time()).sum()
+}
+
+/// Assert that stops only ever get shallower, and return their depths.
+fn stop_depths(stops: &[DiveSegment]) -> Vec<usize> {
+    let depths: Vec<usize> = stops.iter().map(|t| t.start_depth()).collect();
+    for pair in depths.windows(2) {
+        assert!(pair[1] < pair[0], "stops out of order: {:?}", depths);
+    }
+    depths
+}
+
+#[test]
+fn six_metre_last_stop_merges_final_ascent() {
+    let air = gas!(21, 0);
+    let mut deco = dive(30, 70, 45, 25, &air);
+    deco.set_stop_settings(
+        StopSettings::new(3, 6, Duration::from_secs(60), Duration::from_secs(60)).unwrap(),
+    );
+    let stops = ascend(&mut deco, &air);
+    let depths = stop_depths(&stops);
+    assert_eq!(depths.last(), Some(&6));
+    assert!(depths.iter().all(|t| t % 3 == 0 && *t >= 6));
+    assert_eq!(deco.depth(), 0);
+
+    // Off-gassing is slower at 6m, so deco takes longer than with a 3m last stop.
+    let mut three = dive(30, 70, 45, 25, &air);
+    let three_stops = ascend(&mut three, &air);
+    assert_eq!(stop_depths(&three_stops).last(), Some(&3));
+    assert!(total(&stops) > total(&three_stops));
+}
+
+#[test]
+fn one_metre_interval_stops_every_metre() {
+    let air = gas!(21, 0);
+    let mut deco = dive(30, 70, 45, 25, &air);
+    deco.set_stop_settings(
+        StopSettings::new(1, 3, Duration::from_secs(0), Duration::from_secs(1)).unwrap(),
+    );
+    let stops = ascend(&mut deco, &air);
+    let depths = stop_depths(&stops);
+    for pair in depths.windows(2) {
+        assert_eq!(pair[0] - pair[1], 1);
+    }
+    // Second resolution allows stops that are not whole minutes.
+    assert!(stops.iter().any(|t| t.time().as_secs() % 60 != 0));
+}
+
+#[test]
+fn first_stop_is_the_gf_low_ceiling_rounded_deeper() {
+    // 45 m for 25 minutes on air has a GF 30 ceiling of 20.7 m, so the first stop is at 21 m even
+    // though GF 70 would allow a shallower one.
+    let air = gas!(21, 0);
+    let deco = dive(30, 70, 45, 25, &air);
+    let ceiling = deco.ceiling_depth(Some(30), 10.0);
+    assert!((ceiling - 20.68).abs() < 0.01, "{}", ceiling);
+    assert!(deco.ceiling_depth(Some(70), 10.0) < 18.0);
+    assert_eq!(deco.stop_depth(10.0), 21);
+    assert_eq!(
+        deco.next_stop(DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, &air, 10.0)
+            .unwrap()
+            .start_depth(),
+        21
+    );
+
+    // With a 1 m stop interval the same ceiling gives a first stop at 21 m, and at 10 m intervals
+    // it is rounded down to 30 m.
+    for &(interval, expected) in &[(1, 21), (10, 30)] {
+        let mut deco = deco;
+        deco.set_stop_settings(
+            StopSettings::new(
+                interval,
+                3,
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            )
+            .unwrap(),
+        );
+        assert_eq!(deco.stop_depth(10.0), expected);
+    }
+
+    // A dive within the NDL at GF 70 needs no stop, even with a GF 30 ceiling below the surface.
+    let shallow = dive(30, 70, 18, 25, &air);
+    assert!(shallow.ceiling_depth(Some(30), 10.0) > 0.0);
+    assert!(shallow.ceiling_depth(Some(70), 10.0) == 0.0);
+    assert_eq!(shallow.stop_depth(10.0), 0);
+}
+
+#[test]
+fn ndl_matches_minute_by_minute_simulation() {
+    let gases = [gas!(21, 0), gas!(32, 0), gas!(21, 35)];
+    for gas in gases.iter() {
+        for &(gf_low, gf_high) in [(100, 100), (30, 70)].iter() {
+            for &depth in [15, 18, 21, 24, 27, 30, 33, 36, 40].iter() {
+                if gas.pp_o2(depth, 10.0) > 1.4 {
+                    continue;
+                }
+                let deco = dive(gf_low, gf_high, depth, 0, gas);
+                let (ndl, compartment) = ndl_minutes(&deco, gas);
+                assert!(
+                    ndl > 0 && ndl < TIME_CAP,
+                    "{}/{} at {}m: {}",
+                    gas.o2(),
+                    gas.he(),
+                    depth,
+                    ndl
+                );
+
+                // Still inside the limit on the last whole minute, and past it a minute later.
+                assert!(stay(&deco, ndl, gas).ndl(gas, 10.0).is_ok());
+                let exceeded = stay(&deco, ndl + 1, gas);
+                assert_eq!(
+                    exceeded.ndl(gas, 10.0).err(),
+                    Some(DecoError::NdlExceededError)
+                );
+
+                // The controlling compartment crosses its surface M-value within that minute.
+                let limit = gf_high as Float;
+                let before = stay(&deco, ndl, gas).supersaturation(10.0).surface;
+                let after = exceeded.supersaturation(10.0).surface;
+                assert!(before[compartment] < limit && after[compartment] >= limit);
+            }
+        }
+    }
+}
+
+#[test]
+fn air_ndl_is_within_published_buhlmann_ranges() {
+    // ZHL-16C at GF 100/100 with the descent counted, against the range of NDLs published for
+    // Bühlmann tables and computers at these depths.
+    let air = gas!(21, 0);
+    for &(depth, low, high) in [(18, 50, 65), (30, 13, 20), (40, 7, 11)].iter() {
+        let (ndl, _) = ndl_minutes(&dive(100, 100, depth, 0, &air), &air);
+        assert!(ndl >= low && ndl <= high, "{}m: {}", depth, ndl);
+    }
+}
+
+#[test]
+fn slower_compartments_control_shallower_ndls() {
+    let air = gas!(21, 0);
+    let mut last = (0, 0);
+    for &depth in [45, 40, 30, 24, 18, 15].iter() {
+        let (ndl, compartment) = ndl_minutes(&dive(100, 100, depth, 0, &air), &air);
+        assert!(ndl > last.0);
+        assert!(compartment >= last.1, "{}m: {}", depth, compartment);
+        last = (ndl, compartment);
+    }
+    assert!(last.1 > 0);
+}
+
+#[test]
+fn ndl_is_shorter_at_altitude() {
+    let air = gas!(21, 0);
+    let deco = dive(100, 100, 24, 0, &air);
+    let (sea, _) = deco.ndl_with_compartment(&air, 10.0, 1.0).unwrap();
+    let (altitude, _) = deco.ndl_with_compartment(&air, 10.0, 0.8).unwrap();
+    assert!(altitude.time() < sea.time());
+}
+
+#[test]
+fn ndl_is_capped_and_errors_once_exceeded() {
+    let air = gas!(21, 0);
+    let surface = ZHL16::new_by_variant(Tissue::default(), 100, 100, Variant::C);
+    assert_eq!(ndl_minutes(&surface, &air), (TIME_CAP, 0));
+
+    let deco = dive(100, 100, 30, 40, &air);
+    assert_eq!(
+        deco.ndl_with_compartment(&air, 10.0, 1.0).err(),
+        Some(DecoError::NdlExceededError)
+    );
+}
+
+#[test]
+fn stop_times_match_minute_by_minute_simulation() {
+    let cases = [(gas!(21, 0), 45, 25), (gas!(18, 45), 60, 25)];
+    for (gas, depth, minutes) in cases.iter() {
+        let mut deco = dive(30, 70, *depth, *minutes, gas);
+        let mut stops = 0;
+        loop {
+            let stop_depth = deco.stop_depth(10.0);
+            if stop_depth == 0 {
+                break;
+            }
+            let ascent = DiveSegment::new(
+                SegmentType::AscDesc,
+                deco.depth(),
+                stop_depth,
+                time_taken(DEFAULT_ASCENT_RATE, deco.depth(), stop_depth),
+                DEFAULT_ASCENT_RATE,
+                DEFAULT_DESCENT_RATE,
+            )
+            .unwrap();
+            deco.add_segment(&ascent, gas, 10.0);
+            let stop = deco
+                .next_stop(DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, gas, 10.0)
+                .unwrap();
+
+            // The shortest whole-minute stop after which the diver may leave the stop.
+            let cleared = |minutes: u64| {
+                let mut held = deco;
+                let segment = DiveSegment::constant(
+                    SegmentType::DecoStop,
+                    stop_depth,
+                    Duration::from_secs(minutes * 60),
+                    DEFAULT_ASCENT_RATE,
+                    DEFAULT_DESCENT_RATE,
+                );
+                held.add_segment(&segment, gas, 10.0);
+                held.stop_depth(10.0) < stop_depth
+            };
+            let brute = (1..TIME_CAP).find(|&t| cleared(t)).unwrap();
+            assert_eq!(stop.time().as_secs(), brute * 60, "stop at {}m", stop_depth);
+
+            deco.add_segment(&stop, gas, 10.0);
+            stops += 1;
+        }
+        assert!(stops > 3);
+    }
+}
+
+#[test]
+fn incremental_planner_keeps_last_result_while_working() {
+    let air = gas!(21, 0);
+    let shallow = dive(100, 100, 18, 20, &air);
+    let deep = dive(30, 70, 45, 25, &air);
+    let mut planner = IncrementalPlanner::new(&shallow, &air);
+    assert_eq!(planner.phase(), PlannerPhase::Idle);
+    assert!(!planner.step(100));
+    assert!(planner.result().is_none());
+
+    planner
+        .begin(
+            &shallow,
+            &air,
+            DEFAULT_ASCENT_RATE,
+            DEFAULT_DESCENT_RATE,
+            10.0,
+        )
+        .unwrap();
+    assert!(!planner.step(TISSUE_COUNT - 1));
+    assert!(planner.result().is_none());
+    assert!(planner.step(1));
+    let first = planner.result().unwrap();
+    assert!(first.next_stop().segment_type() == SegmentType::NoDeco);
+
+    // A new calculation leaves the last result in place until it completes.
+    planner
+        .begin(&deep, &air, DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, 10.0)
+        .unwrap();
+    assert!(!planner.step(2));
+    assert_eq!(planner.result().unwrap().tts(), first.tts());
+    assert!(planner.step(usize::MAX));
+    let second = planner.result().unwrap();
+    assert!(second.next_stop().segment_type() == SegmentType::DecoStop);
+
+    // The result matches the first stop of a full ascent.
+    let mut reference = deep;
+    let stops = ascend(&mut reference, &air);
+    assert_eq!(second.next_stop().start_depth(), stops[0].start_depth());
+    assert_eq!(second.next_stop().time(), stops[0].time());
+}
+
+#[test]
+fn incremental_planner_does_one_stop_per_step_on_deep_trimix() {
+    let trimix = gas!(18, 45);
+    let deco = dive(30, 70, 60, 25, &trimix);
+    let mut planner = IncrementalPlanner::new(&deco, &trimix);
+    planner
+        .begin(
+            &deco,
+            &trimix,
+            DEFAULT_ASCENT_RATE,
+            DEFAULT_DESCENT_RATE,
+            10.0,
+        )
+        .unwrap();
+    assert_eq!(planner.phase(), PlannerPhase::Stops);
+
+    let mut steps = 0;
+    while !planner.step(1) {
+        assert_eq!(planner.phase(), PlannerPhase::Stops);
+        steps += 1;
+        assert!(steps < 1000, "calculation did not end");
+    }
+    steps += 1;
+    assert_eq!(planner.phase(), PlannerPhase::Done);
+    assert_eq!(planner.error(), None);
+
+    // One step per stop, plus the step that finds the diver clear to surface.
+    let mut reference = deco;
+    let stops = ascend(&mut reference, &trimix);
+    assert!(stops.len() > 5);
+    assert_eq!(steps, stops.len() + 1);
+
+    let result = planner.result().unwrap();
+    assert_eq!(result.next_stop().start_depth(), stops[0].start_depth());
+    assert_eq!(result.next_stop().time(), stops[0].time());
+    let ascent = time_taken(DEFAULT_ASCENT_RATE, 60, 0);
+    // The planner rounds each leg of the ascent, so allow for a second of drift.
+    let expected = total(&stops) + ascent;
+    let drift = if result.tts() > expected {
+        result.tts() - expected
+    } else {
+        expected - result.tts()
+    };
+    assert!(drift <= Duration::from_secs(1));
+}
+
+#[test]
+fn incremental_planner_does_one_compartment_per_ndl_step() {
+    let air = gas!(21, 0);
+    let deco = dive(100, 100, 30, 10, &air);
+    let mut planner = IncrementalPlanner::new(&deco, &air);
+    planner
+        .begin(&deco, &air, DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, 10.0)
+        .unwrap();
+    for _ in 1..TISSUE_COUNT {
+        assert!(!planner.step(1));
+        assert_eq!(planner.phase(), PlannerPhase::Ndl);
+    }
+    assert!(planner.step(1));
+    assert!(planner.step(1));
+
+    let result = planner.result().unwrap();
+    assert!(result.next_stop().segment_type() == SegmentType::NoDeco);
+    assert_eq!(
+        *result.next_stop().time(),
+        *deco.ndl(&air, 10.0).unwrap().time()
+    );
+}
+
+#[test]
+fn incremental_planner_rejects_ascent_rate_without_abandoning_work() {
+    let air = gas!(21, 0);
+    let deco = dive(30, 70, 45, 25, &air);
+    let mut planner = IncrementalPlanner::new(&deco, &air);
+    planner
+        .begin(&deco, &air, DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, 10.0)
+        .unwrap();
+    assert!(!planner.step(1));
+
+    assert_eq!(
+        planner.begin(&deco, &air, 9, DEFAULT_DESCENT_RATE, 10.0),
+        Err(DecoError::AscentRateError)
+    );
+    assert_eq!(planner.phase(), PlannerPhase::Stops);
+    assert!(planner.step(usize::MAX));
+}
+
+#[test]
+fn incremental_planner_ends_at_a_stop_that_never_clears() {
+    // Air at 6 m leaves 1.21 bar of nitrogen in a compartment at equilibrium, while GF 10 only
+    // allows the slowest compartment about 1.03 bar at the surface. The stop at 6 m can never
+    // clear, so it is capped and the calculation ends there instead of repeating it.
+    let air = gas!(21, 0);
+    let mut deco = dive(10, 10, 40, 60, &air);
+    let one_minute = Duration::from_secs(60);
+    deco.set_stop_settings(StopSettings::new(3, 6, one_minute, one_minute).unwrap());
+    let mut planner = IncrementalPlanner::new(&deco, &air);
+    planner
+        .begin(&deco, &air, DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, 10.0)
+        .unwrap();
+    let mut steps = 0;
+    while !planner.step(1) {
+        steps += 1;
+        assert!(steps < 100, "calculation did not end");
+    }
+    let result = planner.result().unwrap();
+    assert!(result.capped());
+    assert!(result.tts() >= Duration::from_secs(TIME_CAP * 60));
+    assert!(result.next_stop().segment_type() == SegmentType::DecoStop);
+
+    // The same dive at GF 30/70 clears every stop.
+    let mut cleared = dive(30, 70, 40, 60, &air);
+    cleared.set_stop_settings(StopSettings::new(3, 6, one_minute, one_minute).unwrap());
+    planner
+        .begin(
+            &cleared,
+            &air,
+            DEFAULT_ASCENT_RATE,
+            DEFAULT_DESCENT_RATE,
+            10.0,
+        )
+        .unwrap();
+    assert!(planner.step(usize::MAX));
+    assert!(!planner.result().unwrap().capped());
+}
+
+#[test]
+fn incremental_planner_ascends_at_the_rate_of_each_band() {
+    // 9 m min^-1 to 6 m, 3 m min^-1 to 3 m and 1 m min^-1 to the surface.
+    let mut rates = AscentRates::new(-9).unwrap();
+    rates.add_band(3, -1).unwrap();
+    rates.add_band(6, -3).unwrap();
+    let air = gas!(21, 0);
+
+    // From 30 m within the NDL: 160 seconds to 6 m, 60 to 3 m and 180 to the surface.
+    let deco = dive(100, 100, 30, 10, &air);
+    let mut planner = IncrementalPlanner::new(&deco, &air);
+    planner.begin_with_rates(&deco, &air, &rates, DEFAULT_DESCENT_RATE, 10.0);
+    assert!(planner.step(usize::MAX));
+    let result = planner.result().unwrap();
+    assert!(result.next_stop().segment_type() == SegmentType::NoDeco);
+    assert_eq!(result.tts(), Duration::from_secs(400));
+    assert!(!result.capped());
+
+    // With deco, the first stop is the one the model finds with the same rates, and the slower
+    // shallow rates lengthen the time to surface.
+    let deco = dive(30, 70, 45, 25, &air);
+    planner.begin_with_rates(&deco, &air, &rates, DEFAULT_DESCENT_RATE, 10.0);
+    assert!(planner.step(usize::MAX));
+    let banded = planner.result().unwrap();
+    let stop = deco
+        .next_stop_with_rates(&rates, DEFAULT_DESCENT_RATE, &air, 10.0)
+        .unwrap();
+    assert_eq!(banded.next_stop().start_depth(), stop.start_depth());
+    assert_eq!(banded.next_stop().time(), stop.time());
+    planner
+        .begin(&deco, &air, -9, DEFAULT_DESCENT_RATE, 10.0)
+        .unwrap();
+    assert!(planner.step(usize::MAX));
+    assert!(banded.tts() > planner.result().unwrap().tts());
+}
+
+/// Ascend from a decompression dive with a gradient factor profile, and return the stops made.
+fn ascend_with_profile(
+    gf_low: usize,
+    gf_high: usize,
+    profile: Option<GradientFactorProfile>,
+) -> Vec<DiveSegment> {
+    let air = gas!(21, 0);
+    let mut deco = dive(gf_low, gf_high, 45, 30, &air);
+    if let Some(profile) = profile {
+        deco.set_gf_profile(profile);
+    }
+    ascend(&mut deco, &air)
+}
+
+/// Assert that two ascents make the same stops, for the same times.
+fn assert_same_stops(a: &[DiveSegment], b: &[DiveSegment]) {
+    assert_eq!(stop_depths(a), stop_depths(b));
+    for (x, y) in a.iter().zip(b) {
+        assert_eq!(x.time(), y.time(), "{}m", x.start_depth());
+    }
+}
+
+#[test]
+fn constant_profiles_match_linear_with_equal_gradient_factors() {
+    let linear = ascend_with_profile(50, 50, None);
+    assert!(!linear.is_empty());
+
+    let high_only = ascend_with_profile(20, 50, Some(GradientFactorProfile::HighOnly));
+    let table = GradientFactorProfile::table(&[(0, 50)]).unwrap();
+    let per_stop = GradientFactorProfile::per_stop(&[50]).unwrap();
+    assert_same_stops(&linear, &high_only);
+    assert_same_stops(&linear, &ascend_with_profile(20, 80, Some(table)));
+    assert_same_stops(&linear, &ascend_with_profile(20, 80, Some(per_stop)));
+}
+
+#[test]
+fn lower_gradient_factors_deepen_and_lengthen_stops() {
+    let low = ascend_with_profile(30, 30, None);
+    let high = ascend_with_profile(80, 80, None);
+    assert!(stop_depths(&low)[0] > stop_depths(&high)[0]);
+    assert!(total(&low) > total(&high));
+
+    // The first stop is placed with the first gradient factor, and later stops use the second.
+    let per_stop = GradientFactorProfile::per_stop(&[30, 80]).unwrap();
+    let deep_first = ascend_with_profile(80, 80, Some(per_stop));
+    assert_eq!(stop_depths(&deep_first)[0], stop_depths(&low)[0]);
+    assert!(total(&high) < total(&deep_first) && total(&deep_first) < total(&low));
+
+    // A table that is conservative deep and liberal shallow sits between the two extremes.
+    let table = GradientFactorProfile::table(&[(0, 80), (40, 30)]).unwrap();
+    let sloped = total(&ascend_with_profile(80, 80, Some(table)));
+    assert!(total(&high) < sloped && sloped < total(&low));
+}
+
+/// Returns the stops of a dive at 40 m for 20 minutes then 25 m for 15 minutes on EAN32, at GF 10/90
+/// with a stop resolution of one second.
+fn multi_level_stops(deco: &mut ZHL16) -> Vec<DiveSegment> {
+    let nitrox = gas!(32, 0);
+    deco.set_stop_settings(
+        StopSettings::new(3, 3, Duration::from_secs(0), Duration::from_secs(1)).unwrap(),
+    );
+    load(deco, 40, 20, &nitrox);
+    let ascent = DiveSegment::new(
+        SegmentType::AscDesc,
+        40,
+        25,
+        time_taken(DEFAULT_ASCENT_RATE, 40, 25),
+        DEFAULT_ASCENT_RATE,
+        DEFAULT_DESCENT_RATE,
+    )
+    .unwrap();
+    deco.add_segment(&ascent, &nitrox, 10.0);
+    *deco = stay(deco, 15, &nitrox);
+    ascend(deco, &nitrox)
+}
+
+#[test]
+fn stops_get_shallower_on_multi_level_repetitive_dives() {
+    // The GF 10 ceiling of 12.6 m at 25 m puts the first stop at 15 m, which clears at once. The
+    // gradient factor line from there clears the 12 m and 9 m stops too.
+    let mut first = ZHL16::new_by_variant(Tissue::default(), 10, 90, Variant::C);
+    let stops = multi_level_stops(&mut first);
+    assert_eq!(stop_depths(&stops), vec![15, 6, 3]);
+
+    // The same dive again after 45 minutes at the surface, starting from the residual loading.
+    let mut second = ZHL16::new_by_variant(first.tissue(), 10, 90, Variant::C);
+    let interval = DiveSegment::constant(
+        SegmentType::DiveSegment,
+        0,
+        Duration::from_secs(45 * 60),
+        DEFAULT_ASCENT_RATE,
+        DEFAULT_DESCENT_RATE,
+    );
+    second.add_segment(&interval, &gas!(21, 0), 10.0);
+    // The residual loading lengthens the stops, but never makes a later stop deeper.
+    let repetitive = multi_level_stops(&mut second);
+    assert_eq!(stop_depths(&repetitive), vec![15, 6, 3]);
+    assert!(total(&repetitive) > total(&stops));
+}
+
+/// Returns a model at the surface with the first compartment at the given pressures, and every other
+/// compartment saturated at the surface.
+fn first_compartment(variant: Variant, p_n2: Float, p_he: Float) -> ZHL16 {
+    let mut deco = ZHL16::new_by_variant(Tissue::default(), 100, 100, variant);
+    let mut tissue = deco.tissue();
+    tissue.p_n2[0] = p_n2;
+    tissue.p_he[0] = p_he;
+    tissue.p_t[0] = p_n2 + p_he;
+    deco.set_tissue(tissue);
+    deco
+}
+
+#[test]
+fn supersaturation_matches_hand_calculated_m_values() {
+    // ZHL-16B compartment 1: a = 1.1696, b = 0.5578, giving an M-value of 2.9624 bar at the surface.
+    let nitrogen = first_compartment(Variant::B, 1.5, 0.0).supersaturation(10.0);
+    assert!(
+        (nitrogen.current[0] - 25.48).abs() < 0.01,
+        "{}",
+        nitrogen.current[0]
+    );
+    assert!((nitrogen.surface[0] - nitrogen.current[0]).abs() < 1e-4);
+    assert_eq!(nitrogen.leading_compartment, 0);
+    assert!((nitrogen.gf99 - nitrogen.current[0]).abs() < 1e-4);
+
+    // Helium weights the coefficients by pressure: a = 1.3194, b = 0.5309, M-value 3.2031 bar.
+    let trimix = first_compartment(Variant::B, 1.0, 0.5).supersaturation(10.0);
+    assert!(
+        (trimix.current[0] - 22.70).abs() < 0.01,
+        "{}",
+        trimix.current[0]
+    );
+
+    // Saturated at the surface, every compartment is below ambient pressure by the water vapour.
+    let saturated =
+        ZHL16::new_by_variant(Tissue::default(), 100, 100, Variant::C).supersaturation(10.0);
+    assert!(saturated.current.iter().all(|&t| t < 0.0));
+    assert_eq!((saturated.gf99, saturated.surface_gf), (0.0, 0.0));
+}
+
+#[test]
+fn supersaturation_at_the_ceiling_is_the_gradient_factor() {
+    let air = gas!(21, 0);
+    let deco = dive(100, 100, 40, 30, &air);
+    let result = deco.supersaturation(10.0);
+    assert!(result.gf99 < result.surface_gf);
+    assert!(result.surface_gf > 100.0);
+
+    // The Bühlmann ceiling at a gradient factor is where the leading compartment reaches it.
+    for &gf in [0.3, 0.7, 1.0].iter() {
+        let ceiling = deco.find_ascent_ceiling(Some(gf));
+        let leading = (0..TISSUE_COUNT)
+            .map(|idx| {
+                let tissue = &deco.tissue;
+                Supersaturation::percentage(
+                    tissue.p_n2[idx] + tissue.p_he[idx],
+                    ceiling,
+                    deco.tissue_a_value(tissue, idx),
+                    deco.tissue_b_value(tissue, idx),
+                )
+            })
+            .fold(Float::MIN, Float::max);
+        assert!(
+            (leading - gf * 100.0).abs() < 0.01,
+            "GF {}: {}",
+            gf,
+            leading
+        );
+    }
+}
+
+#[test]
+fn surface_gf_crosses_100_at_the_ndl() {
+    let air = gas!(21, 0);
+    let deco = dive(100, 100, 30, 10, &air);
+    let (ndl, _) = ndl_minutes(&deco, &air);
+    let within = stay(&deco, ndl, &air).supersaturation(10.0);
+    let exceeded = stay(&deco, ndl + 1, &air).supersaturation(10.0);
+    assert!(within.surface_gf <= 100.0, "{}", within.surface_gf);
+    assert!(exceeded.surface_gf > 100.0, "{}", exceeded.surface_gf);
+    // At depth the tissues are still on-gassing, so GF99 lags behind.
+    assert!(exceeded.gf99 < exceeded.surface_gf);
+    let leading = exceeded.current[exceeded.leading_compartment];
+    assert_eq!(exceeded.gf99, leading.max(0.0));
+}
+
+#[test]
+fn ceiling_matches_hand_calculated_buhlmann_ceilings() {
+    // ZHL-16B compartment 1 at 4 bar of nitrogen tolerates (P - a * GF) / (GF / b + 1 - GF).
+    let deco = first_compartment(Variant::B, 4.0, 0.0);
+    for &(gf, bar) in [(100, 1.5788), (50, 2.4458)].iter() {
+        assert!((deco.ceiling(Some(gf)) - bar).abs() < 1e-3, "GF {}", gf);
+        let metres = (bar - 1.0) * 10.0;
+        assert!(
+            (deco.ceiling_depth(Some(gf), 10.0) - metres).abs() < 0.01,
+            "GF {}",
+            gf
+        );
+    }
+    // Salt water takes more depth per bar.
+    assert!((deco.ceiling_depth(Some(100), 10.3) - 5.962).abs() < 0.01);
+    // Without an override, the next stop is placed at GF low.
+    let mut conservative = deco;
+    conservative.set_gradient_factors(50, 100).unwrap();
+    assert_eq!(conservative.ceiling(None), deco.ceiling(Some(50)));
+
+    // A ceiling above the surface is reported in bar, but clamped to the surface in metres.
+    let shallow = first_compartment(Variant::B, 2.0, 0.0);
+    assert!(shallow.ceiling(Some(100)) < 1.0);
+    assert_eq!(shallow.ceiling_depth(Some(100), 10.0), 0.0);
+}
+
+#[test]
+fn tissue_reports_each_inert_gas() {
+    let trimix = gas!(18, 45);
+    let deco = dive(30, 70, 50, 20, &trimix);
+    let tissue = deco.tissue();
+    for idx in 0..TISSUE_COUNT {
+        assert_eq!(tissue.p_he()[idx], tissue.p_he[idx]);
+        assert_eq!(tissue.p_n2()[idx], tissue.p_n2[idx]);
+        assert!((tissue.p_t()[idx] - tissue.p_n2[idx] - tissue.p_he[idx]).abs() < 1e-4);
+    }
+    // The fastest helium compartment has loaded far more than the nitrogen one, which started
+    // saturated with nitrogen from the surface.
+    assert!(tissue.p_he()[0] > 2.0);
+    assert!(tissue.p_he()[0] > tissue.p_n2()[0]);
+    assert!(dive(30, 70, 50, 20, &gas!(21, 0))
+        .tissue()
+        .p_he()
+        .iter()
+        .all(|&t| t == 0.0));
+}
+
+/// NDLs (measured in minutes) at GF 30/70 of a gas, given by its O2 and helium, at each depth.
+type NdlReference = ((usize, usize), &'static [(usize, u64)]);
+
+/// NDLs of the reference profiles, calculated in double precision.
+const NDL_REFERENCE: [NdlReference; 3] = [
+    (
+        (21, 0),
+        &[
+            (12, 84),
+            (15, 47),
+            (18, 28),
+            (21, 18),
+            (24, 13),
+            (27, 10),
+            (30, 8),
+            (33, 6),
+            (36, 5),
+            (40, 4),
+        ],
+    ),
+    (
+        (32, 0),
+        &[
+            (12, 226),
+            (15, 91),
+            (18, 55),
+            (21, 34),
+            (24, 23),
+            (27, 16),
+            (30, 13),
+            (33, 10),
+        ],
+    ),
+    (
+        (21, 35),
+        &[
+            (12, 65),
+            (15, 35),
+            (18, 21),
+            (21, 14),
+            (24, 10),
+            (27, 7),
+            (30, 6),
+            (33, 5),
+            (36, 4),
+            (40, 3),
+        ],
+    ),
+];
+
+/// An ascent at GF 30/70 with a stop resolution of one second.
+struct ScheduleReference {
+    /// O2 and helium of the gas breathed throughout.
+    gas: (usize, usize),
+    /// Bottom depth.
+    depth: usize,
+    /// Bottom time, including the descent (measured in minutes).
+    minutes: u64,
+    /// Depth of the first stop.
+    first_depth: usize,
+    /// Duration of the first stop (measured in seconds).
+    first_time: u64,
+    /// Number of stops.
+    stops: usize,
+    /// Time to surface (measured in seconds).
+    tts: u64,
+}
+
+/// Ascents of the reference profiles, calculated in double precision.
+const SCHEDULE_REFERENCE: [ScheduleReference; 3] = [
+    ScheduleReference {
+        gas: (21, 0),
+        depth: 45,
+        minutes: 25,
+        first_depth: 21,
+        first_time: 57,
+        stops: 7,
+        tts: 3885,
+    },
+    ScheduleReference {
+        gas: (21, 35),
+        depth: 60,
+        minutes: 20,
+        first_depth: 30,
+        first_time: 0,
+        stops: 10,
+        tts: 5990,
+    },
+    ScheduleReference {
+        gas: (18, 45),
+        depth: 60,
+        minutes: 20,
+        first_depth: 30,
+        first_time: 37,
+        stops: 10,
+        tts: 9175,
+    },
+];
+
+/// Calculate the NDLs and ascents of the reference profiles, and compare each against the double
+/// precision result. `tts_drift` is how far the TTS may stray.
+fn check_references(tts_drift: Duration) {
+    for ((o2, he), ndls) in NDL_REFERENCE.iter() {
+        let gas = gas!(*o2, *he);
+        for &(depth, ndl) in ndls.iter() {
+            let (minutes, _) = ndl_minutes(&dive(30, 70, depth, 0, &gas), &gas);
+            assert_eq!(minutes, ndl, "{}/{} at {}m", o2, he, depth);
+        }
+    }
+
+    for reference in SCHEDULE_REFERENCE.iter() {
+        let (o2, he) = reference.gas;
+        let gas = gas!(o2, he);
+        let mut deco = dive(30, 70, reference.depth, reference.minutes, &gas);
+        deco.set_stop_settings(
+            StopSettings::new(3, 3, Duration::from_secs(0), Duration::from_secs(1)).unwrap(),
+        );
+        let stops = ascend(&mut deco, &gas);
+        assert_eq!(stops[0].start_depth(), reference.first_depth);
+        assert_eq!(stops[0].time().as_secs(), reference.first_time);
+        assert_eq!(stops.len(), reference.stops);
+
+        let tts = total(&stops) + time_taken(DEFAULT_ASCENT_RATE, reference.depth, 0);
+        let reference_tts = Duration::from_secs(reference.tts);
+        let drift = core::cmp::max(tts, reference_tts) - core::cmp::min(tts, reference_tts);
+        assert!(
+            drift <= tts_drift,
+            "{}/{} at {}m: {:?}",
+            o2,
+            he,
+            reference.depth,
+            tts
+        );
+    }
+}
+
+#[test]
+#[cfg(not(feature = "single-precision"))]
+fn double_precision_matches_reference_profiles() {
+    check_references(Duration::from_secs(0));
+}
+
+#[test]
+#[cfg(feature = "single-precision")]
+fn single_precision_stays_within_bounds_of_double_precision() {
+    // NDLs, first stops and the number of stops are identical. Rounding of the stop times may move
+    // the TTS by a few seconds.
+    check_references(Duration::from_secs(5));
+}
@@ -1,3 +1,7 @@
+use crate::deco::zhl16::coefficients::{
+    ZHL16Coefficients, ZHL16B_COEFFICIENTS, ZHL16C_COEFFICIENTS,
+};
+
 /// Represents the variants of ZHL16 defined in the library.
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -7,3 +11,25 @@ pub enum Variant {
     /// ZHL-16C
     C,
 }
+
+impl Variant {
+    /// Returns the tissue loading constants of the variant.
+    pub fn coefficients(&self) -> &'static ZHL16Coefficients {
+        match self {
+            Variant::B => &ZHL16B_COEFFICIENTS,
+            Variant::C => &ZHL16C_COEFFICIENTS,
+        }
+    }
+
+    /// Returns the variant a set of tissue loading constants belongs to, if it is one of the
+    /// library's own tables.
+    pub fn of(coefficients: &ZHL16Coefficients) -> Option<Self> {
+        if core::ptr::eq(coefficients, &ZHL16B_COEFFICIENTS) {
+            Some(Variant::B)
+        } else if core::ptr::eq(coefficients, &ZHL16C_COEFFICIENTS) {
+            Some(Variant::C)
+        } else {
+            None
+        }
+    }
+}
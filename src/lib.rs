@@ -1,21 +1,31 @@
 //! Diver decompression library. Includes ZHL-16 (B/C)
-#![no_std]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![feature(core_intrinsics)]
-#![cfg_attr(not(feature = "std"), allow(unused_imports), allow(dead_code))]
+#![allow(internal_features)]
+#![cfg_attr(not(any(feature = "std", test)), allow(unused_imports), allow(dead_code))]
 
+#[cfg(not(any(feature = "std", test)))]
 use core::intrinsics;
+use core::mem;
+#[cfg(not(any(feature = "std", test)))]
 use core::panic::PanicInfo;
 
-use crate::common::{DiveSegment, Gas, SegmentType};
-use crate::deco::zhl16::util::{ZHL16C_N2_A, ZHL16C_N2_B, ZHL16C_N2_HALFLIFE, ZHL16C_HE_A, ZHL16C_HE_B, ZHL16C_HE_HALFLIFE};
-use crate::deco::Tissue;
-use crate::deco::zhl16::variant::Variant::C;
+use crate::common::{DiveSegment, Float, Gas, SegmentType};
+use crate::deco::zhl16::coefficients::ZHL16C_COEFFICIENTS;
+use crate::deco::{StopSettings, Tissue, TISSUE_COUNT};
 use core::time::Duration;
-use crate::common::dive_segment::SegmentType::DecoStop;
-use crate::deco::zhl16::ZHL16;
+use crate::common::ascent::{AscentBand, AscentRates, AscentRatesError, ASCENT_BAND_CAPACITY};
+use crate::plan::ndl::{NdlEntry, NdlTable, NdlTableError};
+use crate::deco::zhl16::{Conservatism, DecoError, DecoResult, GradientFactorProfile, IncrementalPlanner, Supersaturation, ZHL16, GF_STOP_CAPACITY, GF_TABLE_CAPACITY};
 
 pub mod common;
 pub mod deco;
+pub mod plan;
+
+#[cfg(test)]
+mod tests;
+
+use crate::plan::ndl::NDL_TABLE_CAPACITY;
 
 #[repr(C)]
 pub struct CDiveSegment {
@@ -33,6 +43,90 @@ pub struct CDiveSegment {
     descent_rate: isize,
 }
 
+/// The no-decompression limit at one depth on one gas, as written by [`ndl_table`].
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct CNdlEntry {
+    /// Depth of the entry.
+    depth: usize,
+    /// Gas breathed on the descent and at the depth.
+    gas: Gas,
+    /// Time that can be spent at the depth once it is reached, or zero if the diver would already
+    /// need to decompress (milliseconds)
+    ndl: u64,
+}
+
+impl From<&NdlEntry> for CNdlEntry {
+    fn from(entry: &NdlEntry) -> Self {
+        Self {
+            depth: entry.depth(),
+            gas: *entry.gas(),
+            ndl: entry.ndl().as_millis() as u64,
+        }
+    }
+}
+
+/// Status returned by every function of the C API.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum CStatus {
+    /// The function completed successfully.
+    Success,
+    /// A required pointer was null.
+    NullPointerError,
+    /// The percentage fractions of a gas do not add up to 100.
+    GasFractionError,
+    /// A depth exceeds [`MAX_DEPTH`].
+    DepthError,
+    /// An ascent rate is not negative, a descent rate is not positive, or either exceeds [`MAX_RATE`].
+    RateError,
+    /// A duration is zero.
+    DurationError,
+    /// Caller storage or an output buffer is too small.
+    SizeError,
+    /// Caller storage is not aligned as required.
+    AlignmentError,
+    /// The stop settings are invalid. See [`StopSettings::new`].
+    StopSettingsError,
+    /// The diver has exceeded the no-decompression limit.
+    NdlExceededError,
+    /// The diver is within the no-decompression limit, so has no deco stop to make.
+    NoStopError,
+    /// No calculation has completed yet.
+    NoResultError,
+    /// The type of a segment is inconsistent with its depths. See [`DiveSegment::new`].
+    SegmentError,
+    /// The depth of water inducing 1 bar of pressure is not a positive number.
+    MetresPerBarError,
+    /// A gradient factor is zero or above 100, or GF low is above GF high.
+    GradientFactorError,
+    /// An argument is not one of the values the function accepts.
+    InvalidArgument,
+    /// An altitude is above the highest an NDL table can be calculated for.
+    AltitudeError,
+}
+
+impl From<DecoError> for CStatus {
+    fn from(value: DecoError) -> Self {
+        match value {
+            DecoError::AscentRateError => CStatus::RateError,
+            DecoError::NdlExceededError => CStatus::NdlExceededError,
+            DecoError::NoStopError => CStatus::NoStopError,
+        }
+    }
+}
+
+/// Version of the C API. This is increased whenever a function signature or the layout of a struct
+/// shared with C changes. The layouts of the deco model and planner are private to the library, so
+/// C code never needs recompiling when they change.
+pub const ABI_VERSION: u32 = 5;
+
+/// Deepest depth accepted by the C API (measured in metres).
+pub const MAX_DEPTH: usize = 1000;
+
+/// Fastest ascent or descent rate accepted by the C API (measured in m min^-1).
+pub const MAX_RATE: isize = 100;
+
 impl From<DiveSegment> for CDiveSegment {
     fn from(value: DiveSegment) -> Self {
         CDiveSegment {
@@ -46,54 +140,888 @@ impl From<DiveSegment> for CDiveSegment {
     }
 }
 
+// Every function of the C API validates its arguments and returns a [`CStatus`] instead of panicking,
+// as the panic handler aborts the whole firmware. Outputs are only written on success.
+//
+// Stack usage below is the deepest frame chain of each function, measured on an optimised
+// x86_64 build with `-Z emit-stack-sizes`, excluding the floating point routines from libm. The
+// figures are host measurements only and carry no guarantee for the ESP32: Xtensa frame sizes
+// differ, so measure them with the target toolchain before relying on them.
+
+#[cfg(not(any(feature = "std", test)))]
 #[panic_handler]
 #[allow(unused_unsafe)]
 fn panic(_: &PanicInfo) -> ! {
     unsafe { intrinsics::abort() }
 }
 
+/// Convert a `Result` from the C API helpers into the status returned to C.
+fn status(result: Result<(), CStatus>) -> CStatus {
+    match result {
+        Ok(()) => CStatus::Success,
+        Err(e) => e,
+    }
+}
+
+/// Dereference a pointer passed in from C.
+unsafe fn from_ptr<'a, T>(ptr: *const T) -> Result<&'a T, CStatus> {
+    ptr.as_ref().ok_or(CStatus::NullPointerError)
+}
+
+/// Mutably dereference a pointer passed in from C.
+unsafe fn from_mut_ptr<'a, T>(ptr: *mut T) -> Result<&'a mut T, CStatus> {
+    ptr.as_mut().ok_or(CStatus::NullPointerError)
+}
+
+/// Dereference a gas passed in from C, checking that its fractions add up to 100.
+unsafe fn gas_from_ptr<'a>(ptr: *const Gas) -> Result<&'a Gas, CStatus> {
+    let gas = from_ptr(ptr)?;
+    match Gas::new(gas.o2(), gas.he(), gas.n2()) {
+        Ok(_) => Ok(gas),
+        Err(_) => Err(CStatus::GasFractionError),
+    }
+}
+
+/// Check that caller storage can hold a `T`.
+fn check_storage<T>(ptr: *mut T, size: usize) -> Result<(), CStatus> {
+    if ptr.is_null() {
+        return Err(CStatus::NullPointerError);
+    }
+    if size < mem::size_of::<T>() {
+        return Err(CStatus::SizeError);
+    }
+    // Alignments are powers of two, so the low bits of an aligned address are zero.
+    if (ptr as usize) & (mem::align_of::<T>() - 1) != 0 {
+        return Err(CStatus::AlignmentError);
+    }
+    Ok(())
+}
+
+/// Convert a segment passed in from C, checking its depths.
+fn segment_from_c(segment: &CDiveSegment) -> Result<DiveSegment, CStatus> {
+    check_depth(segment.start_depth)?;
+    check_depth(segment.end_depth)?;
+    DiveSegment::new(
+        segment.segment_type,
+        segment.start_depth,
+        segment.end_depth,
+        Duration::from_millis(segment.time),
+        segment.ascent_rate,
+        segment.descent_rate,
+    )
+    .map_err(|_| CStatus::SegmentError)
+}
+
+/// Check that the depth of water inducing 1 bar of pressure is positive and finite.
+fn check_metres_per_bar(metres_per_bar: Float) -> Result<(), CStatus> {
+    if !(metres_per_bar.is_finite() && metres_per_bar > 0.0) {
+        return Err(CStatus::MetresPerBarError);
+    }
+    Ok(())
+}
+
+/// Check that a depth is within [`MAX_DEPTH`].
+fn check_depth(depth: usize) -> Result<(), CStatus> {
+    if depth > MAX_DEPTH {
+        return Err(CStatus::DepthError);
+    }
+    Ok(())
+}
+
+/// Build ascent rates passed in from C, checking every rate and depth.
+unsafe fn ascent_rates_from_ptr(ascent_rate: isize, bands: *const AscentBand, band_count: usize, descent_rate: isize) -> Result<AscentRates, CStatus> {
+    check_rates(ascent_rate, descent_rate)?;
+    if band_count > ASCENT_BAND_CAPACITY {
+        return Err(CStatus::SizeError);
+    }
+    if band_count > 0 && bands.is_null() {
+        return Err(CStatus::NullPointerError);
+    }
+
+    let mut rates = AscentRates::new(ascent_rate).map_err(|_| CStatus::RateError)?;
+    if band_count > 0 {
+        for band in core::slice::from_raw_parts(bands, band_count) {
+            check_rates(band.rate(), descent_rate)?;
+            check_depth(band.depth())?;
+            rates.add_band(band.depth(), band.rate()).map_err(|e| match e {
+                AscentRatesError::RateError => CStatus::RateError,
+                AscentRatesError::DepthError => CStatus::DepthError,
+                AscentRatesError::CapacityError => CStatus::SizeError,
+            })?;
+        }
+    }
+    Ok(rates)
+}
+
+/// Check that an ascent rate is negative, a descent rate positive, and neither exceeds [`MAX_RATE`].
+fn check_rates(ascent_rate: isize, descent_rate: isize) -> Result<(), CStatus> {
+    if !(-MAX_RATE..0).contains(&ascent_rate) || !(1..=MAX_RATE).contains(&descent_rate) {
+        return Err(CStatus::RateError);
+    }
+    Ok(())
+}
+
+/// Returns the version of the C API the library was built with. Compare this to [`ABI_VERSION`]
+/// in the header to detect a mismatched library.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
 #[no_mangle]
-pub extern "C" fn initialise(deco: &mut ZHL16) {
-    deco.tissue = Tissue {
-        p_n2: [0.7405; 16],
-        p_he: [0.0; 16],
-        p_t: [0.7405; 16]
-    };
+pub extern "C" fn abi_version() -> u32 {
+    ABI_VERSION
+}
 
-    deco.n2_a = ZHL16C_N2_A;
-    deco.n2_b = ZHL16C_N2_B;
-    deco.n2_hl = ZHL16C_N2_HALFLIFE;
+/// Returns the number of bytes of storage a deco model needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub extern "C" fn deco_size() -> usize {
+    mem::size_of::<ZHL16>()
+}
 
-    deco.he_a = ZHL16C_HE_A;
-    deco.he_b = ZHL16C_HE_B;
-    deco.he_hl = ZHL16C_HE_HALFLIFE;
+/// Returns the alignment (in bytes) the storage of a deco model needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub extern "C" fn deco_align() -> usize {
+    mem::align_of::<ZHL16>()
+}
 
-    deco.gf_low = 1.0;
-    deco.gf_high = 1.0;
+/// Initialise a deco model in caller storage with ZHL-16C, gradient factors of 100/100 and
+/// air-saturated tissues.
+/// # Arguments
+/// * `deco` - Storage of at least [`deco_size`] bytes, aligned to [`deco_align`]
+/// * `size` - Size of the storage (measured in bytes)
+/// # Safety
+/// `deco` must be null or point to `size` writable bytes.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 8 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn initialise(deco: *mut ZHL16, size: usize) -> CStatus {
+    status((|| {
+        check_storage(deco, size)?;
+        let tissue = Tissue {
+            p_n2: [0.7405; 16],
+            p_he: [0.0; 16],
+            p_t: [0.7405; 16]
+        };
+        deco.write(ZHL16::new(tissue, &ZHL16C_COEFFICIENTS, 100, 100));
+        Ok(())
+    })())
+}
 
-    deco.first_deco_depth = usize::MAX;
+/// Write the current depth of a deco model to `depth`.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `depth` must be null
+/// or point to a writable `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn deco_depth(deco: *const ZHL16, depth: *mut usize) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        *from_mut_ptr(depth)? = deco.diver_depth;
+        Ok(())
+    })())
 }
 
+/// Copy the nitrogen and helium pressures (measured in bar) of every compartment of a deco model.
+/// # Arguments
+/// * `p_n2` - Buffer for the nitrogen pressures
+/// * `p_he` - Buffer for the helium pressures
+/// * `len` - Length of each buffer, which must be at least [`TISSUE_COUNT`]
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `p_n2` and `p_he` must
+/// be null or point to `len` writable `Float`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
 #[no_mangle]
-pub extern "C" fn tick_segment(deco: &mut ZHL16, gas: &Gas, depth: usize, tick: u64) {
-    let segment = DiveSegment::new(
-        SegmentType::DiveSegment,
-        depth,
-        depth,
-        Duration::from_secs(tick),
-        -5, 5       // Placeholder value - this is a constant segment
-    ).unwrap();
+pub unsafe extern "C" fn deco_tissue(deco: *const ZHL16, p_n2: *mut Float, p_he: *mut Float, len: usize) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        if p_n2.is_null() || p_he.is_null() {
+            return Err(CStatus::NullPointerError);
+        }
+        if len < TISSUE_COUNT {
+            return Err(CStatus::SizeError);
+        }
+        let tissue = &deco.tissue;
+        p_n2.copy_from_nonoverlapping(tissue.p_n2.as_ptr(), TISSUE_COUNT);
+        p_he.copy_from_nonoverlapping(tissue.p_he.as_ptr(), TISSUE_COUNT);
+        Ok(())
+    })())
+}
 
-    deco.add_segment(&segment, &gas, 10.0);
+/// Write how close each compartment of a deco model is to its M-value to `supersaturation`. See
+/// [`Supersaturation`].
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `supersaturation`
+/// must be null or point to writable memory for a `Supersaturation`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 336 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn deco_supersaturation(deco: *const ZHL16, metres_per_bar: Float, supersaturation: *mut Supersaturation) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *from_mut_ptr(supersaturation)? = deco.supersaturation(metres_per_bar);
+        Ok(())
+    })())
 }
 
+/// Change the stop settings of a deco model.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
 #[no_mangle]
-pub extern "C" fn get_next_stop(deco: &ZHL16, gas: &Gas, ascent_rate: isize, descent_rate: isize) -> CDiveSegment {
-    if deco.find_ascent_ceiling(Some(deco.gfh())) < 1.0 {
-        match deco.ndl(gas, 1000.0) {
-            None => { unreachable!(); }
-            Some(t) => { return t.into(); }
+pub unsafe extern "C" fn set_stop_settings(deco: *mut ZHL16, stop_interval: usize, last_stop_depth: usize, min_stop_time: u64, stop_resolution: u64) -> CStatus {
+    status((|| {
+        let deco = from_mut_ptr(deco)?;
+        check_depth(last_stop_depth)?;
+        match StopSettings::new(stop_interval, last_stop_depth, Duration::from_secs(min_stop_time), Duration::from_secs(stop_resolution)) {
+            Ok(t) => {
+                deco.set_stop_settings(t);
+                Ok(())
+            }
+            Err(_) => Err(CStatus::StopSettingsError)
         }
-    }
-    deco.next_stop(ascent_rate, descent_rate, gas, 10.0).into()
-}
\ No newline at end of file
+    })())
+}
+
+/// Change the gradient factors of a deco model. See [`ZHL16::set_gradient_factors`] for how this
+/// affects a dive in progress.
+/// # Arguments
+/// * `gf_low` - Gradient Factor low value (as a percentage)
+/// * `gf_high` - Gradient Factor high value (as a percentage)
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn set_gradient_factors(deco: *mut ZHL16, gf_low: usize, gf_high: usize) -> CStatus {
+    status((|| {
+        let deco = from_mut_ptr(deco)?;
+        deco.set_gradient_factors(gf_low, gf_high)
+            .map_err(|_| CStatus::GradientFactorError)
+    })())
+}
+
+/// Write the gradient factors (as percentages) of a deco model to `gf_low` and `gf_high`.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `gf_low` and `gf_high`
+/// must be null or point to writable `size_t`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn get_gradient_factors(deco: *const ZHL16, gf_low: *mut usize, gf_high: *mut usize) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        let gf_low = from_mut_ptr(gf_low)?;
+        let gf_high = from_mut_ptr(gf_high)?;
+        let (low, high) = deco.gradient_factors();
+        *gf_low = low;
+        *gf_high = high;
+        Ok(())
+    })())
+}
+
+/// Change the gradient factors of a deco model to those of a preset conservatism level.
+/// # Arguments
+/// * `level` - One of the values of [`Conservatism`]. It is passed as an integer, as C may hold any
+///   value in an enum
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn set_conservatism(deco: *mut ZHL16, level: u8) -> CStatus {
+    status((|| {
+        let deco = from_mut_ptr(deco)?;
+        let level = Conservatism::from_u8(level).ok_or(CStatus::InvalidArgument)?;
+        deco.set_conservatism(level);
+        Ok(())
+    })())
+}
+
+/// Use the classic gradient factor line from GF low at the first stop to GF high at the surface.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn set_gf_profile_linear(deco: *mut ZHL16) -> CStatus {
+    status((|| {
+        from_mut_ptr(deco)?.set_gf_profile(GradientFactorProfile::Linear);
+        Ok(())
+    })())
+}
+
+/// Use GF high at every depth.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`].
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn set_gf_profile_high_only(deco: *mut ZHL16) -> CStatus {
+    status((|| {
+        from_mut_ptr(deco)?.set_gf_profile(GradientFactorProfile::HighOnly);
+        Ok(())
+    })())
+}
+
+/// Use gradient factors interpolated between points at fixed depths. See
+/// [`GradientFactorProfile::table`].
+/// # Arguments
+/// * `depths` - Depth of each point, strictly increasing
+/// * `gfs` - Gradient factor (as a percentage) at each point
+/// * `len` - Number of points, at most [`GF_TABLE_CAPACITY`]
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `depths` and `gfs`
+/// must be null or point to `len` `size_t`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 440 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn set_gf_profile_table(deco: *mut ZHL16, depths: *const usize, gfs: *const usize, len: usize) -> CStatus {
+    status((|| {
+        let deco = from_mut_ptr(deco)?;
+        if depths.is_null() || gfs.is_null() {
+            return Err(CStatus::NullPointerError);
+        }
+        if len == 0 || len > GF_TABLE_CAPACITY {
+            return Err(CStatus::SizeError);
+        }
+        let mut points = [(0, 0); GF_TABLE_CAPACITY];
+        for (idx, point) in points.iter_mut().take(len).enumerate() {
+            *point = (*depths.add(idx), *gfs.add(idx));
+        }
+        let profile = GradientFactorProfile::table(&points[..len])
+            .map_err(|_| CStatus::GradientFactorError)?;
+        deco.set_gf_profile(profile);
+        Ok(())
+    })())
+}
+
+/// Give each stop its own gradient factor, counted from the first stop. See
+/// [`GradientFactorProfile::per_stop`].
+/// # Arguments
+/// * `gfs` - Gradient factor (as a percentage) of each stop
+/// * `len` - Number of stops, at most [`GF_STOP_CAPACITY`]
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `gfs` must be null
+/// or point to `len` `size_t`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 328 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn set_gf_profile_per_stop(deco: *mut ZHL16, gfs: *const usize, len: usize) -> CStatus {
+    status((|| {
+        let deco = from_mut_ptr(deco)?;
+        if gfs.is_null() {
+            return Err(CStatus::NullPointerError);
+        }
+        if len == 0 || len > GF_STOP_CAPACITY {
+            return Err(CStatus::SizeError);
+        }
+        let profile = GradientFactorProfile::per_stop(core::slice::from_raw_parts(gfs, len))
+            .map_err(|_| CStatus::GradientFactorError)?;
+        deco.set_gf_profile(profile);
+        Ok(())
+    })())
+}
+
+/// Write the depth of the first deco stop made to `depth`. The gradient factor curve is anchored
+/// at this depth once it is set. Returns `NoStopError` if no deco stop has been made yet.
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `depth` must be null
+/// or point to a writable `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn deco_first_deco_depth(deco: *const ZHL16, depth: *mut usize) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        let depth = from_mut_ptr(depth)?;
+        *depth = deco.first_deco_depth().ok_or(CStatus::NoStopError)?;
+        Ok(())
+    })())
+}
+
+/// Load a deco model with a constant depth segment lasting `tick` seconds.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], and `gas` must be null or
+/// point to a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 672 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tick_segment(deco: *mut ZHL16, gas: *const Gas, depth: usize, tick: u64, metres_per_bar: Float) -> CStatus {
+    status((|| {
+        let deco = from_mut_ptr(deco)?;
+        let gas = gas_from_ptr(gas)?;
+        check_depth(depth)?;
+        check_metres_per_bar(metres_per_bar)?;
+        if tick == 0 {
+            return Err(CStatus::DurationError);
+        }
+
+        // Ticks are nearly always the same length, so cache the decay factors for it.
+        if deco.tick() != Some(Duration::from_secs(tick)) {
+            deco.set_tick(Duration::from_secs(tick));
+        }
+
+        let segment = DiveSegment::constant(
+            SegmentType::DiveSegment,
+            depth,
+            Duration::from_secs(tick),
+            -5, 5       // Placeholder value - this is a constant segment
+        );
+
+        deco.add_segment(&segment, gas, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the NDL (as a `NoDeco` segment) or the next deco stop (as a `DecoStop` segment) to `segment`.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], `gas` must be null or
+/// point to a `Gas`, and `segment` must be null or point to writable memory for a `CDiveSegment`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 1320 bytes. The ascent to the stop is simulated on a copy of the tissues only.
+#[no_mangle]
+pub unsafe extern "C" fn get_next_stop(deco: *const ZHL16, gas: *const Gas, ascent_rate: isize, descent_rate: isize, metres_per_bar: Float, segment: *mut CDiveSegment) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        let gas = gas_from_ptr(gas)?;
+        let segment = from_mut_ptr(segment)?;
+        check_rates(ascent_rate, descent_rate)?;
+        check_metres_per_bar(metres_per_bar)?;
+
+        let next = match deco.ndl(gas, metres_per_bar) {
+            Ok(t) => t,
+            Err(_) => deco.next_stop(ascent_rate, descent_rate, gas, metres_per_bar)?,
+        };
+        *segment = next.into();
+        Ok(())
+    })())
+}
+
+/// Write the no-decompression limit of the model to `ndl`, and the index of the compartment
+/// controlling it to `compartment`. See [`ZHL16::ndl_with_compartment`] for how the compartment is
+/// chosen.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// * `ndl` - No-decompression limit (milliseconds)
+/// * `compartment` - Index of the compartment controlling the limit, from 0 for the fastest
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], `gas` must be null or
+/// point to a `Gas`, and `ndl` and `compartment` must be null or point to a writable `uint64_t` and
+/// `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 608 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn get_ndl(deco: *const ZHL16, gas: *const Gas, metres_per_bar: Float, ndl: *mut u64, compartment: *mut usize) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        let gas = gas_from_ptr(gas)?;
+        let ndl = from_mut_ptr(ndl)?;
+        let compartment = from_mut_ptr(compartment)?;
+        check_metres_per_bar(metres_per_bar)?;
+
+        let (segment, idx) = deco.ndl_with_compartment(gas, metres_per_bar, 1.0)?;
+        *ndl = segment.time().as_millis() as u64;
+        *compartment = idx;
+        Ok(())
+    })())
+}
+
+/// Write the NDLs at each depth for each gas to `entries`, ordered by gas, then by depth. The deco
+/// model is not changed. See [`NdlTable::for_depths`] for how the surface interval and altitude
+/// are applied.
+/// # Arguments
+/// * `depths` - Depths to calculate NDLs at
+/// * `depth_count` - Number of depths
+/// * `gases` - Gases to calculate NDLs for
+/// * `gas_count` - Number of gases. There can be at most [`NDL_TABLE_CAPACITY`] entries in all
+/// * `surface_interval` - Time spent at the surface breathing air before the dive (seconds)
+/// * `altitude` - Altitude of the dive site above sea level (measured in m)
+/// * `descent_rate` - Descent rate (measured in m min^-1)
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// * `entries` - Buffer to write the table to
+/// * `capacity` - Number of entries `entries` can hold, at least `depth_count * gas_count`
+/// # Safety
+/// `deco` must be null or point to a deco model set up by [`initialise`], `depths` must be null or
+/// point to `depth_count` `size_t`s, `gases` must be null or point to `gas_count` `Gas`es, and
+/// `entries` must be null or point to writable memory for `capacity` `CNdlEntry`s.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 11112 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ndl_table(deco: *const ZHL16, depths: *const usize, depth_count: usize, gases: *const Gas, gas_count: usize, surface_interval: u64, altitude: usize, descent_rate: isize, metres_per_bar: Float, entries: *mut CNdlEntry, capacity: usize) -> CStatus {
+    status((|| {
+        let deco = from_ptr(deco)?;
+        if depths.is_null() || gases.is_null() || entries.is_null() {
+            return Err(CStatus::NullPointerError);
+        }
+        let entry_count = depth_count.checked_mul(gas_count).ok_or(CStatus::SizeError)?;
+        if entry_count == 0 || entry_count > NDL_TABLE_CAPACITY || capacity < entry_count {
+            return Err(CStatus::SizeError);
+        }
+        check_rates(-1, descent_rate)?;
+        check_metres_per_bar(metres_per_bar)?;
+
+        let depths = core::slice::from_raw_parts(depths, depth_count);
+        for &depth in depths {
+            check_depth(depth)?;
+        }
+        let gases = core::slice::from_raw_parts(gases, gas_count);
+        for gas in gases {
+            gas_from_ptr(gas)?;
+        }
+
+        let table = NdlTable::for_depths(
+            deco,
+            depths,
+            gases,
+            Duration::from_secs(surface_interval),
+            altitude,
+            descent_rate,
+            metres_per_bar,
+        )
+        .map_err(|e| match e {
+            NdlTableError::CapacityError => CStatus::SizeError,
+            NdlTableError::AltitudeError => CStatus::AltitudeError,
+        })?;
+        for (idx, entry) in table.entries().iter().enumerate() {
+            *entries.add(idx) = entry.into();
+        }
+        Ok(())
+    })())
+}
+
+/// Returns the number of bytes of storage an incremental planner needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub extern "C" fn planner_size() -> usize {
+    mem::size_of::<IncrementalPlanner>()
+}
+
+/// Returns the alignment (in bytes) the storage of an incremental planner needs.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub extern "C" fn planner_align() -> usize {
+    mem::align_of::<IncrementalPlanner>()
+}
+
+/// Initialise an incremental planner for a deco model in caller storage, with no calculation in progress.
+/// # Arguments
+/// * `planner` - Storage of at least [`planner_size`] bytes, aligned to [`planner_align`]
+/// * `size` - Size of the storage (measured in bytes)
+/// * `deco` - Deco model to plan for
+/// * `gas` - Gas to plan the ascent with
+/// # Safety
+/// `planner` must be null or point to `size` writable bytes, `deco` must be null or point to a deco
+/// model set up by [`initialise`], and `gas` must be null or point to a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 40 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn planner_initialise(planner: *mut IncrementalPlanner, size: usize, deco: *const ZHL16, gas: *const Gas) -> CStatus {
+    status((|| {
+        check_storage(planner, size)?;
+        let deco = from_ptr(deco)?;
+        let gas = gas_from_ptr(gas)?;
+        planner.write(IncrementalPlanner::new(deco, gas));
+        Ok(())
+    })())
+}
+
+/// Start a new calculation from a snapshot of a deco model.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], `deco` must be
+/// null or point to a deco model set up by [`initialise`], and `gas` must be null or point to a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 88 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn planner_begin(planner: *mut IncrementalPlanner, deco: *const ZHL16, gas: *const Gas, ascent_rate: isize, descent_rate: isize, metres_per_bar: Float) -> CStatus {
+    status((|| {
+        let planner = from_mut_ptr(planner)?;
+        let deco = from_ptr(deco)?;
+        let gas = gas_from_ptr(gas)?;
+        check_rates(ascent_rate, descent_rate)?;
+        check_metres_per_bar(metres_per_bar)?;
+        planner.begin(deco, gas, ascent_rate, descent_rate, metres_per_bar)?;
+        Ok(())
+    })())
+}
+
+/// Start a new calculation from a snapshot of a deco model, ascending at rates that depend on depth.
+/// # Arguments
+/// * `ascent_rate` - Ascent rate below the deepest band (measured in m min^-1)
+/// * `bands` - Depth bands, each ascended at its rate from its depth up to the next shallower band,
+///   or the surface
+/// * `band_count` - Number of bands, at most [`ASCENT_BAND_CAPACITY`]. May be 0
+/// * `descent_rate` - Descent rate (measured in m min^-1)
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], `deco` must be
+/// null or point to a deco model set up by [`initialise`], `gas` must be null or point to a `Gas`,
+/// and `bands` must point to `band_count` `AscentBand`s unless `band_count` is 0.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 384 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn planner_begin_with_rates(planner: *mut IncrementalPlanner, deco: *const ZHL16, gas: *const Gas, ascent_rate: isize, bands: *const AscentBand, band_count: usize, descent_rate: isize, metres_per_bar: Float) -> CStatus {
+    status((|| {
+        let planner = from_mut_ptr(planner)?;
+        let deco = from_ptr(deco)?;
+        let gas = gas_from_ptr(gas)?;
+        let rates = ascent_rates_from_ptr(ascent_rate, bands, band_count, descent_rate)?;
+        check_metres_per_bar(metres_per_bar)?;
+        planner.begin_with_rates(deco, gas, &rates, descent_rate, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the fastest ascent rate allowed at a depth to `rate`, such as to raise an ascent rate
+/// alarm.
+/// # Arguments
+/// * `ascent_rate` - Ascent rate below the deepest band (measured in m min^-1)
+/// * `bands` - Depth bands, each ascended at its rate from its depth up to the next shallower band,
+///   or the surface
+/// * `band_count` - Number of bands, at most [`ASCENT_BAND_CAPACITY`]. May be 0
+/// * `depth` - Depth of the diver
+/// * `rate` - Ascent rate allowed at `depth` (measured in m min^-1)
+/// # Safety
+/// `bands` must point to `band_count` `AscentBand`s unless `band_count` is 0, and `rate` must be
+/// null or point to a writable `intptr_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 320 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ascent_rate_at(ascent_rate: isize, bands: *const AscentBand, band_count: usize, depth: usize, rate: *mut isize) -> CStatus {
+    status((|| {
+        let rate = from_mut_ptr(rate)?;
+        check_depth(depth)?;
+        let rates = ascent_rates_from_ptr(ascent_rate, bands, band_count, 1)?;
+        *rate = rates.rate_at(depth);
+        Ok(())
+    })())
+}
+
+/// Advance the calculation in progress by at most `budget` iterations, and write whether it is
+/// complete to `complete`. Returns the error that stopped the calculation if it failed.
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], and `complete`
+/// must be null or point to a writable `bool`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 1528 bytes, regardless of `budget`.
+#[no_mangle]
+pub unsafe extern "C" fn planner_step(planner: *mut IncrementalPlanner, budget: usize, complete: *mut bool) -> CStatus {
+    status((|| {
+        let planner = from_mut_ptr(planner)?;
+        let complete = from_mut_ptr(complete)?;
+        *complete = planner.step(budget);
+        match planner.error() {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    })())
+}
+
+/// Write the result of the last completed calculation to `result`.
+/// # Safety
+/// `planner` must be null or point to a planner set up by [`planner_initialise`], and `result` must
+/// be null or point to writable memory for a `DecoResult`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn planner_result(planner: *const IncrementalPlanner, result: *mut DecoResult) -> CStatus {
+    status((|| {
+        let planner = from_ptr(planner)?;
+        let result = from_mut_ptr(result)?;
+        *result = planner.result().ok_or(CStatus::NoResultError)?;
+        Ok(())
+    })())
+}
+
+/// Check the fractions of a gas mix and write it to `gas`.
+/// # Arguments
+/// * `o2` - Percentage fraction of oxygen in the mix
+/// * `he` - Percentage fraction of helium in the mix
+/// * `n2` - Percentage fraction of nitrogen in the mix
+/// # Safety
+/// `gas` must be null or point to writable memory for a `Gas`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn gas_new(o2: usize, he: usize, n2: usize, gas: *mut Gas) -> CStatus {
+    status((|| {
+        let gas = from_mut_ptr(gas)?;
+        *gas = Gas::new(o2, he, n2).map_err(|_| CStatus::GasFractionError)?;
+        Ok(())
+    })())
+}
+
+/// Write the ppO2 (measured in bar) of a gas at a given depth to `pp_o2`.
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `pp_o2` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn gas_pp_o2(gas: *const Gas, depth: usize, metres_per_bar: Float, pp_o2: *mut Float) -> CStatus {
+    status((|| {
+        let gas = gas_from_ptr(gas)?;
+        let pp_o2 = from_mut_ptr(pp_o2)?;
+        check_depth(depth)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *pp_o2 = gas.pp_o2(depth, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the ppHe (measured in bar) of a gas at a given depth to `pp_he`.
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `pp_he` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn gas_pp_he(gas: *const Gas, depth: usize, metres_per_bar: Float, pp_he: *mut Float) -> CStatus {
+    status((|| {
+        let gas = gas_from_ptr(gas)?;
+        let pp_he = from_mut_ptr(pp_he)?;
+        check_depth(depth)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *pp_he = gas.pp_he(depth, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the ppN2 (measured in bar) of a gas at a given depth to `pp_n2`.
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `pp_n2` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn gas_pp_n2(gas: *const Gas, depth: usize, metres_per_bar: Float, pp_n2: *mut Float) -> CStatus {
+    status((|| {
+        let gas = gas_from_ptr(gas)?;
+        let pp_n2 = from_mut_ptr(pp_n2)?;
+        check_depth(depth)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *pp_n2 = gas.pp_n2(depth, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the Equivalent Narcotic Depth (END) of a gas at a given depth to `end` (measured in m).
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `gas` must be null or point to a `Gas`, and `end` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn gas_end(gas: *const Gas, depth: usize, metres_per_bar: Float, end: *mut Float) -> CStatus {
+    status((|| {
+        let gas = gas_from_ptr(gas)?;
+        let end = from_mut_ptr(end)?;
+        check_depth(depth)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *end = gas.equivalent_narcotic_depth_with_metres_per_bar(depth, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the Oxygen Toxicity Units (OTU) accumulated during a segment to `otu`.
+/// # Arguments
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// # Safety
+/// `segment` must be null or point to a `CDiveSegment`, `gas` must be null or point to a `Gas`, and
+/// `otu` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 112 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn otu(segment: *const CDiveSegment, gas: *const Gas, metres_per_bar: Float, otu: *mut Float) -> CStatus {
+    status((|| {
+        let segment = segment_from_c(from_ptr(segment)?)?;
+        let gas = gas_from_ptr(gas)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *from_mut_ptr(otu)? = common::otu::otu_with_metres_per_bar(&segment, gas, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the time (measured in milliseconds) taken to change between two depths at a given rate to `time`.
+/// # Safety
+/// `time` must be null or point to a writable `uint64_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn time_taken(rate: isize, depth_1: usize, depth_2: usize, time: *mut u64) -> CStatus {
+    status((|| {
+        let time = from_mut_ptr(time)?;
+        check_depth(depth_1)?;
+        check_depth(depth_2)?;
+        if rate == 0 || !(-MAX_RATE..=MAX_RATE).contains(&rate) {
+            return Err(CStatus::RateError);
+        }
+        *time = common::time_taken(rate, depth_1, depth_2).as_millis() as u64;
+        Ok(())
+    })())
+}
+
+/// Write the pressure (measured in bar) induced by a depth of water to `bar`.
+/// # Safety
+/// `bar` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn mtr_bar(mtr: Float, metres_per_bar: Float, bar: *mut Float) -> CStatus {
+    status((|| {
+        let bar = from_mut_ptr(bar)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *bar = common::mtr_bar(mtr, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the depth of water that induces a pressure (measured in bar) to `mtr`.
+/// # Safety
+/// `mtr` must be null or point to a writable `Float`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// No stack frame.
+#[no_mangle]
+pub unsafe extern "C" fn bar_mtr(bar: Float, metres_per_bar: Float, mtr: *mut Float) -> CStatus {
+    status((|| {
+        let mtr = from_mut_ptr(mtr)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *mtr = common::bar_mtr(bar, metres_per_bar);
+        Ok(())
+    })())
+}
+
+/// Write the quantity of gas a diver would consume in a segment to `consumed`.
+/// # Arguments
+/// * `segment` - Segment to calculate the gas consumed for
+/// * `sac_rate` - Surface Air Consumption (SAC) rate (measured in bar min^-1)
+/// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+/// * `consumed` - Output for the gas consumed
+/// # Safety
+/// `segment` must be null or point to a `CDiveSegment`, and `consumed` must be null or point to a
+/// writable `size_t`.
+/// # Stack usage (x86_64 host, not measured on Xtensa)
+/// At most 8 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gas_consumed(segment: *const CDiveSegment, sac_rate: usize, metres_per_bar: Float, consumed: *mut usize) -> CStatus {
+    status((|| {
+        let segment = segment_from_c(from_ptr(segment)?)?;
+        let consumed = from_mut_ptr(consumed)?;
+        check_metres_per_bar(metres_per_bar)?;
+        *consumed = segment.gas_consumed(sac_rate, metres_per_bar);
+        Ok(())
+    })())
+}
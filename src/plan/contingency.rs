@@ -0,0 +1,286 @@
+use crate::common::dive_segment::SegmentType;
+use crate::plan::{DivePlan, DivePlanError, Level, PlanResult, TANK_CAPACITY};
+use core::convert::TryFrom;
+use core::time::Duration;
+
+/// Maximum number of depth or time offsets in a [`ContingencyTable`].
+pub const CONTINGENCY_OFFSET_CAPACITY: usize = 4;
+
+/// Maximum number of stops kept in a [`Schedule`].
+pub const SCHEDULE_STOP_CAPACITY: usize = 24;
+
+/// A stop that is not in use.
+const EMPTY_STOP: ScheduleStop = ScheduleStop {
+    depth: 0,
+    time: 0,
+    o2: 0,
+    he: 0,
+    deep: false,
+};
+
+/// A deep or deco stop of a [`Schedule`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduleStop {
+    /// Depth of the stop (measured in m).
+    depth: u16,
+    /// Duration of the stop, rounded up (measured in minutes).
+    time: u16,
+    /// Percentage of oxygen in the gas breathed at the stop.
+    o2: u8,
+    /// Percentage of helium in the gas breathed at the stop.
+    he: u8,
+    /// Whether the stop is a deep stop rather than one the deco model requires.
+    deep: bool,
+}
+
+impl ScheduleStop {
+    /// Returns the depth of the stop (measured in m).
+    pub fn depth(&self) -> u16 {
+        self.depth
+    }
+
+    /// Returns the duration of the stop, rounded up (measured in minutes).
+    pub fn time(&self) -> u16 {
+        self.time
+    }
+
+    /// Returns the percentage of oxygen in the gas breathed at the stop. Air breaks split a stop
+    /// into several at the same depth, each with its own gas.
+    pub fn o2(&self) -> u8 {
+        self.o2
+    }
+
+    /// Returns the percentage of helium in the gas breathed at the stop.
+    pub fn he(&self) -> u8 {
+        self.he
+    }
+
+    /// Returns whether the stop is a deep stop rather than one the deco model requires.
+    pub fn is_deep(&self) -> bool {
+        self.deep
+    }
+}
+
+/// A calculated dive reduced to what a slate carries. Times are rounded up to whole minutes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schedule {
+    /// Depth of the deepest level (measured in m).
+    depth: u16,
+    /// Time at the deepest level (measured in minutes).
+    bottom_time: u16,
+    /// Total duration of the dive (measured in minutes).
+    runtime: u16,
+    /// Time to surface from the last level (measured in minutes).
+    tts: u16,
+    /// Number of stops in use.
+    stop_count: u8,
+    /// Deep and deco stops of the dive, in order.
+    stops: [ScheduleStop; SCHEDULE_STOP_CAPACITY],
+    /// Volume of gas drawn from each tank of the plan (measured in L).
+    tank_consumed: [u32; TANK_CAPACITY],
+}
+
+impl Schedule {
+    /// Returns the schedule of a calculated dive.
+    /// # Arguments
+    /// * `level` - Deepest level of the dive
+    /// * `result` - The calculated dive
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the dive has more than
+    /// [`SCHEDULE_STOP_CAPACITY`] stops, or a depth, time or volume too large for the schedule.
+    fn new(level: &Level, result: &PlanResult) -> Result<Self, DivePlanError> {
+        if result.stops().count() > SCHEDULE_STOP_CAPACITY {
+            return Err(DivePlanError::CapacityError);
+        }
+
+        let mut schedule = Self {
+            depth: narrow(level.depth())?,
+            bottom_time: minutes(*level.time())?,
+            runtime: minutes(result.runtime())?,
+            tts: minutes(result.tts())?,
+            stop_count: 0,
+            stops: [EMPTY_STOP; SCHEDULE_STOP_CAPACITY],
+            tank_consumed: [0; TANK_CAPACITY],
+        };
+        for (idx, stop) in result.stops().enumerate() {
+            schedule.stops[idx] = ScheduleStop {
+                depth: narrow(stop.segment().start_depth())?,
+                time: minutes(*stop.segment().time())?,
+                o2: narrow(stop.gas().o2())?,
+                he: narrow(stop.gas().he())?,
+                deep: stop.segment().segment_type() == SegmentType::DeepStop,
+            };
+            schedule.stop_count += 1;
+        }
+        for idx in 0..result.tanks().len() {
+            schedule.tank_consumed[idx] = narrow(result.tank_consumed(idx).unwrap_or(0))?;
+        }
+        Ok(schedule)
+    }
+
+    /// Returns the depth of the deepest level (measured in m).
+    pub fn depth(&self) -> u16 {
+        self.depth
+    }
+
+    /// Returns the time at the deepest level (measured in minutes).
+    pub fn bottom_time(&self) -> u16 {
+        self.bottom_time
+    }
+
+    /// Returns the total duration of the dive (measured in minutes).
+    pub fn runtime(&self) -> u16 {
+        self.runtime
+    }
+
+    /// Returns the time to surface from the last level (measured in minutes).
+    pub fn tts(&self) -> u16 {
+        self.tts
+    }
+
+    /// Returns the deep and deco stops of the dive, in order.
+    pub fn stops(&self) -> &[ScheduleStop] {
+        &self.stops[..self.stop_count as usize]
+    }
+
+    /// Returns the volume of gas drawn from a tank (measured in L), in the order the tanks were
+    /// added to the plan.
+    pub fn tank_consumed(&self) -> &[u32; TANK_CAPACITY] {
+        &self.tank_consumed
+    }
+}
+
+/// Schedules for a plan and its deviations, going deeper and staying longer at the deepest level.
+/// Rows are depth offsets and columns are time offsets.
+#[derive(Copy, Clone)]
+pub struct ContingencyTable {
+    /// Number of depth offsets in use.
+    rows: usize,
+    /// Number of time offsets in use.
+    columns: usize,
+    /// Depth added to the deepest level in each row (measured in m).
+    depth_offsets: [usize; CONTINGENCY_OFFSET_CAPACITY],
+    /// Time added to the deepest level in each column (measured in minutes).
+    time_offsets: [usize; CONTINGENCY_OFFSET_CAPACITY],
+    /// Schedules in row order.
+    schedules: [Schedule; CONTINGENCY_OFFSET_CAPACITY * CONTINGENCY_OFFSET_CAPACITY],
+}
+
+impl ContingencyTable {
+    /// Calculate the schedules of a plan with every combination of depth and time offsets applied
+    /// to its deepest level (the first, if several are equally deep). Include an offset of zero
+    /// to get the plan itself.
+    /// # Arguments
+    /// * `plan` - Plan to deviate from
+    /// * `depth_offsets` - Depths to add to the deepest level (measured in m)
+    /// * `time_offsets` - Times to add to the deepest level (measured in minutes)
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if there are no offsets or more than
+    /// [`CONTINGENCY_OFFSET_CAPACITY`] of either, or if a deviation cannot be calculated or has
+    /// more than [`SCHEDULE_STOP_CAPACITY`] stops.
+    pub fn new(
+        plan: &DivePlan,
+        depth_offsets: &[usize],
+        time_offsets: &[usize],
+    ) -> Result<Self, DivePlanError> {
+        if depth_offsets.is_empty()
+            || time_offsets.is_empty()
+            || depth_offsets.len() > CONTINGENCY_OFFSET_CAPACITY
+            || time_offsets.len() > CONTINGENCY_OFFSET_CAPACITY
+        {
+            return Err(DivePlanError::CapacityError);
+        }
+        let mut deepest: Option<usize> = None;
+        for (idx, t) in plan.levels().iter().enumerate() {
+            let deeper = match deepest {
+                Some(d) => t.depth() > plan.levels()[d].depth(),
+                None => true,
+            };
+            if deeper {
+                deepest = Some(idx);
+            }
+        }
+        let deepest = deepest.ok_or(DivePlanError::NoLevelError)?;
+
+        let empty = Schedule {
+            depth: 0,
+            bottom_time: 0,
+            runtime: 0,
+            tts: 0,
+            stop_count: 0,
+            stops: [EMPTY_STOP; SCHEDULE_STOP_CAPACITY],
+            tank_consumed: [0; TANK_CAPACITY],
+        };
+        let mut table = Self {
+            rows: depth_offsets.len(),
+            columns: time_offsets.len(),
+            depth_offsets: [0; CONTINGENCY_OFFSET_CAPACITY],
+            time_offsets: [0; CONTINGENCY_OFFSET_CAPACITY],
+            schedules: [empty; CONTINGENCY_OFFSET_CAPACITY * CONTINGENCY_OFFSET_CAPACITY],
+        };
+        table.depth_offsets[..depth_offsets.len()].copy_from_slice(depth_offsets);
+        table.time_offsets[..time_offsets.len()].copy_from_slice(time_offsets);
+
+        // Every deviation is calculated into the same dive, to keep the stack small.
+        let mut result = PlanResult::default();
+        for (row, &depth_offset) in depth_offsets.iter().enumerate() {
+            for (column, &time_offset) in time_offsets.iter().enumerate() {
+                let base = plan.levels()[deepest];
+                let level = Level::new(
+                    base.depth() + depth_offset,
+                    *base.time() + Duration::from_secs(time_offset as u64 * 60),
+                    *base.gas(),
+                );
+                let mut deviation = *plan;
+                deviation.clear_levels();
+                for (idx, t) in plan.levels().iter().enumerate() {
+                    deviation.add_level(if idx == deepest { level } else { *t })?;
+                }
+                deviation.plan_into(&mut result)?;
+                table.schedules[row * table.columns + column] = Schedule::new(&level, &result)?;
+            }
+        }
+        Ok(table)
+    }
+
+    /// Returns the depth offsets of the rows (measured in m).
+    pub fn depth_offsets(&self) -> &[usize] {
+        &self.depth_offsets[..self.rows]
+    }
+
+    /// Returns the time offsets of the columns (measured in minutes).
+    pub fn time_offsets(&self) -> &[usize] {
+        &self.time_offsets[..self.columns]
+    }
+
+    /// Returns the schedule for a depth offset and a time offset, if it exists.
+    /// # Arguments
+    /// * `row` - Index of the depth offset
+    /// * `column` - Index of the time offset
+    pub fn schedule(&self, row: usize, column: usize) -> Option<&Schedule> {
+        if row >= self.rows || column >= self.columns {
+            return None;
+        }
+        Some(&self.schedules[row * self.columns + column])
+    }
+
+    /// Returns every schedule in row order.
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules[..self.rows * self.columns]
+    }
+}
+
+/// Convert a duration to whole minutes, rounding up.
+fn minutes(time: Duration) -> Result<u16, DivePlanError> {
+    let rounded = time
+        .checked_add(Duration::from_secs(59))
+        .ok_or(DivePlanError::CapacityError)?;
+    narrow(rounded.as_secs() / 60)
+}
+
+/// Convert a value to a narrower integer, failing if it does not fit.
+fn narrow<T: TryFrom<U>, U>(value: U) -> Result<T, DivePlanError> {
+    T::try_from(value).map_err(|_| DivePlanError::CapacityError)
+}
@@ -0,0 +1,108 @@
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::{pow, Float};
+use crate::common::gas::Gas;
+use crate::common::DEFAULT_DESCENT_RATE;
+use crate::deco::zhl16::ZHL16;
+use crate::gas;
+use crate::plan::ndl::NdlTable;
+use crate::plan::{DivePlan, DivePlanError, PlanResult};
+use core::time::Duration;
+
+/// Half-life of CNS oxygen toxicity at the surface (measured in minutes).
+const CNS_HALF_LIFE: Float = 90.0;
+
+/// A series of dives on the same day. The residual tissue loading, OTU and CNS left by each dive
+/// are carried through the surface interval into the next.
+///
+/// Plan each dive with [`DiveDay::dive`] and spend time at the surface with [`DiveDay::surface`].
+/// [`DiveDay::ndl_table`] gives the NDLs available before the next dive.
+#[derive(Copy, Clone)]
+pub struct DiveDay {
+    /// Deco model of the diver, carried from dive to dive.
+    deco: ZHL16,
+    /// Depth of water required to induce 1 bar of pressure at the surface.
+    metres_per_bar: Float,
+    /// Number of dives made so far.
+    dives: usize,
+    /// Oxygen Toxicity Units accumulated over the day.
+    otu: Float,
+    /// CNS oxygen toxicity left from the dives so far (as a percentage).
+    cns: Float,
+}
+
+impl DiveDay {
+    /// Returns a new day for a diver who has been at the surface for a long time.
+    /// # Arguments
+    /// * `deco` - Deco model of the diver before the first dive
+    pub fn new(deco: ZHL16) -> Self {
+        Self {
+            deco,
+            metres_per_bar: 10.0,
+            dives: 0,
+            otu: 0.0,
+            cns: 0.0,
+        }
+    }
+
+    /// Calculate the next dive of the day, starting from the residual tissue loading of the
+    /// diver. Everything else about the deco model, such as the gradient factors, is taken from
+    /// the plan.
+    /// # Arguments
+    /// * `plan` - Plan of the dive
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the dive cannot be calculated. The day is
+    /// left unchanged.
+    pub fn dive(&mut self, plan: &DivePlan) -> Result<PlanResult, DivePlanError> {
+        let mut plan = *plan;
+        let mut deco = *plan.deco();
+        deco.set_tissue(self.deco.tissue());
+        plan.set_deco(deco);
+
+        let result = plan.plan()?;
+        self.deco = *result.deco();
+        self.metres_per_bar = result.metres_per_bar();
+        self.dives += 1;
+        self.otu += result.otu();
+        self.cns += result.cns();
+        Ok(result)
+    }
+
+    /// Spend time at the surface breathing air. Tissues off-gas and CNS toxicity decays; OTUs are
+    /// counted for the whole day.
+    /// # Arguments
+    /// * `interval` - Time spent at the surface
+    pub fn surface(&mut self, interval: Duration) {
+        let segment = DiveSegment::constant(SegmentType::DiveSegment, 0, interval, -1, 1);
+        self.deco
+            .add_segment(&segment, &gas!(21, 0), self.metres_per_bar);
+        let minutes = interval.as_secs_f64() as Float / 60.0;
+        self.cns *= pow(0.5, minutes / CNS_HALF_LIFE);
+    }
+
+    /// Returns the NDLs available for the next dive at the standard depths.
+    /// # Arguments
+    /// * `gas` - Gas of the next dive
+    pub fn ndl_table(&self, gas: &Gas) -> NdlTable {
+        NdlTable::new(&self.deco, gas, DEFAULT_DESCENT_RATE, self.metres_per_bar)
+    }
+
+    /// Returns the deco model of the diver now.
+    pub fn deco(&self) -> &ZHL16 {
+        &self.deco
+    }
+
+    /// Returns the number of dives made so far.
+    pub fn dives(&self) -> usize {
+        self.dives
+    }
+
+    /// Returns the Oxygen Toxicity Units accumulated over the day.
+    pub fn otu(&self) -> Float {
+        self.otu
+    }
+
+    /// Returns the CNS oxygen toxicity left now (as a percentage of the NOAA limit).
+    pub fn cns(&self) -> Float {
+        self.cns
+    }
+}
@@ -0,0 +1,243 @@
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use crate::gas;
+use crate::plan::{DivePlan, DivePlanError, PlanResult, DECO_GAS_CAPACITY};
+use core::time::Duration;
+
+/// Maximum number of candidate gases [`select_deco_gases`] chooses from.
+pub const CANDIDATE_CAPACITY: usize = 8;
+
+/// Default lowest ppO2 a deco gas may be breathed at (measured in bar).
+pub const DEFAULT_MIN_PP_O2: Float = 0.16;
+
+/// Default highest density a gas may be switched to at (measured in g L^-1).
+pub const DEFAULT_MAX_DENSITY: Float = 6.2;
+
+/// What [`select_deco_gases`] minimises.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Objective {
+    /// Time to surface from the last level.
+    Tts,
+    /// Total volume of gas breathed on the dive.
+    GasConsumed,
+}
+
+/// Limits every switch to a deco gas must respect, on top of the deco ppO2 limit of the plan.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GasConstraints {
+    /// Lowest ppO2 a deco gas may be breathed at (measured in bar).
+    min_pp_o2: Float,
+    /// Highest density a gas may be switched to at (measured in g L^-1).
+    max_density: Float,
+    /// Whether switches must follow the rule of fifths for isobaric counter diffusion.
+    icd: bool,
+}
+
+impl GasConstraints {
+    /// Returns new GasConstraints with the given parameters.
+    /// # Arguments
+    /// * `min_pp_o2` - Lowest ppO2 a deco gas may be breathed at (measured in bar)
+    /// * `max_density` - Highest density a gas may be switched to at (measured in g L^-1)
+    /// * `icd` - Whether switches must follow the rule of fifths: nitrogen may rise by at most a
+    ///   fifth of the drop in helium
+    pub fn new(min_pp_o2: Float, max_density: Float, icd: bool) -> Self {
+        Self {
+            min_pp_o2,
+            max_density,
+            icd,
+        }
+    }
+
+    /// Returns whether every switch onto a deco gas of a calculated dive respects the constraints.
+    /// Switches between bottom gases, or back to one for an air break, are left to the plan.
+    fn allows(&self, result: &PlanResult, deco_gases: &[Gas]) -> bool {
+        let metres_per_bar = result.metres_per_bar();
+        let mut previous: Option<Gas> = None;
+        for segment in result.segments() {
+            let gas = *segment.gas();
+            let switched_from = previous.filter(|t| *t != gas);
+            previous = Some(gas);
+            if !deco_gases.contains(&gas) {
+                continue;
+            }
+            let shallowest = core::cmp::min(
+                segment.segment().start_depth(),
+                segment.segment().end_depth(),
+            );
+            if gas.pp_o2(shallowest, metres_per_bar) < self.min_pp_o2 {
+                return false;
+            }
+            if let Some(from) = switched_from {
+                let depth = segment.segment().start_depth();
+                if gas.density(depth, metres_per_bar) > self.max_density {
+                    return false;
+                }
+                if self.icd && counter_diffuses(&from, &gas) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl Default for GasConstraints {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_PP_O2, DEFAULT_MAX_DENSITY, true)
+    }
+}
+
+/// The deco gases chosen by [`select_deco_gases`], and the dive they give.
+#[derive(Copy, Clone)]
+pub struct GasSelection {
+    /// Number of gases chosen.
+    count: usize,
+    /// Gases chosen, in the order of the candidates.
+    gases: [Gas; DECO_GAS_CAPACITY],
+    /// Time to surface from the last level with the gases.
+    tts: Duration,
+    /// Total volume of gas breathed on the dive with the gases (measured in L).
+    gas_consumed: usize,
+}
+
+impl GasSelection {
+    /// Returns the gases chosen, in the order of the candidates. This is empty if no deco gas
+    /// improves on the bottom gas.
+    pub fn gases(&self) -> &[Gas] {
+        &self.gases[..self.count]
+    }
+
+    /// Returns the time to surface from the last level with the gases.
+    pub fn tts(&self) -> Duration {
+        self.tts
+    }
+
+    /// Returns the total volume of gas breathed on the dive with the gases (measured in L).
+    pub fn gas_consumed(&self) -> usize {
+        self.gas_consumed
+    }
+
+    /// Returns the value of an objective for the selection.
+    fn cost(&self, objective: Objective) -> (u64, usize) {
+        match objective {
+            Objective::Tts => (self.tts.as_secs(), self.count),
+            Objective::GasConsumed => (self.gas_consumed as u64, self.count),
+        }
+    }
+}
+
+/// Choose the subset of at most `max_gases` candidate gases that minimises an objective for a
+/// dive, replacing the deco gases of the plan. Every subset is calculated; those breaking a
+/// constraint, or needing more segments than a plan can hold, are skipped. Ties go to the subset
+/// with fewer gases.
+/// # Arguments
+/// * `plan` - Plan of the dive
+/// * `candidates` - Gases to choose from
+/// * `max_gases` - Most deco gases the diver can carry
+/// * `objective` - What to minimise
+/// * `constraints` - Limits every gas switch must respect
+/// # Errors
+/// This function will return a [`DivePlanError`] if there are more than [`CANDIDATE_CAPACITY`]
+/// candidates, if `max_gases` is above [`DECO_GAS_CAPACITY`], if the plan cannot be calculated
+/// without any deco gas, or if no subset meets the constraints.
+pub fn select_deco_gases(
+    plan: &DivePlan,
+    candidates: &[Gas],
+    max_gases: usize,
+    objective: Objective,
+    constraints: &GasConstraints,
+) -> Result<GasSelection, DivePlanError> {
+    if candidates.len() > CANDIDATE_CAPACITY || max_gases > DECO_GAS_CAPACITY {
+        return Err(DivePlanError::CapacityError);
+    }
+
+    let mut best: Option<GasSelection> = None;
+    // Every subset is calculated into the same dive, to keep the stack small.
+    let mut result = PlanResult::default();
+    for mask in 0..(1usize << candidates.len()) {
+        if mask.count_ones() as usize > max_gases {
+            continue;
+        }
+        let mut selection = GasSelection {
+            count: 0,
+            gases: [gas!(21, 0); DECO_GAS_CAPACITY],
+            tts: Duration::from_secs(0),
+            gas_consumed: 0,
+        };
+        let mut subset = *plan;
+        subset.clear_deco_gases();
+        for (idx, gas) in candidates.iter().enumerate() {
+            if mask & (1 << idx) != 0 {
+                subset.add_deco_gas(*gas)?;
+                selection.gases[selection.count] = *gas;
+                selection.count += 1;
+            }
+        }
+
+        match subset.plan_into(&mut result) {
+            Ok(()) => {}
+            // Without deco gases, the plan itself is at fault.
+            Err(e) if mask == 0 => return Err(e),
+            Err(_) => continue,
+        }
+        if !constraints.allows(&result, selection.gases()) {
+            continue;
+        }
+        selection.tts = result.tts();
+        selection.gas_consumed = result.segments().map(|t| t.gas_consumed()).sum();
+        let better = match best {
+            Some(t) => selection.cost(objective) < t.cost(objective),
+            None => true,
+        };
+        if better {
+            best = Some(selection);
+        }
+    }
+    best.ok_or(DivePlanError::NoGasSelectionError)
+}
+
+/// Returns whether a switch between gases breaks the rule of fifths: nitrogen must not rise by more
+/// than a fifth of the drop in helium.
+fn counter_diffuses(from: &Gas, to: &Gas) -> bool {
+    let he_drop = from.he() as isize - to.he() as isize;
+    let n2_rise = to.n2() as isize - from.n2() as isize;
+    he_drop > 0 && n2_rise * 5 > he_drop
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_of_fifths_limits_nitrogen_rise_to_a_fifth_of_helium_drop() {
+        for &((from_o2, from_he), (to_o2, to_he), expected) in [
+            // Helium drops by 45 and nitrogen rises by 13, which is more than 9.
+            ((18, 45), (50, 0), true),
+            // Helium drops by 10 and nitrogen rises by 7, which is more than 2.
+            ((18, 45), (21, 35), true),
+            // Helium drops by 20 and nitrogen rises by 3, which is less than 4.
+            ((18, 45), (35, 25), false),
+            // Helium drops by 20 and nitrogen rises by exactly 4.
+            ((18, 45), (34, 25), false),
+            // Nitrogen falls, and without helium there is nothing to counter diffuse.
+            ((21, 35), (100, 0), false),
+            ((21, 0), (50, 0), false),
+            // Helium rising is never a problem.
+            ((50, 0), (18, 45), false),
+        ]
+        .iter()
+        {
+            let from = gas!(from_o2, from_he);
+            let to = gas!(to_o2, to_he);
+            assert_eq!(
+                counter_diffuses(&from, &to),
+                expected,
+                "{}/{} to {}/{}",
+                from_o2,
+                from_he,
+                to_o2,
+                to_he
+            );
+        }
+    }
+}
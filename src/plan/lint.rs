@@ -0,0 +1,390 @@
+use crate::common::ascent::AscentRates;
+use crate::common::cns::cns_with_metres_per_bar;
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use crate::common::otu::otu_with_metres_per_bar;
+use crate::deco::zhl16::variant::Variant;
+use crate::deco::zhl16::ZHL16;
+use crate::deco::Tissue;
+use crate::plan::solver::{DEFAULT_MAX_CNS, DEFAULT_MAX_OTU};
+use crate::plan::DEFAULT_DECO_PP_O2;
+
+/// Maximum number of findings kept by a [`Lint`].
+pub const FINDING_CAPACITY: usize = 32;
+
+/// How serious a finding is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// Beyond a recommended limit, but within the hard limit.
+    Warning,
+    /// Beyond a hard limit.
+    Error,
+}
+
+/// The limit a finding is about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FindingKind {
+    /// ppO2 above the working limit at the bottom, or the deco limit on the ascent (measured in bar).
+    PpO2High,
+    /// ppO2 below the hypoxic limit (measured in bar).
+    PpO2Low,
+    /// Equivalent Narcotic Depth above the narcosis limit (measured in m).
+    End,
+    /// Gas density above a limit (measured in g L^-1).
+    Density,
+    /// Ascent faster than the ascent rates of the depths crossed allow (average rate, measured in
+    /// m min^-1).
+    AscentRate,
+    /// CNS oxygen toxicity accumulated so far above the limit (as a percentage).
+    Cns,
+    /// Oxygen Toxicity Units accumulated so far above the limit.
+    Otu,
+    /// The segment ends above the ceiling of the deco model at GF high (measured in m).
+    Ceiling,
+}
+
+/// A limit broken by a segment.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Finding {
+    /// Index of the segment in the sequence linted.
+    segment: usize,
+    /// How serious the finding is.
+    severity: Severity,
+    /// The limit the finding is about.
+    kind: FindingKind,
+    /// Value reached by the segment, in the unit of the kind.
+    value: Float,
+    /// Limit the value broke, in the unit of the kind.
+    limit: Float,
+}
+
+impl Finding {
+    /// Returns the index of the segment in the sequence linted.
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+
+    /// Returns how serious the finding is.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Returns the limit the finding is about.
+    pub fn kind(&self) -> FindingKind {
+        self.kind
+    }
+
+    /// Returns the value reached by the segment, in the unit of the kind.
+    pub fn value(&self) -> Float {
+        self.value
+    }
+
+    /// Returns the limit the value broke, in the unit of the kind.
+    pub fn limit(&self) -> Float {
+        self.limit
+    }
+}
+
+/// Limits checked by [`lint`].
+#[derive(Copy, Clone)]
+pub struct LintSettings {
+    /// Deco model of the diver before the first segment.
+    deco: ZHL16,
+    /// Depth of water required to induce 1 bar of pressure.
+    metres_per_bar: Float,
+    /// Lowest ppO2 allowed (measured in bar).
+    min_pp_o2: Float,
+    /// Highest ppO2 allowed at the bottom (measured in bar).
+    working_pp_o2: Float,
+    /// Highest ppO2 allowed on the ascent (measured in bar).
+    deco_pp_o2: Float,
+    /// Highest Equivalent Narcotic Depth allowed (measured in m).
+    max_end: usize,
+    /// Gas density above which a warning is given (measured in g L^-1).
+    density_warning: Float,
+    /// Highest gas density allowed (measured in g L^-1).
+    max_density: Float,
+    /// Fastest ascent rates allowed, which may depend on depth.
+    ascent_rates: AscentRates,
+    /// Highest CNS oxygen toxicity allowed (as a percentage).
+    max_cns: Float,
+    /// Most Oxygen Toxicity Units allowed.
+    max_otu: Float,
+}
+
+impl LintSettings {
+    /// Returns settings with commonly used limits: ppO2 from 0.16 to 1.4 bar at the bottom and
+    /// 1.6 bar on the ascent, END of 30 m, density warning at 5.2 g/L and limit at 6.2 g/L, ascent
+    /// at the default ascent rate, and the default CNS and OTU limits. Ceilings are checked with
+    /// ZHL-16C at GF 100/100 for a diver who has been at the surface for a long time.
+    pub fn new() -> Self {
+        Self {
+            deco: ZHL16::new_by_variant(Tissue::default(), 100, 100, Variant::C),
+            metres_per_bar: 10.0,
+            min_pp_o2: 0.16,
+            working_pp_o2: 1.4,
+            deco_pp_o2: DEFAULT_DECO_PP_O2,
+            max_end: 30,
+            density_warning: 5.2,
+            max_density: 6.2,
+            ascent_rates: AscentRates::default(),
+            max_cns: DEFAULT_MAX_CNS,
+            max_otu: DEFAULT_MAX_OTU,
+        }
+    }
+
+    /// Set the deco model ceilings are checked with. Ceilings use its GF high.
+    /// # Arguments
+    /// * `deco` - Deco model of the diver before the first segment
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn set_deco(&mut self, deco: ZHL16, metres_per_bar: Float) {
+        self.deco = deco;
+        self.metres_per_bar = metres_per_bar;
+    }
+
+    /// Set the ppO2 limits.
+    /// # Arguments
+    /// * `min_pp_o2` - Lowest ppO2 allowed (measured in bar)
+    /// * `working_pp_o2` - Highest ppO2 allowed at the bottom (measured in bar)
+    /// * `deco_pp_o2` - Highest ppO2 allowed on the ascent (measured in bar)
+    pub fn set_pp_o2_limits(&mut self, min_pp_o2: Float, working_pp_o2: Float, deco_pp_o2: Float) {
+        self.min_pp_o2 = min_pp_o2;
+        self.working_pp_o2 = working_pp_o2;
+        self.deco_pp_o2 = deco_pp_o2;
+    }
+
+    /// Set the highest Equivalent Narcotic Depth allowed (measured in m).
+    pub fn set_max_end(&mut self, max_end: usize) {
+        self.max_end = max_end;
+    }
+
+    /// Set the gas density limits.
+    /// # Arguments
+    /// * `density_warning` - Density above which a warning is given (measured in g L^-1)
+    /// * `max_density` - Highest density allowed (measured in g L^-1)
+    pub fn set_density_limits(&mut self, density_warning: Float, max_density: Float) {
+        self.density_warning = density_warning;
+        self.max_density = max_density;
+    }
+
+    /// Set the fastest ascent rates allowed. An ascent is too fast if it takes less time than the
+    /// rates of the bands it crosses allow.
+    pub fn set_ascent_rates(&mut self, ascent_rates: AscentRates) {
+        self.ascent_rates = ascent_rates;
+    }
+
+    /// Set the oxygen toxicity limits.
+    /// # Arguments
+    /// * `max_cns` - Highest CNS oxygen toxicity allowed (as a percentage of the NOAA limit)
+    /// * `max_otu` - Most Oxygen Toxicity Units allowed
+    pub fn set_toxicity_limits(&mut self, max_cns: Float, max_otu: Float) {
+        self.max_cns = max_cns;
+        self.max_otu = max_otu;
+    }
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The findings of [`lint`].
+#[derive(Copy, Clone)]
+pub struct Lint {
+    /// Number of findings kept.
+    len: usize,
+    /// Number of findings, including those past [`FINDING_CAPACITY`].
+    total: usize,
+    /// Findings in segment order.
+    findings: [Finding; FINDING_CAPACITY],
+}
+
+impl Lint {
+    /// Returns the findings in segment order. At most [`FINDING_CAPACITY`] are kept.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings[..self.len]
+    }
+
+    /// Returns the number of findings, including those that were not kept.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the most serious severity found, if there are any findings.
+    pub fn worst(&self) -> Option<Severity> {
+        self.findings().iter().map(|t| t.severity).max()
+    }
+
+    /// Record a finding.
+    fn push(
+        &mut self,
+        segment: usize,
+        severity: Severity,
+        kind: FindingKind,
+        value: Float,
+        limit: Float,
+    ) {
+        if self.len < FINDING_CAPACITY {
+            self.findings[self.len] = Finding {
+                segment,
+                severity,
+                kind,
+                value,
+                limit,
+            };
+            self.len += 1;
+        }
+        self.total += 1;
+    }
+}
+
+/// Check a sequence of segments and the gases breathed during them against safety limits, and
+/// report every limit broken. CNS and OTU are reported once, at the segment where the total first
+/// goes over.
+/// # Arguments
+/// * `segments` - Segments of the dive in order, each with the gas breathed during it
+/// * `settings` - Limits to check
+pub fn lint<I>(segments: I, settings: &LintSettings) -> Lint
+where
+    I: IntoIterator<Item = (DiveSegment, Gas)>,
+{
+    let mut lint = Lint {
+        len: 0,
+        total: 0,
+        findings: [Finding {
+            segment: 0,
+            severity: Severity::Warning,
+            kind: FindingKind::End,
+            value: 0.0,
+            limit: 0.0,
+        }; FINDING_CAPACITY],
+    };
+    let mut deco = settings.deco;
+    let metres_per_bar = settings.metres_per_bar;
+    let (_, gf_high) = deco.gradient_factors();
+    let (mut total_cns, mut total_otu) = (0.0, 0.0);
+
+    for (idx, (segment, gas)) in segments.into_iter().enumerate() {
+        let deepest = core::cmp::max(segment.start_depth(), segment.end_depth());
+        let shallowest = core::cmp::min(segment.start_depth(), segment.end_depth());
+        let ascending = segment.end_depth() < segment.start_depth();
+
+        let stop = matches!(
+            segment.segment_type(),
+            SegmentType::DeepStop | SegmentType::DecoStop
+        );
+        let max_pp_o2 = if ascending || stop {
+            settings.deco_pp_o2
+        } else {
+            settings.working_pp_o2
+        };
+        let max_p_o2 = gas.pp_o2(deepest, metres_per_bar);
+        if max_p_o2 > max_pp_o2 {
+            lint.push(
+                idx,
+                Severity::Error,
+                FindingKind::PpO2High,
+                max_p_o2,
+                max_pp_o2,
+            );
+        }
+        let min_p_o2 = gas.pp_o2(shallowest, metres_per_bar);
+        if min_p_o2 < settings.min_pp_o2 {
+            lint.push(
+                idx,
+                Severity::Error,
+                FindingKind::PpO2Low,
+                min_p_o2,
+                settings.min_pp_o2,
+            );
+        }
+
+        let end = gas.equivalent_narcotic_depth_with_metres_per_bar(deepest, metres_per_bar);
+        if end > settings.max_end as Float {
+            lint.push(
+                idx,
+                Severity::Warning,
+                FindingKind::End,
+                end,
+                settings.max_end as Float,
+            );
+        }
+
+        let density = gas.density(deepest, metres_per_bar);
+        if density > settings.max_density {
+            lint.push(
+                idx,
+                Severity::Error,
+                FindingKind::Density,
+                density,
+                settings.max_density,
+            );
+        } else if density > settings.density_warning {
+            lint.push(
+                idx,
+                Severity::Warning,
+                FindingKind::Density,
+                density,
+                settings.density_warning,
+            );
+        }
+
+        if ascending {
+            let delta_depth = (segment.start_depth() - segment.end_depth()) as Float;
+            let allowed = settings
+                .ascent_rates
+                .time_taken(segment.start_depth(), segment.end_depth());
+            if *segment.time() < allowed {
+                let rate = delta_depth / (segment.time().as_secs_f64() as Float / 60.0);
+                let max_rate = delta_depth / (allowed.as_secs_f64() as Float / 60.0);
+                lint.push(
+                    idx,
+                    Severity::Error,
+                    FindingKind::AscentRate,
+                    rate,
+                    max_rate,
+                );
+            }
+        }
+
+        let was_within = (total_cns <= settings.max_cns, total_otu <= settings.max_otu);
+        total_cns += cns_with_metres_per_bar(&segment, &gas, metres_per_bar);
+        total_otu += otu_with_metres_per_bar(&segment, &gas, metres_per_bar);
+        if was_within.0 && total_cns > settings.max_cns {
+            lint.push(
+                idx,
+                Severity::Error,
+                FindingKind::Cns,
+                total_cns,
+                settings.max_cns,
+            );
+        }
+        if was_within.1 && total_otu > settings.max_otu {
+            lint.push(
+                idx,
+                Severity::Error,
+                FindingKind::Otu,
+                total_otu,
+                settings.max_otu,
+            );
+        }
+
+        deco.add_segment(&segment, &gas, metres_per_bar);
+        let ceiling = deco.ceiling_depth(Some(gf_high), metres_per_bar);
+        if ceiling > segment.end_depth() as Float {
+            lint.push(
+                idx,
+                Severity::Error,
+                FindingKind::Ceiling,
+                ceiling,
+                segment.end_depth() as Float,
+            );
+        }
+    }
+    lint
+}
@@ -0,0 +1,691 @@
+//! Dive planning
+
+use crate::common::ascent::AscentRates;
+use crate::common::dive_profile::DiveProfileError;
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use crate::common::tank::Tank;
+use crate::common::water::WaterType;
+use crate::common::DEFAULT_DESCENT_RATE;
+use crate::deco::zhl16::gradient_factor::GradientFactorError;
+use crate::deco::zhl16::variant::Variant;
+use crate::deco::zhl16::{Conservatism, ZHL16};
+use crate::deco::Tissue;
+use crate::gas;
+use core::time::Duration;
+
+pub mod contingency;
+pub mod day;
+pub mod gas_selection;
+pub mod lint;
+pub mod ndl;
+pub mod result;
+pub mod solver;
+
+#[cfg(test)]
+mod tests;
+
+pub use contingency::{ContingencyTable, Schedule, ScheduleStop};
+pub use day::DiveDay;
+pub use gas_selection::{select_deco_gases, GasConstraints, GasSelection, Objective};
+pub use lint::{lint, Finding, FindingKind, Lint, LintSettings, Severity};
+pub use ndl::{NdlEntry, NdlTable, NdlTableError};
+pub use result::{PlanResult, PlanSegment};
+pub use solver::{Constraint, PlanLimits, Solution};
+
+/// Maximum number of bottom levels in a [`DivePlan`].
+pub const LEVEL_CAPACITY: usize = 8;
+
+/// Maximum number of deco gases in a [`DivePlan`].
+pub const DECO_GAS_CAPACITY: usize = 4;
+
+/// Maximum number of tanks in a [`DivePlan`].
+pub const TANK_CAPACITY: usize = 6;
+
+/// Default highest ppO2 a deco gas is switched to at (measured in bar).
+pub const DEFAULT_DECO_PP_O2: Float = 1.6;
+
+/// Default Surface Air Consumption (SAC) rate (measured in L min^-1).
+pub const DEFAULT_SAC_RATE: usize = 20;
+
+/// Represents errors that occur while building or calculating a DivePlan.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DivePlanError {
+    /// A list of the plan, or the dive it produces, has no room left.
+    CapacityError,
+    /// The plan has no bottom levels.
+    NoLevelError,
+    /// A bottom level is at the surface.
+    DepthError,
+    /// The ascent rate is not negative, or the descent rate is not positive.
+    RateError,
+    /// A gradient factor is out of range, or GF low is above GF high.
+    GradientFactorError,
+    /// A segment of the dive could not be joined to the one before it.
+    SegmentError,
+    /// The time between air breaks, or the length of a break, is zero.
+    AirBreakError,
+    /// A deep stop lasts no time, or the ratio of ratio deep stops is not above 0 and below 1.
+    DeepStopError,
+    /// No subset of the candidate deco gases gives a dive within the gas constraints.
+    NoGasSelectionError,
+}
+
+impl From<DiveProfileError> for DivePlanError {
+    fn from(e: DiveProfileError) -> Self {
+        match e {
+            DiveProfileError::CapacityError => DivePlanError::CapacityError,
+            DiveProfileError::RateError => DivePlanError::RateError,
+            DiveProfileError::ContinuityError | DiveProfileError::DurationError => {
+                DivePlanError::SegmentError
+            }
+        }
+    }
+}
+
+impl From<GradientFactorError> for DivePlanError {
+    fn from(_: GradientFactorError) -> Self {
+        DivePlanError::GradientFactorError
+    }
+}
+
+/// A part of the dive spent at one depth before the ascent.
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level {
+    /// Depth of the level.
+    depth: usize,
+    /// Time spent at the depth, not counting the descent or ascent to it.
+    time: Duration,
+    /// Gas breathed at the level and on the way to it.
+    gas: Gas,
+}
+
+impl Level {
+    /// Returns a new Level with the given parameters.
+    /// # Arguments
+    /// * `depth` - Depth of the level
+    /// * `time` - Time spent at the depth, not counting the descent or ascent to it
+    /// * `gas` - Gas breathed at the level and on the way to it
+    pub fn new(depth: usize, time: Duration, gas: Gas) -> Self {
+        Self { depth, time, gas }
+    }
+
+    /// Returns the depth of the level.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the time spent at the depth of the level.
+    pub fn time(&self) -> &Duration {
+        &self.time
+    }
+
+    /// Returns the gas breathed at the level.
+    pub fn gas(&self) -> &Gas {
+        &self.gas
+    }
+}
+
+/// Breaks on back gas during long deco stops on oxygen-rich gases. Time at stops on a gas above the
+/// ppO2 threshold adds up, and once it reaches the break interval the diver switches to the gas of
+/// the last level for the length of a break before carrying on. A stop on a leaner gas that lasts
+/// at least as long as a break counts as one.
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AirBreaks {
+    /// Time at stops on a gas above the threshold between breaks.
+    interval: Duration,
+    /// Length of each break.
+    break_time: Duration,
+    /// ppO2 above which stops are interrupted with breaks (measured in bar).
+    pp_o2: Float,
+}
+
+impl AirBreaks {
+    /// Returns a new AirBreaks with the given parameters.
+    /// # Arguments
+    /// * `interval` - Time at stops on a gas above `pp_o2` between breaks
+    /// * `break_time` - Length of each break
+    /// * `pp_o2` - ppO2 above which stops are interrupted with breaks (measured in bar)
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if `interval` or `break_time` is zero.
+    pub fn new(
+        interval: Duration,
+        break_time: Duration,
+        pp_o2: Float,
+    ) -> Result<Self, DivePlanError> {
+        if interval.as_secs() == 0 || break_time.as_secs() == 0 {
+            return Err(DivePlanError::AirBreakError);
+        }
+        Ok(Self {
+            interval,
+            break_time,
+            pp_o2,
+        })
+    }
+
+    /// Returns the time at stops on a gas above the threshold between breaks.
+    pub fn interval(&self) -> &Duration {
+        &self.interval
+    }
+
+    /// Returns the length of each break.
+    pub fn break_time(&self) -> &Duration {
+        &self.break_time
+    }
+
+    /// Returns the ppO2 above which stops are interrupted with breaks (measured in bar).
+    pub fn pp_o2(&self) -> Float {
+        self.pp_o2
+    }
+}
+
+/// Stops made deeper than the deco model requires, on the way up to the first required stop. They
+/// are not deco stops to the model, so they do not anchor the gradient factors, but the rest of the
+/// ascent is calculated from the tissue loading they leave.
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeepStops {
+    /// Pyle stops: a stop at the midpoint between the depth the ascent starts from and the first
+    /// required stop, repeated from each deep stop until the midpoint is no longer deeper than the
+    /// first required stop.
+    Pyle {
+        /// Time spent at each deep stop.
+        stop_time: Duration,
+    },
+    /// Ratio stops: a stop at a fraction of the depth the ascent starts from, then a stop at every
+    /// stop interval until the first required stop.
+    Ratio {
+        /// Fraction of the starting depth of the first deep stop, above 0 and below 1.
+        ratio: Float,
+        /// Time spent at each deep stop.
+        stop_time: Duration,
+    },
+}
+
+/// The inputs of a dive plan: bottom levels, deco gases, tanks and the settings to decompress with.
+/// Set it up with the `add_` and `set_` functions, then call [`DivePlan::plan`] to calculate the
+/// dive. The plan itself is left untouched, so it can be changed and calculated again.
+///
+/// The diver descends to each level in turn, then ascends with the stops the deco model requires.
+/// At each stop, the diver switches to the richest deco gas whose ppO2 at the stop is within the
+/// deco ppO2 limit. If air breaks are set, long stops on oxygen-rich gases are interrupted with
+/// breaks on the gas of the last level. If deep stops are set, they are made before the first
+/// required stop.
+#[derive(Copy, Clone)]
+pub struct DivePlan {
+    /// Deco model of the diver before the dive.
+    deco: ZHL16,
+    /// Water the dive takes place in.
+    water: WaterType,
+    /// Ascent rates, which may depend on depth.
+    ascent_rates: AscentRates,
+    /// Descent rate (measured in m min^-1)
+    descent_rate: isize,
+    /// SAC rate at the bottom levels (measured in L min^-1)
+    bottom_sac_rate: usize,
+    /// SAC rate on the ascent (measured in L min^-1)
+    deco_sac_rate: usize,
+    /// Highest ppO2 a deco gas is switched to at (measured in bar).
+    deco_pp_o2: Float,
+    /// Breaks on back gas during stops on oxygen-rich gases, if any.
+    air_breaks: Option<AirBreaks>,
+    /// Stops made deeper than the deco model requires, if any.
+    deep_stops: Option<DeepStops>,
+
+    /// Number of bottom levels in use.
+    level_count: usize,
+    /// Bottom levels, in order.
+    levels: [Level; LEVEL_CAPACITY],
+    /// Number of deco gases in use.
+    deco_gas_count: usize,
+    /// Gases available for the ascent.
+    deco_gases: [Gas; DECO_GAS_CAPACITY],
+    /// Number of tanks in use.
+    tank_count: usize,
+    /// Tanks carried on the dive, filled to their service pressure.
+    tanks: [Tank; TANK_CAPACITY],
+}
+
+impl DivePlan {
+    /// Returns an empty plan for a diver who has been at the surface for a long time, using ZHL-16C
+    /// at medium conservatism in salt water, with the default rates and SAC rates.
+    pub fn new() -> Self {
+        let (gf_low, gf_high) = Conservatism::Medium.gradient_factors();
+        let air = gas!(21, 0);
+        Self {
+            deco: ZHL16::new_by_variant(Tissue::default(), gf_low, gf_high, Variant::C),
+            water: WaterType::Salt,
+            ascent_rates: AscentRates::default(),
+            descent_rate: DEFAULT_DESCENT_RATE,
+            bottom_sac_rate: DEFAULT_SAC_RATE,
+            deco_sac_rate: DEFAULT_SAC_RATE,
+            deco_pp_o2: DEFAULT_DECO_PP_O2,
+            air_breaks: None,
+            deep_stops: None,
+            level_count: 0,
+            levels: [Level::new(0, Duration::from_secs(0), air); LEVEL_CAPACITY],
+            deco_gas_count: 0,
+            deco_gases: [air; DECO_GAS_CAPACITY],
+            tank_count: 0,
+            tanks: [Tank::new(air, 0, 0); TANK_CAPACITY],
+        }
+    }
+
+    /// Add a bottom level to the end of the plan.
+    /// # Arguments
+    /// * `level` - Level to add
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the level is at the surface, or if the plan
+    /// already has [`LEVEL_CAPACITY`] levels.
+    pub fn add_level(&mut self, level: Level) -> Result<(), DivePlanError> {
+        if level.depth == 0 {
+            return Err(DivePlanError::DepthError);
+        }
+        if self.level_count == LEVEL_CAPACITY {
+            return Err(DivePlanError::CapacityError);
+        }
+        self.levels[self.level_count] = level;
+        self.level_count += 1;
+        Ok(())
+    }
+
+    /// Add a gas the diver can switch to on the ascent.
+    /// # Arguments
+    /// * `gas` - Gas to add
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the plan already has
+    /// [`DECO_GAS_CAPACITY`] deco gases.
+    pub fn add_deco_gas(&mut self, gas: Gas) -> Result<(), DivePlanError> {
+        if self.deco_gas_count == DECO_GAS_CAPACITY {
+            return Err(DivePlanError::CapacityError);
+        }
+        self.deco_gases[self.deco_gas_count] = gas;
+        self.deco_gas_count += 1;
+        Ok(())
+    }
+
+    /// Add a tank the diver carries. Gas breathed on the dive is drawn from the first tank of the
+    /// same mix.
+    /// # Arguments
+    /// * `tank` - Tank to add
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the plan already has [`TANK_CAPACITY`]
+    /// tanks.
+    pub fn add_tank(&mut self, tank: Tank) -> Result<(), DivePlanError> {
+        if self.tank_count == TANK_CAPACITY {
+            return Err(DivePlanError::CapacityError);
+        }
+        self.tanks[self.tank_count] = tank;
+        self.tank_count += 1;
+        Ok(())
+    }
+
+    /// Remove every deco gas from the plan.
+    pub fn clear_deco_gases(&mut self) {
+        self.deco_gas_count = 0;
+    }
+
+    /// Remove every bottom level from the plan.
+    pub fn clear_levels(&mut self) {
+        self.level_count = 0;
+    }
+
+    /// Returns the bottom levels of the plan, in order.
+    pub fn levels(&self) -> &[Level] {
+        &self.levels[..self.level_count]
+    }
+
+    /// Returns the deco gases of the plan.
+    pub fn deco_gases(&self) -> &[Gas] {
+        &self.deco_gases[..self.deco_gas_count]
+    }
+
+    /// Returns the tanks of the plan.
+    pub fn tanks(&self) -> &[Tank] {
+        &self.tanks[..self.tank_count]
+    }
+
+    /// Returns the deco model of the diver before the dive.
+    pub fn deco(&self) -> &ZHL16 {
+        &self.deco
+    }
+
+    /// Set the deco model of the diver before the dive. Its tissues, gradient factors, stop settings
+    /// and gradient factor profile are all used by the plan. The model should have the diver at the
+    /// surface.
+    /// # Arguments
+    /// * `deco` - Deco model to plan with
+    pub fn set_deco(&mut self, deco: ZHL16) {
+        self.deco = deco;
+    }
+
+    /// Set the gradient factors of the deco model.
+    /// # Arguments
+    /// * `gf_low` - GF low (as a percentage)
+    /// * `gf_high` - GF high (as a percentage)
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if either value is zero or above 100, or if
+    /// `gf_low` is above `gf_high`. The plan is left unchanged.
+    pub fn set_gradient_factors(
+        &mut self,
+        gf_low: usize,
+        gf_high: usize,
+    ) -> Result<(), DivePlanError> {
+        self.deco.set_gradient_factors(gf_low, gf_high)?;
+        Ok(())
+    }
+
+    /// Returns the water the dive takes place in.
+    pub fn water(&self) -> WaterType {
+        self.water
+    }
+
+    /// Set the water the dive takes place in.
+    pub fn set_water(&mut self, water: WaterType) {
+        self.water = water;
+    }
+
+    /// Returns the ascent and descent rates of the plan (measured in m min^-1). The ascent rate is
+    /// the one below the deepest ascent rate band.
+    pub fn rates(&self) -> (isize, isize) {
+        (self.ascent_rates.rate(), self.descent_rate)
+    }
+
+    /// Set the ascent and descent rates of the plan. Any ascent rate bands are removed.
+    /// # Arguments
+    /// * `ascent_rate` - Ascent rate (measured in m min^-1)
+    /// * `descent_rate` - Descent rate (measured in m min^-1)
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if `ascent_rate` is not negative or
+    /// `descent_rate` is not positive. The plan is left unchanged.
+    pub fn set_rates(
+        &mut self,
+        ascent_rate: isize,
+        descent_rate: isize,
+    ) -> Result<(), DivePlanError> {
+        if ascent_rate >= 0 || descent_rate <= 0 {
+            return Err(DivePlanError::RateError);
+        }
+        self.ascent_rates = AscentRates::new(ascent_rate).map_err(|_| DivePlanError::RateError)?;
+        self.descent_rate = descent_rate;
+        Ok(())
+    }
+
+    /// Returns the ascent rates of the plan.
+    pub fn ascent_rates(&self) -> &AscentRates {
+        &self.ascent_rates
+    }
+
+    /// Set ascent rates that depend on depth, used for every ascent of the plan, including the ones
+    /// between levels.
+    /// # Arguments
+    /// * `ascent_rates` - Ascent rates to use
+    pub fn set_ascent_rates(&mut self, ascent_rates: AscentRates) {
+        self.ascent_rates = ascent_rates;
+    }
+
+    /// Returns the SAC rates at the bottom levels and on the ascent (measured in L min^-1).
+    pub fn sac_rates(&self) -> (usize, usize) {
+        (self.bottom_sac_rate, self.deco_sac_rate)
+    }
+
+    /// Set the SAC rates used to calculate gas consumption.
+    /// # Arguments
+    /// * `bottom_sac_rate` - SAC rate at the bottom levels and on the way to them (measured in L min^-1)
+    /// * `deco_sac_rate` - SAC rate on the ascent (measured in L min^-1)
+    pub fn set_sac_rates(&mut self, bottom_sac_rate: usize, deco_sac_rate: usize) {
+        self.bottom_sac_rate = bottom_sac_rate;
+        self.deco_sac_rate = deco_sac_rate;
+    }
+
+    /// Returns the highest ppO2 a deco gas is switched to at (measured in bar).
+    pub fn deco_pp_o2(&self) -> Float {
+        self.deco_pp_o2
+    }
+
+    /// Set the highest ppO2 a deco gas is switched to at.
+    /// # Arguments
+    /// * `deco_pp_o2` - ppO2 limit of deco gases (measured in bar)
+    pub fn set_deco_pp_o2(&mut self, deco_pp_o2: Float) {
+        self.deco_pp_o2 = deco_pp_o2;
+    }
+
+    /// Returns the air breaks of the plan, if any.
+    pub fn air_breaks(&self) -> Option<&AirBreaks> {
+        self.air_breaks.as_ref()
+    }
+
+    /// Set the air breaks taken during stops on oxygen-rich gases.
+    /// # Arguments
+    /// * `air_breaks` - Air breaks to take, or `None` to stay on the deco gas for whole stops
+    pub fn set_air_breaks(&mut self, air_breaks: Option<AirBreaks>) {
+        self.air_breaks = air_breaks;
+    }
+
+    /// Returns the deep stops of the plan, if any.
+    pub fn deep_stops(&self) -> Option<&DeepStops> {
+        self.deep_stops.as_ref()
+    }
+
+    /// Set the deep stops made before the first required stop.
+    /// # Arguments
+    /// * `deep_stops` - Deep stops to make, or `None` to only make the stops the deco model requires
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if a deep stop lasts no time, or if the ratio
+    /// of ratio deep stops is not above 0 and below 1. The plan is left unchanged.
+    pub fn set_deep_stops(&mut self, deep_stops: Option<DeepStops>) -> Result<(), DivePlanError> {
+        match deep_stops {
+            Some(DeepStops::Pyle { stop_time }) if stop_time.as_secs() == 0 => {
+                return Err(DivePlanError::DeepStopError)
+            }
+            Some(DeepStops::Ratio { ratio, stop_time })
+                if stop_time.as_secs() == 0 || ratio <= 0.0 || ratio >= 1.0 =>
+            {
+                return Err(DivePlanError::DeepStopError)
+            }
+            _ => {}
+        }
+        self.deep_stops = deep_stops;
+        Ok(())
+    }
+
+    /// Calculate the dive: move to each level in turn, then ascend to the surface with every stop the
+    /// deco model requires. Moving up to a level above the ceiling makes the stops below it first.
+    ///
+    /// The result is returned by value, which costs a whole [`PlanResult`] of stack. Use
+    /// [`DivePlan::plan_into`] to fill one kept elsewhere instead.
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the plan has no levels, or if the dive
+    /// needs more segments than a [`DiveProfile`](crate::common::DiveProfile) can hold.
+    pub fn plan(&self) -> Result<PlanResult, DivePlanError> {
+        let mut result = PlanResult::default();
+        self.plan_into(&mut result)?;
+        Ok(result)
+    }
+
+    /// Calculate the dive into an existing [`PlanResult`], replacing whatever it held. See
+    /// [`DivePlan::plan`].
+    /// # Arguments
+    /// * `result` - Dive to overwrite with the calculated one
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if the plan has no levels, or if the dive
+    /// needs more segments than a [`DiveProfile`](crate::common::DiveProfile) can hold. `result`
+    /// then holds the part of the dive calculated before the error.
+    pub fn plan_into(&self, result: &mut PlanResult) -> Result<(), DivePlanError> {
+        if self.level_count == 0 {
+            return Err(DivePlanError::NoLevelError);
+        }
+
+        result.reset(&self.deco, self.water.metres_per_bar(), self.tanks());
+        for level in self.levels() {
+            if level.depth < result.deco().depth() {
+                if result.deco().stop_depth(result.metres_per_bar()) > level.depth {
+                    // The level is above the ceiling, so the stops below it are made first.
+                    self.ascend(result, level.gas, level.gas, level.depth)?;
+                } else {
+                    self.ascend_to(result, level.depth, &level.gas, self.bottom_sac_rate)?;
+                }
+            }
+            let segment = DiveSegment::constant(
+                SegmentType::DiveSegment,
+                level.depth,
+                level.time,
+                self.ascent_rates.rate_at(level.depth),
+                self.descent_rate,
+            );
+            result.push(segment, &level.gas, self.bottom_sac_rate)?;
+        }
+        result.end_bottom();
+
+        let back_gas = self.levels[self.level_count - 1].gas;
+        let gas = self.deep_stop(result, back_gas)?;
+        self.ascend(result, gas, back_gas, 0)
+    }
+
+    /// Make the deep stops of the plan, if any, and return the gas breathed after them.
+    fn deep_stop(&self, result: &mut PlanResult, mut gas: Gas) -> Result<Gas, DivePlanError> {
+        let metres_per_bar = result.metres_per_bar();
+        let stop_settings = result.deco().stop_settings();
+        let (stop_time, mut next) = match self.deep_stops {
+            None => return Ok(gas),
+            Some(DeepStops::Pyle { stop_time }) => (stop_time, None),
+            Some(DeepStops::Ratio { ratio, stop_time }) => {
+                let depth = result.deco().depth() as Float * ratio;
+                (stop_time, Some(stop_settings.stop_depth(depth)))
+            }
+        };
+
+        loop {
+            let depth = result.deco().depth();
+            let first_stop = result.deco().stop_depth(metres_per_bar);
+            let stop_depth = match next {
+                Some(t) => t,
+                None => stop_settings.stop_depth((depth + first_stop) as Float / 2.0),
+            };
+            if first_stop == 0 || stop_depth <= first_stop || stop_depth >= depth {
+                return Ok(gas);
+            }
+
+            self.ascend_to(result, stop_depth, &gas, self.deco_sac_rate)?;
+            gas = self.deco_gas(stop_depth, gas, metres_per_bar);
+            let stop = DiveSegment::constant(
+                SegmentType::DeepStop,
+                stop_depth,
+                stop_time,
+                self.ascent_rates.rate_at(stop_depth),
+                self.descent_rate,
+            );
+            result.push(stop, &gas, self.deco_sac_rate)?;
+            next = next.map(|t| stop_settings.next_stop_depth(t));
+        }
+    }
+
+    /// Ascend from the end of a dive to a depth, making every stop the deco model requires below it
+    /// and taking air breaks on `back_gas`.
+    fn ascend(
+        &self,
+        result: &mut PlanResult,
+        mut gas: Gas,
+        back_gas: Gas,
+        target: usize,
+    ) -> Result<(), DivePlanError> {
+        let metres_per_bar = result.metres_per_bar();
+        // Time spent at stops on an oxygen-rich gas since the last air break.
+        let mut since_break = Duration::from_secs(0);
+        loop {
+            let depth = result.deco().depth();
+            let stop_depth = result.deco().stop_depth(metres_per_bar);
+
+            if stop_depth <= target {
+                self.ascend_to(result, target, &gas, self.deco_sac_rate)?;
+                return Ok(());
+            }
+
+            if stop_depth < depth {
+                // Ascend to the stop first, as the ceiling may have moved by the time it is reached.
+                self.ascend_to(result, stop_depth, &gas, self.deco_sac_rate)?;
+                gas = self.deco_gas(stop_depth, gas, metres_per_bar);
+                continue;
+            }
+
+            // The only error is having no stop to make, which was ruled out above.
+            let stop = result
+                .deco()
+                .next_stop_with_rates(&self.ascent_rates, self.descent_rate, &gas, metres_per_bar)
+                .map_err(|_| DivePlanError::SegmentError)?;
+
+            if let Some(breaks) = self.air_breaks {
+                if gas.pp_o2(depth, metres_per_bar) > breaks.pp_o2 {
+                    let left = breaks.interval - since_break.min(breaks.interval);
+                    if *stop.time() > left {
+                        // Breathe the deco gas until the break is due, then take the break. The
+                        // stop is worked out again afterwards, as the break slows off-gassing.
+                        if left.as_secs() > 0 {
+                            result.push(self.stop(depth, left), &gas, self.deco_sac_rate)?;
+                        }
+                        result.push(
+                            self.stop(depth, breaks.break_time),
+                            &back_gas,
+                            self.deco_sac_rate,
+                        )?;
+                        since_break = Duration::from_secs(0);
+                        continue;
+                    }
+                    since_break += *stop.time();
+                } else if *stop.time() >= breaks.break_time {
+                    // A stop on a leaner gas that lasts as long as a break counts as one.
+                    since_break = Duration::from_secs(0);
+                }
+            }
+            result.push(stop, &gas, self.deco_sac_rate)?;
+        }
+    }
+
+    /// Returns a deco stop at the rates of the plan.
+    fn stop(&self, depth: usize, time: Duration) -> DiveSegment {
+        DiveSegment::constant(
+            SegmentType::DecoStop,
+            depth,
+            time,
+            self.ascent_rates.rate_at(depth),
+            self.descent_rate,
+        )
+    }
+
+    /// Ascend from the end of a dive to a depth at the ascent rates of the plan, with one segment
+    /// for each ascent rate band crossed.
+    fn ascend_to(
+        &self,
+        result: &mut PlanResult,
+        depth: usize,
+        gas: &Gas,
+        sac_rate: usize,
+    ) -> Result<(), DivePlanError> {
+        let from = result.deco().depth();
+        for segment in self.ascent_rates.segments(from, depth, self.descent_rate) {
+            result.push(segment, gas, sac_rate)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the gas to breathe at a depth on the ascent: the richest deco gas within the ppO2
+    /// limit, if it is richer than the gas already breathed.
+    fn deco_gas(&self, depth: usize, current: Gas, metres_per_bar: Float) -> Gas {
+        self.deco_gases()
+            .iter()
+            .filter(|t| t.pp_o2(depth, metres_per_bar) <= self.deco_pp_o2)
+            .fold(
+                current,
+                |best, t| if t.o2() > best.o2() { *t } else { best },
+            )
+    }
+}
+
+impl Default for DivePlan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,209 @@
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::{ceil, Float};
+use crate::common::gas::Gas;
+use crate::common::{altitude_pressure, time_taken};
+use crate::deco::zhl16::ZHL16;
+use crate::gas;
+use core::time::Duration;
+
+/// Maximum number of entries in an [`NdlTable`].
+pub const NDL_TABLE_CAPACITY: usize = 48;
+
+/// Highest altitude an [`NdlTable`] can be calculated for (measured in m).
+pub const MAX_ALTITUDE: usize = 7000;
+
+/// Represents errors that occur while calculating an NdlTable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NdlTableError {
+    /// There are no depths or gases, or more entries than the table can hold.
+    CapacityError,
+    /// The altitude is above [`MAX_ALTITUDE`].
+    AltitudeError,
+}
+
+/// Depths of the standard NDL table, from 12 m to 45 m in 3 m steps.
+pub const NDL_DEPTHS: [usize; 12] = [12, 15, 18, 21, 24, 27, 30, 33, 36, 39, 42, 45];
+
+/// The no-decompression limit at one depth on one gas.
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NdlEntry {
+    /// Depth of the entry.
+    depth: usize,
+    /// Gas breathed on the descent and at the depth.
+    gas: Gas,
+    /// Time that can be spent at the depth once it is reached. Zero if the diver would already
+    /// need to decompress.
+    ndl: Duration,
+}
+
+impl NdlEntry {
+    /// Returns the depth of the entry.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the gas of the entry.
+    pub fn gas(&self) -> &Gas {
+        &self.gas
+    }
+
+    /// Returns the time that can be spent at the depth once it is reached.
+    pub fn ndl(&self) -> Duration {
+        self.ndl
+    }
+}
+
+/// No-decompression limits at a set of depths, for a diver starting from the surface with some
+/// residual tissue loading.
+#[derive(Copy, Clone)]
+pub struct NdlTable {
+    /// Number of entries in use.
+    len: usize,
+    /// Entries of the table, in order.
+    entries: [NdlEntry; NDL_TABLE_CAPACITY],
+}
+
+impl NdlTable {
+    /// Returns the NDLs at the standard depths ([`NDL_DEPTHS`]) for a gas. The model is not
+    /// changed; each entry descends a virtual copy of it from the surface.
+    /// # Arguments
+    /// * `deco` - Deco model of the diver at the surface
+    /// * `gas` - Gas breathed on the descent and at the depth
+    /// * `descent_rate` - Descent rate (measured in m min^-1)
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    pub fn new(deco: &ZHL16, gas: &Gas, descent_rate: isize, metres_per_bar: Float) -> Self {
+        let mut table = Self::empty();
+        for &depth in NDL_DEPTHS.iter() {
+            table.push(entry(deco, depth, gas, descent_rate, metres_per_bar, 1.0));
+        }
+        table
+    }
+
+    /// Returns the NDLs at each depth for each gas, after a surface interval at an altitude. The
+    /// model is not changed. Entries are ordered by gas, then by depth.
+    ///
+    /// At altitude, each depth is treated as the depth at sea level with the same ambient pressure
+    /// (rounded deeper), and the diver must be able to ascend to the lower surface pressure. The
+    /// surface interval is spent at sea level pressure, which slightly overstates the residual
+    /// loading at altitude.
+    /// # Arguments
+    /// * `deco` - Deco model of the diver at the surface
+    /// * `depths` - Depths to calculate NDLs at
+    /// * `gases` - Gases to calculate NDLs for
+    /// * `surface_interval` - Time spent at the surface breathing air before the dive
+    /// * `altitude` - Altitude of the dive site above sea level (measured in m)
+    /// * `descent_rate` - Descent rate (measured in m min^-1)
+    /// * `metres_per_bar` - Depth of water required to induce 1 bar of pressure
+    /// # Errors
+    /// This function will return an [`NdlTableError`] if there are no depths or gases, if there
+    /// would be more than [`NDL_TABLE_CAPACITY`] entries, or if `altitude` is above
+    /// [`MAX_ALTITUDE`].
+    pub fn for_depths(
+        deco: &ZHL16,
+        depths: &[usize],
+        gases: &[Gas],
+        surface_interval: Duration,
+        altitude: usize,
+        descent_rate: isize,
+        metres_per_bar: Float,
+    ) -> Result<Self, NdlTableError> {
+        match depths.len().checked_mul(gases.len()) {
+            Some(count) if count > 0 && count <= NDL_TABLE_CAPACITY => {}
+            _ => return Err(NdlTableError::CapacityError),
+        }
+        if altitude > MAX_ALTITUDE {
+            return Err(NdlTableError::AltitudeError);
+        }
+
+        let mut surfaced = *deco;
+        if surface_interval > Duration::from_secs(0) {
+            let segment =
+                DiveSegment::constant(SegmentType::DiveSegment, 0, surface_interval, -1, 1);
+            surfaced.add_segment(&segment, &gas!(21, 0), metres_per_bar);
+        }
+
+        let surface_pressure = altitude_pressure(altitude);
+        let mut table = Self::empty();
+        for gas in gases {
+            for &depth in depths {
+                let mut t = entry(
+                    &surfaced,
+                    sea_level_depth(depth, surface_pressure, metres_per_bar),
+                    gas,
+                    descent_rate,
+                    metres_per_bar,
+                    surface_pressure,
+                );
+                t.depth = depth;
+                table.push(t);
+            }
+        }
+        Ok(table)
+    }
+
+    /// Returns a table with no entries.
+    fn empty() -> Self {
+        Self {
+            len: 0,
+            entries: [NdlEntry {
+                depth: 0,
+                gas: gas!(21, 0),
+                ndl: Duration::from_secs(0),
+            }; NDL_TABLE_CAPACITY],
+        }
+    }
+
+    /// Append an entry to the table. Callers check the capacity first.
+    fn push(&mut self, entry: NdlEntry) {
+        self.entries[self.len] = entry;
+        self.len += 1;
+    }
+
+    /// Returns the entries of the table, in order.
+    pub fn entries(&self) -> &[NdlEntry] {
+        &self.entries[..self.len]
+    }
+}
+
+/// Returns the NDL at a depth for a virtual copy of the model descending to it from the surface.
+fn entry(
+    deco: &ZHL16,
+    depth: usize,
+    gas: &Gas,
+    descent_rate: isize,
+    metres_per_bar: Float,
+    surface_pressure: Float,
+) -> NdlEntry {
+    let mut virtual_deco = *deco;
+    if let Ok(descent) = DiveSegment::new(
+        SegmentType::AscDesc,
+        virtual_deco.depth(),
+        depth,
+        time_taken(descent_rate, virtual_deco.depth(), depth),
+        -descent_rate.abs(),
+        descent_rate.abs(),
+    ) {
+        virtual_deco.add_segment(&descent, gas, metres_per_bar);
+    }
+    let ndl = virtual_deco
+        .ndl_with_compartment(gas, metres_per_bar, surface_pressure)
+        .map(|(t, _)| *t.time())
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    NdlEntry {
+        depth,
+        gas: *gas,
+        ndl,
+    }
+}
+
+/// Returns the depth at sea level with the same ambient pressure as a depth at a lower surface
+/// pressure, rounded deeper.
+fn sea_level_depth(depth: usize, surface_pressure: Float, metres_per_bar: Float) -> usize {
+    let equivalent = depth as Float - (1.0 - surface_pressure) * metres_per_bar;
+    if equivalent <= 0.0 {
+        0
+    } else {
+        ceil(equivalent) as usize
+    }
+}
@@ -0,0 +1,295 @@
+use crate::common::cns::cns_with_metres_per_bar;
+use crate::common::dive_profile::{DiveProfile, PROFILE_CAPACITY};
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use crate::common::otu::otu_with_metres_per_bar;
+use crate::common::tank::Tank;
+use crate::deco::zhl16::ZHL16;
+use crate::gas;
+use crate::plan::{DivePlan, DivePlanError, TANK_CAPACITY};
+use core::time::Duration;
+
+/// A segment of a calculated dive, with the gas breathed during it.
+#[derive(Copy, Clone)]
+pub struct PlanSegment {
+    /// The segment itself.
+    segment: DiveSegment,
+    /// Gas breathed during the segment.
+    gas: Gas,
+    /// Time from the start of the dive to the end of the segment.
+    runtime: Duration,
+    /// Volume of gas breathed during the segment (measured in L).
+    gas_consumed: usize,
+}
+
+impl PlanSegment {
+    /// Returns the segment itself.
+    pub fn segment(&self) -> &DiveSegment {
+        &self.segment
+    }
+
+    /// Returns the gas breathed during the segment.
+    pub fn gas(&self) -> &Gas {
+        &self.gas
+    }
+
+    /// Returns the time from the start of the dive to the end of the segment.
+    pub fn runtime(&self) -> Duration {
+        self.runtime
+    }
+
+    /// Returns the volume of gas breathed during the segment (measured in L).
+    pub fn gas_consumed(&self) -> usize {
+        self.gas_consumed
+    }
+}
+
+/// A dive calculated from a [`DivePlan`], with its totals and the deco model of the diver on
+/// surfacing.
+///
+/// Every segment is stored inline, so a PlanResult takes about 7 KB (6.5 KB in single precision)
+/// on a 64-bit host, and somewhat less on 32-bit targets. [`DivePlan::plan`] returns it by value,
+/// so on a device with a small stack, keep one in static or heap memory and fill it with
+/// [`DivePlan::plan_into`] instead.
+#[derive(Copy, Clone)]
+pub struct PlanResult {
+    /// Deco model of the diver at the end of the dive so far.
+    deco: ZHL16,
+    /// Depth of water required to induce 1 bar of pressure.
+    metres_per_bar: Float,
+    /// Segments of the dive.
+    profile: DiveProfile,
+    /// Gas breathed during each segment.
+    gases: [Gas; PROFILE_CAPACITY],
+    /// Volume of gas breathed during each segment (measured in L).
+    consumed: [usize; PROFILE_CAPACITY],
+    /// Number of segments before the ascent from the last level.
+    bottom_len: usize,
+    /// Oxygen Toxicity Units accumulated during the dive.
+    otu: Float,
+    /// CNS oxygen toxicity accumulated during the dive (as a percentage).
+    cns: Float,
+    /// Number of tanks in use.
+    tank_count: usize,
+    /// Tanks carried on the dive.
+    tanks: [Tank; TANK_CAPACITY],
+    /// Volume of gas drawn from each tank (measured in L).
+    tank_consumed: [usize; TANK_CAPACITY],
+}
+
+impl PlanResult {
+    /// Returns an empty dive starting from a deco model.
+    pub(crate) fn new(deco: &ZHL16, metres_per_bar: Float, tanks: &[Tank]) -> Self {
+        let air = gas!(21, 0);
+        let mut result = Self {
+            deco: *deco,
+            metres_per_bar,
+            profile: DiveProfile::new(),
+            gases: [air; PROFILE_CAPACITY],
+            consumed: [0; PROFILE_CAPACITY],
+            bottom_len: 0,
+            otu: 0.0,
+            cns: 0.0,
+            tank_count: tanks.len(),
+            tanks: [Tank::new(air, 0, 0); TANK_CAPACITY],
+            tank_consumed: [0; TANK_CAPACITY],
+        };
+        result.tanks[..tanks.len()].copy_from_slice(tanks);
+        result
+    }
+
+    /// Empty the dive in place and start it again from a deco model, without building a new
+    /// PlanResult on the stack.
+    pub(crate) fn reset(&mut self, deco: &ZHL16, metres_per_bar: Float, tanks: &[Tank]) {
+        self.deco = *deco;
+        self.metres_per_bar = metres_per_bar;
+        self.profile.clear();
+        self.bottom_len = 0;
+        self.otu = 0.0;
+        self.cns = 0.0;
+        self.tank_count = tanks.len();
+        self.tanks[..tanks.len()].copy_from_slice(tanks);
+        self.tank_consumed = [0; TANK_CAPACITY];
+    }
+
+    /// Append a segment to the dive, along with any depth change needed to reach it, and load the
+    /// deco model with them.
+    pub(crate) fn push(
+        &mut self,
+        segment: DiveSegment,
+        gas: &Gas,
+        sac_rate: usize,
+    ) -> Result<(), DivePlanError> {
+        let start = self.profile.len();
+        self.profile.push(segment)?;
+        for idx in start..self.profile.len() {
+            let segment = self.profile.segments()[idx];
+            self.deco.add_segment(&segment, gas, self.metres_per_bar);
+
+            let consumed = segment.gas_consumed(sac_rate, self.metres_per_bar);
+            self.gases[idx] = *gas;
+            self.consumed[idx] = consumed;
+            self.otu += otu_with_metres_per_bar(&segment, gas, self.metres_per_bar);
+            self.cns += cns_with_metres_per_bar(&segment, gas, self.metres_per_bar);
+            if let Some(tank) = self.tanks[..self.tank_count]
+                .iter()
+                .position(|t| t.gas() == gas)
+            {
+                self.tank_consumed[tank] += consumed;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark the end of the bottom levels.
+    pub(crate) fn end_bottom(&mut self) {
+        self.bottom_len = self.profile.len();
+    }
+
+    /// Returns the deco model of the diver at the end of the dive.
+    pub fn deco(&self) -> &ZHL16 {
+        &self.deco
+    }
+
+    /// Returns the depth of water required to induce 1 bar of pressure in the dive.
+    pub fn metres_per_bar(&self) -> Float {
+        self.metres_per_bar
+    }
+
+    /// Returns the segments of the dive, without the gas breathed during them.
+    pub fn profile(&self) -> &DiveProfile {
+        &self.profile
+    }
+
+    /// Returns the number of segments in the dive.
+    pub fn len(&self) -> usize {
+        self.profile.len()
+    }
+
+    /// Returns true if the dive has no segments.
+    pub fn is_empty(&self) -> bool {
+        self.profile.is_empty()
+    }
+
+    /// Returns a segment of the dive, if it exists.
+    /// # Arguments
+    /// * `idx` - Index of the segment, starting from the first descent
+    pub fn segment(&self, idx: usize) -> Option<PlanSegment> {
+        let segments = self.profile.segments();
+        segments.get(idx).map(|segment| PlanSegment {
+            segment: *segment,
+            gas: self.gases[idx],
+            runtime: segments[..=idx]
+                .iter()
+                .fold(Duration::from_secs(0), |acc, t| acc + *t.time()),
+            gas_consumed: self.consumed[idx],
+        })
+    }
+
+    /// Returns the segments of the dive in order, with the gas breathed during them.
+    pub fn segments(&self) -> impl Iterator<Item = PlanSegment> + '_ {
+        let mut runtime = Duration::from_secs(0);
+        self.profile
+            .segments()
+            .iter()
+            .enumerate()
+            .map(move |(idx, segment)| {
+                runtime += *segment.time();
+                PlanSegment {
+                    segment: *segment,
+                    gas: self.gases[idx],
+                    runtime,
+                    gas_consumed: self.consumed[idx],
+                }
+            })
+    }
+
+    /// Returns the stops of the ascent in order: deep stops, then deco stops.
+    pub fn stops(&self) -> impl Iterator<Item = PlanSegment> + '_ {
+        self.segments().filter(|t| {
+            matches!(
+                t.segment.segment_type(),
+                SegmentType::DeepStop | SegmentType::DecoStop
+            )
+        })
+    }
+
+    /// Returns the total duration of the dive.
+    pub fn runtime(&self) -> Duration {
+        self.profile.runtime()
+    }
+
+    /// Returns the time from leaving the last level to reaching the surface, including every stop.
+    pub fn tts(&self) -> Duration {
+        self.profile.segments()[self.bottom_len..]
+            .iter()
+            .fold(Duration::from_secs(0), |acc, t| acc + *t.time())
+    }
+
+    /// Returns the Oxygen Toxicity Units accumulated during the dive.
+    pub fn otu(&self) -> Float {
+        self.otu
+    }
+
+    /// Returns the CNS oxygen toxicity accumulated during the dive (as a percentage of the NOAA
+    /// limit).
+    pub fn cns(&self) -> Float {
+        self.cns
+    }
+
+    /// Returns the volume of a gas breathed during the dive (measured in L).
+    /// # Arguments
+    /// * `gas` - Gas to total up
+    pub fn gas_consumed(&self, gas: &Gas) -> usize {
+        self.gases[..self.profile.len()]
+            .iter()
+            .zip(self.consumed.iter())
+            .filter(|(t, _)| *t == gas)
+            .map(|(_, consumed)| consumed)
+            .sum()
+    }
+
+    /// Returns the tanks carried on the dive.
+    pub fn tanks(&self) -> &[Tank] {
+        &self.tanks[..self.tank_count]
+    }
+
+    /// Returns the volume of gas drawn from a tank (measured in L), if it exists.
+    /// # Arguments
+    /// * `idx` - Index of the tank, in the order they were added to the plan
+    pub fn tank_consumed(&self, idx: usize) -> Option<usize> {
+        self.tanks().get(idx).map(|_| self.tank_consumed[idx])
+    }
+
+    /// Returns the pressure left in a tank at the end of the dive (measured in bar), if it exists.
+    /// The tank starts at its service pressure, and the pressure used is rounded up to whole bar so
+    /// the figure is never optimistic. The pressure is negative if the dive needs more gas than the
+    /// tank holds.
+    /// # Arguments
+    /// * `idx` - Index of the tank, in the order they were added to the plan
+    pub fn tank_pressure(&self, idx: usize) -> Option<isize> {
+        self.tanks().get(idx).map(|t| {
+            let consumed = self.tank_consumed[idx];
+            let used = if t.raw_volume() == 0 {
+                0
+            } else {
+                let used = consumed / t.raw_volume();
+                if used * t.raw_volume() < consumed {
+                    used + 1
+                } else {
+                    used
+                }
+            };
+            t.service_pressure() as isize - used as isize
+        })
+    }
+}
+
+impl Default for PlanResult {
+    /// Returns an empty dive, to be filled by [`DivePlan::plan_into`].
+    fn default() -> Self {
+        let plan = DivePlan::new();
+        Self::new(plan.deco(), plan.water().metres_per_bar(), &[])
+    }
+}
@@ -0,0 +1,259 @@
+use crate::common::dive_segment::SegmentType;
+use crate::common::float::Float;
+use crate::plan::{DivePlan, DivePlanError, Level, PlanResult, TANK_CAPACITY};
+use core::time::Duration;
+
+/// Longest bottom time the solver searches up to (measured in minutes).
+pub const SOLVER_TIME_LIMIT: usize = 300;
+
+/// Deepest depth the solver searches down to (measured in m).
+pub const SOLVER_DEPTH_LIMIT: usize = 150;
+
+/// Default CNS oxygen toxicity limit of a dive (as a percentage of the NOAA limit).
+pub const DEFAULT_MAX_CNS: Float = 100.0;
+
+/// Default OTU limit of a dive.
+pub const DEFAULT_MAX_OTU: Float = 300.0;
+
+/// Default highest ppO2 of a gas on the way down and at the levels (measured in bar).
+pub const DEFAULT_MAX_PP_O2: Float = 1.4;
+
+/// The limits a dive found by the solver must stay within.
+#[derive(Copy, Clone, PartialEq)]
+pub struct PlanLimits {
+    /// Longest time to surface allowed, if any.
+    max_tts: Option<Duration>,
+    /// Highest CNS oxygen toxicity allowed (as a percentage).
+    max_cns: Float,
+    /// Most Oxygen Toxicity Units allowed.
+    max_otu: Float,
+    /// Highest ppO2 allowed on the way down and at the levels (measured in bar).
+    max_pp_o2: Float,
+    /// Pressure that must be left in each tank of the plan (measured in bar).
+    tank_reserves: [usize; TANK_CAPACITY],
+}
+
+impl PlanLimits {
+    /// Returns limits with no TTS limit, no tank reserves, and the default CNS, OTU and ppO2
+    /// limits.
+    pub fn new() -> Self {
+        Self {
+            max_tts: None,
+            max_cns: DEFAULT_MAX_CNS,
+            max_otu: DEFAULT_MAX_OTU,
+            max_pp_o2: DEFAULT_MAX_PP_O2,
+            tank_reserves: [0; TANK_CAPACITY],
+        }
+    }
+
+    /// Set the longest time to surface allowed, or `None` for no limit.
+    pub fn set_max_tts(&mut self, max_tts: Option<Duration>) {
+        self.max_tts = max_tts;
+    }
+
+    /// Set the highest CNS oxygen toxicity allowed (as a percentage of the NOAA limit).
+    pub fn set_max_cns(&mut self, max_cns: Float) {
+        self.max_cns = max_cns;
+    }
+
+    /// Set the most Oxygen Toxicity Units allowed.
+    pub fn set_max_otu(&mut self, max_otu: Float) {
+        self.max_otu = max_otu;
+    }
+
+    /// Set the highest ppO2 allowed on the way down and at the levels (measured in bar). This
+    /// keeps the levels within the maximum operating depth of their gas.
+    pub fn set_max_pp_o2(&mut self, max_pp_o2: Float) {
+        self.max_pp_o2 = max_pp_o2;
+    }
+
+    /// Set the pressure that must be left in a tank at the end of the dive.
+    /// # Arguments
+    /// * `idx` - Index of the tank, in the order they were added to the plan
+    /// * `reserve` - Reserve pressure (measured in bar)
+    /// # Errors
+    /// This function will return a [`DivePlanError`] if `idx` is not below [`TANK_CAPACITY`].
+    pub fn set_tank_reserve(&mut self, idx: usize, reserve: usize) -> Result<(), DivePlanError> {
+        if idx >= TANK_CAPACITY {
+            return Err(DivePlanError::CapacityError);
+        }
+        self.tank_reserves[idx] = reserve;
+        Ok(())
+    }
+
+    /// Returns the first limit a calculated dive breaks, if any.
+    /// # Arguments
+    /// * `result` - Dive to check
+    pub fn violation(&self, result: &PlanResult) -> Option<Constraint> {
+        let metres_per_bar = result.metres_per_bar();
+        for segment in result.segments() {
+            let (start, end) = (segment.segment().start_depth(), segment.segment().end_depth());
+            let bottom = match segment.segment().segment_type() {
+                SegmentType::DiveSegment => true,
+                SegmentType::AscDesc => end > start,
+                _ => false,
+            };
+            if bottom && segment.gas().pp_o2(end, metres_per_bar) > self.max_pp_o2 {
+                return Some(Constraint::PpO2);
+            }
+        }
+        if let Some(max_tts) = self.max_tts {
+            if result.tts() > max_tts {
+                return Some(Constraint::Tts);
+            }
+        }
+        for idx in 0..result.tanks().len() {
+            let pressure = result.tank_pressure(idx).unwrap_or(0);
+            if pressure < self.tank_reserves[idx] as isize {
+                return Some(Constraint::TankReserve(idx));
+            }
+        }
+        if result.cns() > self.max_cns {
+            return Some(Constraint::Cns);
+        }
+        if result.otu() > self.max_otu {
+            return Some(Constraint::Otu);
+        }
+        None
+    }
+}
+
+impl Default for PlanLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The limits that can stop a dive from being longer or deeper.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// The time to surface would exceed its limit.
+    Tts,
+    /// A tank, by index, would be left below its reserve.
+    TankReserve(usize),
+    /// CNS oxygen toxicity would exceed its limit.
+    Cns,
+    /// Oxygen Toxicity Units would exceed their limit.
+    Otu,
+    /// A level would be deeper than the maximum operating depth of its gas.
+    PpO2,
+    /// The dive would need more segments than a plan can hold.
+    Capacity,
+    /// No limit was reached within [`SOLVER_TIME_LIMIT`] or [`SOLVER_DEPTH_LIMIT`].
+    SearchLimit,
+}
+
+/// The largest bottom time or depth found by the solver, and what stopped it going further.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// Largest value within every limit: minutes for a bottom time, metres for a depth. Zero if
+    /// even the smallest value breaks a limit.
+    value: usize,
+    /// Limit broken by the next value up.
+    binding: Constraint,
+}
+
+impl Solution {
+    /// Returns the largest value within every limit: minutes for a bottom time, metres for a depth.
+    /// Zero if even the smallest value breaks a limit.
+    pub fn value(&self) -> usize {
+        self.value
+    }
+
+    /// Returns the limit broken by the next value up.
+    pub fn binding(&self) -> Constraint {
+        self.binding
+    }
+}
+
+/// Find the longest bottom time, in whole minutes, at the depth of a level. The plan supplies the
+/// deco gases, tanks and settings; its own levels are replaced by the level.
+/// # Arguments
+/// * `plan` - Plan to take everything but the levels from
+/// * `level` - Level giving the depth and gas of the dive. Its time is ignored
+/// * `limits` - Limits the dive must stay within
+/// # Errors
+/// This function will return a [`DivePlanError`] if the level is at the surface.
+pub fn max_bottom_time(
+    plan: &DivePlan,
+    level: &Level,
+    limits: &PlanLimits,
+) -> Result<Solution, DivePlanError> {
+    let mut result = PlanResult::default();
+    solve(SOLVER_TIME_LIMIT, |minutes| {
+        let level = Level::new(
+            level.depth(),
+            Duration::from_secs(minutes as u64 * 60),
+            *level.gas(),
+        );
+        check(plan, level, limits, &mut result)
+    })
+}
+
+/// Find the deepest depth, in whole metres, a level can be at for its bottom time. The plan
+/// supplies the deco gases, tanks and settings; its own levels are replaced by the level. The ppO2
+/// limit keeps the search within the maximum operating depth of the gas.
+/// # Arguments
+/// * `plan` - Plan to take everything but the levels from
+/// * `level` - Level giving the bottom time and gas of the dive. Its depth is ignored
+/// * `limits` - Limits the dive must stay within
+/// # Errors
+/// This function will return a [`DivePlanError`] if the plan cannot be calculated.
+pub fn max_depth(
+    plan: &DivePlan,
+    level: &Level,
+    limits: &PlanLimits,
+) -> Result<Solution, DivePlanError> {
+    let mut result = PlanResult::default();
+    solve(SOLVER_DEPTH_LIMIT, |depth| {
+        let level = Level::new(depth, *level.time(), *level.gas());
+        check(plan, level, limits, &mut result)
+    })
+}
+
+/// Calculate a plan with a single level into `result`, and return the first limit it breaks. Each
+/// search reuses one result, to keep the stack small.
+fn check(
+    plan: &DivePlan,
+    level: Level,
+    limits: &PlanLimits,
+    result: &mut PlanResult,
+) -> Result<Option<Constraint>, DivePlanError> {
+    let mut plan = *plan;
+    plan.clear_levels();
+    plan.add_level(level)?;
+    match plan.plan_into(result) {
+        Ok(()) => Ok(limits.violation(result)),
+        Err(DivePlanError::CapacityError) => Ok(Some(Constraint::Capacity)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Binary search for the largest value from 1 to `limit` that breaks no limit, assuming larger
+/// values never break fewer limits.
+fn solve<F>(limit: usize, mut violation: F) -> Result<Solution, DivePlanError>
+where
+    F: FnMut(usize) -> Result<Option<Constraint>, DivePlanError>,
+{
+    let mut binding = Constraint::SearchLimit;
+    // `low` is known to be within the limits (or zero), `high` to break one (or past the limit).
+    let (mut low, mut high) = (0, limit + 1);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        match violation(mid)? {
+            None => low = mid,
+            Some(constraint) => {
+                high = mid;
+                binding = constraint;
+            }
+        }
+    }
+    Ok(Solution {
+        value: low,
+        binding: if high > limit {
+            Constraint::SearchLimit
+        } else {
+            binding
+        },
+    })
+}
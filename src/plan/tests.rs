@@ -0,0 +1,1222 @@
+use crate::common::cns::cns_with_metres_per_bar;
+use crate::common::dive_segment::{DiveSegment, SegmentType};
+use crate::common::float::Float;
+use crate::common::gas::Gas;
+use crate::common::otu::otu_with_metres_per_bar;
+use crate::common::tank::Tank;
+use crate::common::time_taken;
+use crate::common::water::WaterType;
+use crate::deco::settings::StopSettings;
+use crate::deco::tissue::Tissue;
+use crate::deco::zhl16::variant::Variant;
+use crate::deco::zhl16::ZHL16;
+use crate::gas;
+use crate::plan::contingency::SCHEDULE_STOP_CAPACITY;
+use crate::plan::gas_selection::CANDIDATE_CAPACITY;
+use crate::plan::lint::FINDING_CAPACITY;
+use crate::plan::ndl::{MAX_ALTITUDE, NDL_DEPTHS, NDL_TABLE_CAPACITY};
+use crate::plan::solver::{max_bottom_time, max_depth, SOLVER_TIME_LIMIT};
+use crate::plan::{
+    lint, select_deco_gases, AirBreaks, Constraint, ContingencyTable, DeepStops, DiveDay, DivePlan,
+    DivePlanError, FindingKind, GasConstraints, Level, LintSettings, NdlTable, NdlTableError,
+    Objective, PlanLimits, PlanResult, Severity, DECO_GAS_CAPACITY,
+};
+use core::time::Duration;
+
+/// Returns a plan with a single level, ascending at 9 m min^-1 and descending at 18 m min^-1.
+fn single_level(depth: usize, minutes: u64, gas: Gas) -> DivePlan {
+    let mut plan = DivePlan::new();
+    plan.set_rates(-9, 18).unwrap();
+    plan.add_level(Level::new(depth, Duration::from_secs(minutes * 60), gas))
+        .unwrap();
+    plan
+}
+
+/// Assert that two dives have the same segments and totals.
+fn assert_same_dive(a: &PlanResult, b: &PlanResult) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.segments().zip(b.segments()) {
+        assert_eq!(x.segment().start_depth(), y.segment().start_depth());
+        assert_eq!(x.segment().end_depth(), y.segment().end_depth());
+        assert_eq!(x.runtime(), y.runtime());
+        assert!(x.gas() == y.gas());
+        assert_eq!(x.gas_consumed(), y.gas_consumed());
+    }
+    assert_eq!(a.tts(), b.tts());
+    assert_eq!(a.otu(), b.otu());
+    assert_eq!(a.cns(), b.cns());
+    assert_eq!(a.tanks().len(), b.tanks().len());
+    for idx in 0..a.tanks().len() {
+        assert_eq!(a.tank_consumed(idx), b.tank_consumed(idx));
+    }
+}
+
+#[test]
+fn no_stop_dive_matches_hand_calculated_runtime_and_gas() {
+    let air = gas!(21, 0);
+    let mut plan = single_level(18, 30, air);
+    plan.set_water(WaterType::Fresh);
+    plan.add_tank(Tank::new(air, 12, 232)).unwrap();
+    let result = plan.plan().unwrap();
+
+    // 18 m takes 1 minute down and 2 minutes up, with no stops.
+    let expected = [(0, 18, 60), (18, 18, 30 * 60), (18, 0, 120)];
+    assert_eq!(result.len(), expected.len());
+    for (segment, &(start, end, seconds)) in result.segments().zip(expected.iter()) {
+        assert_eq!(segment.segment().start_depth(), start);
+        assert_eq!(segment.segment().end_depth(), end);
+        assert_eq!(segment.segment().time().as_secs(), seconds);
+    }
+    assert_eq!(result.stops().count(), 0);
+    assert_eq!(result.runtime(), Duration::from_secs(33 * 60));
+    assert_eq!(result.tts(), Duration::from_secs(120));
+
+    // Fresh water takes 10.228 m per bar, so the bottom is at 2.760 bar and the mean depth of
+    // each depth change at 1.880 bar: 37.6 L down, 1655.9 L at the bottom and 75.2 L up.
+    let consumed: Vec<usize> = result.segments().map(|t| t.gas_consumed()).collect();
+    assert_eq!(consumed, [37, 1655, 75]);
+    assert_eq!(result.gas_consumed(&air), 1767);
+    assert_eq!(result.tank_consumed(0), Some(1767));
+    // 1767 L from a 12 L tank is 147.25 bar, which is rounded up so the pressure left is never
+    // overstated.
+    assert_eq!(result.tank_pressure(0), Some(232 - 148));
+}
+
+#[test]
+fn toxicity_uses_the_density_of_the_water() {
+    let nitrox = gas!(50, 0);
+    let salt = single_level(21, 40, nitrox).plan().unwrap();
+    let mut fresh = single_level(21, 40, nitrox);
+    fresh.set_water(WaterType::Fresh);
+    let fresh = fresh.plan().unwrap();
+
+    for result in [&salt, &fresh].iter() {
+        let metres_per_bar = result.metres_per_bar();
+        let (otu, cns) = result.segments().fold((0.0, 0.0), |(otu, cns), t| {
+            (
+                otu + otu_with_metres_per_bar(t.segment(), t.gas(), metres_per_bar),
+                cns + cns_with_metres_per_bar(t.segment(), t.gas(), metres_per_bar),
+            )
+        });
+        assert!((result.otu() - otu).abs() < 1e-3);
+        assert!((result.cns() - cns).abs() < 1e-3);
+    }
+    // Denser water is deeper in bar at the same depth, so it is more toxic.
+    assert!(salt.otu() > fresh.otu());
+    assert!(salt.cns() > fresh.cns());
+    // Salt water takes 9.962 m per bar, so EAN50 at 21 m is 1.554 bar, which allows 79.5 minutes
+    // by the NOAA table. 40 minutes at the bottom alone is 50.3% of the limit.
+    let bottom = salt.segment(1).unwrap();
+    let cns: Float = cns_with_metres_per_bar(bottom.segment(), &nitrox, salt.metres_per_bar());
+    assert!((cns - 50.3).abs() < 0.1, "{}", cns);
+}
+
+#[test]
+fn plan_into_reuses_a_result() {
+    let air = gas!(21, 0);
+    let mut deco = single_level(45, 30, air);
+    deco.add_deco_gas(gas!(50, 0)).unwrap();
+    deco.add_tank(Tank::new(air, 24, 232)).unwrap();
+    let short = single_level(18, 30, air);
+
+    let mut result = PlanResult::default();
+    deco.plan_into(&mut result).unwrap();
+    assert_same_dive(&result, &deco.plan().unwrap());
+    assert!(result.stops().count() > 0);
+
+    // A shorter dive with fewer tanks leaves nothing of the longer one behind.
+    short.plan_into(&mut result).unwrap();
+    assert_same_dive(&result, &short.plan().unwrap());
+    assert_eq!(result.stops().count(), 0);
+    assert!(result.tanks().is_empty());
+
+    assert_eq!(
+        DivePlan::new().plan_into(&mut result),
+        Err(DivePlanError::NoLevelError)
+    );
+    assert!(PlanResult::default().is_empty());
+    assert!(result
+        .stops()
+        .all(|t| t.segment().segment_type() == SegmentType::DecoStop));
+}
+
+/// Returns a plan of a multi-level dive on air: 30 m for 20 minutes, then 18 m for 20 minutes.
+fn multi_level() -> DivePlan {
+    let air = gas!(21, 0);
+    let mut plan = single_level(30, 20, air);
+    plan.add_level(Level::new(18, Duration::from_secs(20 * 60), air))
+        .unwrap();
+    plan
+}
+
+#[test]
+fn day_carries_tissue_and_toxicity_between_dives() {
+    let air = gas!(21, 0);
+    let plan = multi_level();
+    let mut day = DiveDay::new(*plan.deco());
+    let fresh = day.ndl_table(&air);
+
+    let first = day.dive(&plan).unwrap();
+    assert_eq!(day.dives(), 1);
+    assert_eq!(day.deco().tissue().p_n2(), first.deco().tissue().p_n2());
+    day.surface(Duration::from_secs(60 * 60));
+
+    // Residual nitrogen in the slow compartments shortens the shallow NDLs of the next dive. The
+    // fast compartments that control the deep ones have cleared within the hour.
+    let residual = day.ndl_table(&air);
+    assert_eq!(residual.entries().len(), fresh.entries().len());
+    for (before, after) in fresh.entries().iter().zip(residual.entries()) {
+        assert_eq!(before.depth(), after.depth());
+        assert!(after.ndl() <= before.ndl(), "{}m", after.depth());
+    }
+    assert!(residual.entries()[0].ndl() < fresh.entries()[0].ndl());
+
+    // The same plan then needs a longer ascent.
+    let second = day.dive(&plan).unwrap();
+    assert_eq!(day.dives(), 2);
+    assert!(second.tts() > first.tts());
+    assert!((day.otu() - first.otu() - second.otu()).abs() < 1e-3);
+}
+
+#[test]
+fn day_cns_halves_every_90_minutes_at_the_surface() {
+    let nitrox = gas!(50, 0);
+    let plan = single_level(21, 40, nitrox);
+    let mut day = DiveDay::new(*plan.deco());
+    let result = day.dive(&plan).unwrap();
+    assert!((day.cns() - result.cns()).abs() < 1e-3);
+
+    day.surface(Duration::from_secs(90 * 60));
+    assert!((day.cns() - result.cns() / 2.0).abs() < 1e-3);
+    day.surface(Duration::from_secs(180 * 60));
+    assert!((day.cns() - result.cns() / 8.0).abs() < 1e-3);
+    // OTUs are counted for the whole day.
+    assert!((day.otu() - result.otu()).abs() < 1e-3);
+}
+
+#[test]
+fn day_is_unchanged_by_a_dive_that_cannot_be_planned() {
+    let plan = multi_level();
+    let mut day = DiveDay::new(*plan.deco());
+    day.dive(&plan).unwrap();
+    let tissue = day.deco().tissue();
+    let (otu, cns) = (day.otu(), day.cns());
+
+    assert_eq!(
+        day.dive(&DivePlan::new()).err(),
+        Some(DivePlanError::NoLevelError)
+    );
+    assert_eq!(day.dives(), 1);
+    assert_eq!(day.deco().tissue().p_n2(), tissue.p_n2());
+    assert_eq!((day.otu(), day.cns()), (otu, cns));
+}
+
+#[test]
+fn moving_up_to_a_level_above_the_ceiling_makes_the_stops_first() {
+    let air = gas!(21, 0);
+    let mut plan = single_level(50, 25, air);
+    plan.add_level(Level::new(9, Duration::from_secs(10 * 60), air))
+        .unwrap();
+    let result = plan.plan().unwrap();
+
+    // Stops deeper than the second level are made before reaching it.
+    let mut segments = result.segments();
+    let between: Vec<_> = segments
+        .by_ref()
+        .take_while(|t| {
+            t.segment().segment_type() != SegmentType::DiveSegment || t.segment().end_depth() != 9
+        })
+        .filter(|t| t.segment().segment_type() == SegmentType::DecoStop)
+        .map(|t| t.segment().end_depth())
+        .collect();
+    assert!(!between.is_empty());
+    assert!(between.iter().all(|&t| t > 9));
+
+    // The ceiling is never broken on the way.
+    let metres_per_bar = result.metres_per_bar();
+    let mut deco = *plan.deco();
+    for segment in result.segments() {
+        let segment = segment.segment();
+        if segment.end_depth() < segment.start_depth() {
+            assert!(segment.end_depth() >= deco.stop_depth(metres_per_bar));
+        }
+        deco.add_segment(segment, &air, metres_per_bar);
+    }
+}
+
+/// Returns a model at the surface with no residual loading, at GF 100/100.
+fn surface_deco() -> ZHL16 {
+    ZHL16::new_by_variant(Tissue::default(), 100, 100, Variant::C)
+}
+
+/// Returns the NDLs of a table in whole minutes.
+fn ndl_minutes(table: &NdlTable) -> Vec<u64> {
+    table
+        .entries()
+        .iter()
+        .map(|t| t.ndl().as_secs() / 60)
+        .collect()
+}
+
+#[test]
+fn ndl_table_matches_published_buhlmann_ranges() {
+    let air = gas!(21, 0);
+    let table = NdlTable::new(&surface_deco(), &air, 18, 10.0);
+    let entries = table.entries();
+    assert_eq!(entries.len(), NDL_DEPTHS.len());
+
+    // ZHL-16C at GF 100/100 against the range of air NDLs published for Bühlmann tables and
+    // computers at these depths.
+    for &(depth, low, high) in [(12, 120, 200), (18, 50, 65), (30, 13, 20), (39, 7, 12)].iter() {
+        let entry = entries.iter().find(|t| t.depth() == depth).unwrap();
+        let ndl = entry.ndl().as_secs() / 60;
+        assert!(ndl >= low && ndl <= high, "{}m: {}", depth, ndl);
+        assert!(entry.gas() == &air);
+    }
+
+    // Each entry is the NDL of the model once it has descended, and deeper is always shorter.
+    for pair in entries.windows(2) {
+        assert!(pair[1].ndl() < pair[0].ndl());
+    }
+    let mut deco = surface_deco();
+    let descent =
+        DiveSegment::new(SegmentType::AscDesc, 0, 30, time_taken(18, 0, 30), -18, 18).unwrap();
+    deco.add_segment(&descent, &air, 10.0);
+    let entry = entries.iter().find(|t| t.depth() == 30).unwrap();
+    assert_eq!(entry.ndl(), *deco.ndl(&air, 10.0).unwrap().time());
+}
+
+#[test]
+fn ndl_table_accounts_for_residual_loading_and_altitude() {
+    let air = gas!(21, 0);
+    let ean32 = gas!(32, 0);
+    let depths = [15, 21, 30];
+    let mut dived = surface_deco();
+    let bottom = DiveSegment::new(
+        SegmentType::DiveSegment,
+        25,
+        25,
+        Duration::from_secs(40 * 60),
+        -9,
+        18,
+    )
+    .unwrap();
+    dived.add_segment(&bottom, &air, 10.0);
+    let surface =
+        DiveSegment::new(SegmentType::AscDesc, 25, 0, time_taken(-9, 25, 0), -9, 18).unwrap();
+    dived.add_segment(&surface, &air, 10.0);
+
+    let table = |deco: &ZHL16, hours: u64, altitude: usize| {
+        let interval = Duration::from_secs(hours * 3600);
+        NdlTable::for_depths(deco, &depths, &[air, ean32], interval, altitude, 18, 10.0).unwrap()
+    };
+    let fresh = table(&surface_deco(), 0, 0);
+    let repetitive = table(&dived, 1, 0);
+    let rested = table(&dived, 48, 0);
+    let altitude = table(&surface_deco(), 0, 2000);
+
+    // Entries are ordered by gas, then by depth, and nitrox extends every NDL.
+    let entries = fresh.entries();
+    assert_eq!(entries.len(), 6);
+    for (idx, entry) in entries.iter().enumerate() {
+        assert_eq!(entry.depth(), depths[idx % 3]);
+        assert!(entry.gas() == if idx < 3 { &air } else { &ean32 });
+    }
+    for idx in 0..3 {
+        assert!(entries[idx + 3].ndl() > entries[idx].ndl());
+    }
+
+    // An hour after the dive the slow compartments that control shallow NDLs still hold
+    // nitrogen, while the fast ones controlling deep NDLs have cleared. After two days the
+    // tissues are back at the surface.
+    for (a, b) in repetitive.entries().iter().zip(entries) {
+        assert!(a.ndl() <= b.ndl(), "{}m", a.depth());
+    }
+    assert!(repetitive.entries()[0].ndl() < entries[0].ndl());
+    assert_eq!(ndl_minutes(&rested), ndl_minutes(&fresh));
+
+    // At 2000 m the surface pressure is about 0.8 bar, so the same depth allows less time.
+    for (a, b) in altitude.entries().iter().zip(entries) {
+        assert_eq!(a.depth(), b.depth());
+        assert!(a.ndl() < b.ndl(), "{}m", a.depth());
+    }
+}
+
+#[test]
+fn ndl_table_is_validated() {
+    let deco = surface_deco();
+    let air = [gas!(21, 0)];
+    let none = Duration::from_secs(0);
+    let too_many = [21; NDL_TABLE_CAPACITY + 1];
+    for &(depths, gases) in [
+        (&[][..], &air[..]),
+        (&[21][..], &[][..]),
+        (&too_many[..], &air[..]),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            NdlTable::for_depths(&deco, depths, gases, none, 0, 18, 10.0).err(),
+            Some(NdlTableError::CapacityError)
+        );
+    }
+    assert!(NdlTable::for_depths(&deco, &[21], &air, none, MAX_ALTITUDE, 18, 10.0).is_ok());
+    assert_eq!(
+        NdlTable::for_depths(&deco, &[21], &air, none, MAX_ALTITUDE + 1, 18, 10.0).err(),
+        Some(NdlTableError::AltitudeError)
+    );
+}
+
+/// Returns the first limit broken by a plan with a single level, if any.
+fn violation(plan: &DivePlan, level: Level, limits: &PlanLimits) -> Option<Constraint> {
+    let mut plan = *plan;
+    plan.clear_levels();
+    plan.add_level(level).unwrap();
+    limits.violation(&plan.plan().unwrap())
+}
+
+#[test]
+fn max_bottom_time_with_a_tank_reserve_matches_hand_calculated_gas() {
+    let air = gas!(21, 0);
+    let mut plan = single_level(18, 0, air);
+    plan.set_water(WaterType::Fresh);
+    plan.add_tank(Tank::new(air, 12, 200)).unwrap();
+    let mut limits = PlanLimits::new();
+    limits.set_tank_reserve(0, 50).unwrap();
+
+    // 150 bar of a 12 L tank is 1800 L. The descent and ascent take 112.8 L, leaving 1687.2 L for
+    // the bottom at 2.760 bar and 20 L min^-1: 30.6 minutes.
+    let level = Level::new(18, Duration::from_secs(0), air);
+    let solution = max_bottom_time(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.value(), 30);
+    assert_eq!(solution.binding(), Constraint::TankReserve(0));
+
+    // A reserve above the fill pressure allows no bottom time at all.
+    limits.set_tank_reserve(0, 201).unwrap();
+    let solution = max_bottom_time(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.value(), 0);
+    assert_eq!(solution.binding(), Constraint::TankReserve(0));
+}
+
+#[test]
+fn max_bottom_time_on_oxygen_matches_hand_calculated_toxicity() {
+    // Oxygen at 6 m in fresh water is 1.587 bar, which NOAA allows for 55.1 minutes (1.82% a
+    // minute) and which gives 1.91 OTU a minute. The descent and ascent add 0.55% and 1.47 OTU.
+    let o2 = gas!(100, 0);
+    let mut plan = single_level(6, 0, o2);
+    plan.set_water(WaterType::Fresh);
+    let level = Level::new(6, Duration::from_secs(0), o2);
+    // The default working limit of 1.4 bar would not allow oxygen at 6 m at all.
+    let mut limits = PlanLimits::new();
+    assert_eq!(
+        max_bottom_time(&plan, &level, &limits).unwrap().binding(),
+        Constraint::PpO2
+    );
+    limits.set_max_pp_o2(1.6);
+
+    let solution = max_bottom_time(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.value(), 54);
+    assert_eq!(solution.binding(), Constraint::Cns);
+
+    limits.set_max_cns(1000.0);
+    limits.set_max_otu(50.0);
+    let solution = max_bottom_time(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.value(), 25);
+    assert_eq!(solution.binding(), Constraint::Otu);
+}
+
+#[test]
+fn max_bottom_time_within_a_direct_ascent_is_the_ndl() {
+    let air = gas!(21, 0);
+    let mut plan = single_level(30, 0, air);
+    plan.set_gradient_factors(100, 100).unwrap();
+    let mut limits = PlanLimits::new();
+    // 30 m at 9 m min^-1 takes 200 seconds, so any stop breaks the limit.
+    limits.set_max_tts(Some(Duration::from_secs(200)));
+
+    let level = Level::new(30, Duration::from_secs(0), air);
+    let solution = max_bottom_time(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.binding(), Constraint::Tts);
+    // The published range of air NDLs at 30 m, less the 100 second descent.
+    assert!(solution.value() >= 12 && solution.value() <= 19);
+
+    // Every shorter bottom time is within the limit, and the next minute is not.
+    for minutes in 1..=solution.value() as u64 {
+        let level = Level::new(30, Duration::from_secs(minutes * 60), air);
+        assert_eq!(violation(&plan, level, &limits), None, "{} min", minutes);
+    }
+    let over = Level::new(
+        30,
+        Duration::from_secs((solution.value() as u64 + 1) * 60),
+        air,
+    );
+    assert_eq!(violation(&plan, over, &limits), Some(Constraint::Tts));
+}
+
+#[test]
+fn max_depth_is_the_deepest_depth_within_the_limits() {
+    let air = gas!(21, 0);
+    let plan = single_level(20, 0, air);
+    let mut limits = PlanLimits::new();
+    limits.set_max_tts(Some(Duration::from_secs(10 * 60)));
+
+    let level = Level::new(0, Duration::from_secs(20 * 60), air);
+    let solution = max_depth(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.binding(), Constraint::Tts);
+    assert!(solution.value() > 20 && solution.value() < 60);
+    for depth in 1..=solution.value() {
+        let level = Level::new(depth, *level.time(), air);
+        assert_eq!(violation(&plan, level, &limits), None, "{}m", depth);
+    }
+    let over = Level::new(solution.value() + 1, *level.time(), air);
+    assert_eq!(violation(&plan, over, &limits), Some(Constraint::Tts));
+}
+
+#[test]
+fn max_depth_stops_at_the_maximum_operating_depth_of_the_gas() {
+    let nitrox = gas!(32, 0);
+    let plan = single_level(20, 0, nitrox);
+    let mut limits = PlanLimits::new();
+
+    // EAN32 reaches 1.4 bar at 4.375 bar, which is 33.6 m of salt water at 9.962 m per bar.
+    let level = Level::new(0, Duration::from_secs(20 * 60), nitrox);
+    let solution = max_depth(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.value(), 33);
+    assert_eq!(solution.binding(), Constraint::PpO2);
+
+    // A higher working limit of 1.6 bar allows 5 bar, which is 39.8 m.
+    limits.set_max_pp_o2(1.6);
+    let solution = max_depth(&plan, &level, &limits).unwrap();
+    assert_eq!(solution.value(), 39);
+    assert_eq!(solution.binding(), Constraint::PpO2);
+
+    // No time at all is allowed past the limit.
+    let deep = Level::new(45, Duration::from_secs(0), nitrox);
+    let solution = max_bottom_time(&plan, &deep, &limits).unwrap();
+    assert_eq!(solution.value(), 0);
+    assert_eq!(solution.binding(), Constraint::PpO2);
+}
+
+#[test]
+fn solver_reports_when_no_limit_is_reached() {
+    let air = gas!(21, 0);
+    let plan = single_level(3, 0, air);
+    let level = Level::new(3, Duration::from_secs(0), air);
+    let solution = max_bottom_time(&plan, &level, &PlanLimits::new()).unwrap();
+    assert_eq!(solution.value(), SOLVER_TIME_LIMIT);
+    assert_eq!(solution.binding(), Constraint::SearchLimit);
+
+    let surface = Level::new(0, Duration::from_secs(0), air);
+    assert_eq!(
+        max_bottom_time(&plan, &surface, &PlanLimits::new()).err(),
+        Some(DivePlanError::DepthError)
+    );
+}
+
+#[test]
+fn contingency_table_matches_hand_calculated_schedule_and_deviations() {
+    let air = gas!(21, 0);
+    let mut plan = single_level(18, 30, air);
+    plan.set_water(WaterType::Fresh);
+    plan.add_tank(Tank::new(air, 12, 232)).unwrap();
+    let table = ContingencyTable::new(&plan, &[0, 3, 6], &[0, 5]).unwrap();
+    assert_eq!(table.depth_offsets(), [0, 3, 6]);
+    assert_eq!(table.time_offsets(), [0, 5]);
+    assert_eq!(table.schedules().len(), 6);
+    assert!(table.schedule(3, 0).is_none() && table.schedule(0, 2).is_none());
+
+    // The plan itself: 1 minute down, 30 at the bottom and 2 up, drawing 1767 L.
+    let schedule = table.schedule(0, 0).unwrap();
+    assert_eq!(schedule.depth(), 18);
+    assert_eq!(schedule.bottom_time(), 30);
+    assert_eq!((schedule.runtime(), schedule.tts()), (33, 2));
+    assert!(schedule.stops().is_empty());
+    assert_eq!(schedule.tank_consumed()[0], 1767);
+
+    // 21 m for 35 minutes needs a minute at 6 m and a minute at 3 m: 70 seconds down, then 100
+    // to 6 m and 20 between the stops and to the surface make a runtime of 40.5 minutes and a TTS
+    // of 4.3, rounded up.
+    let schedule = table.schedule(1, 1).unwrap();
+    assert_eq!(schedule.depth(), 21);
+    assert_eq!(schedule.bottom_time(), 35);
+    assert_eq!((schedule.runtime(), schedule.tts()), (41, 5));
+    let stops: Vec<(u16, u16)> = schedule
+        .stops()
+        .iter()
+        .map(|t| (t.depth(), t.time()))
+        .collect();
+    assert_eq!(stops, [(6, 1), (3, 1)]);
+
+    // Every deviation is the plan with its level moved, with times rounded up to whole minutes.
+    for (row, &depth) in [18, 21, 24].iter().enumerate() {
+        for (column, &minutes) in [30, 35].iter().enumerate() {
+            let schedule = table.schedule(row, column).unwrap();
+            let mut deviation = single_level(depth, minutes, air);
+            deviation.set_water(WaterType::Fresh);
+            deviation.add_tank(Tank::new(air, 12, 232)).unwrap();
+            let result = deviation.plan().unwrap();
+            let round = |t: Duration| ((t + Duration::from_secs(59)).as_secs() / 60) as u16;
+            assert_eq!(schedule.runtime(), round(result.runtime()));
+            assert_eq!(schedule.tts(), round(result.tts()));
+            assert_eq!(schedule.stops().len(), result.stops().count());
+            for (stop, t) in schedule.stops().iter().zip(result.stops()) {
+                assert_eq!(stop.depth() as usize, t.segment().start_depth());
+                assert_eq!(stop.time(), round(*t.segment().time()));
+                assert_eq!((stop.o2(), stop.he()), (21, 0));
+            }
+            assert_eq!(
+                schedule.tank_consumed()[0] as usize,
+                result.tank_consumed(0).unwrap()
+            );
+        }
+    }
+    // Going deeper or staying longer never shortens the ascent.
+    for row in 0..3 {
+        for column in 0..2 {
+            let schedule = table.schedule(row, column).unwrap();
+            if row > 0 {
+                assert!(schedule.tts() >= table.schedule(row - 1, column).unwrap().tts());
+            }
+            if column > 0 {
+                assert!(schedule.tts() >= table.schedule(row, column - 1).unwrap().tts());
+            }
+        }
+    }
+    assert!(table.schedule(2, 1).unwrap().tts() > table.schedule(0, 0).unwrap().tts());
+}
+
+#[test]
+fn contingency_table_is_validated() {
+    let air = gas!(21, 0);
+    let plan = single_level(18, 30, air);
+    let too_many = [0; 5];
+    for &(depths, times) in [
+        (&[][..], &[0][..]),
+        (&[0][..], &[][..]),
+        (&too_many[..], &[0][..]),
+        (&[0][..], &too_many[..]),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            ContingencyTable::new(&plan, depths, times).err(),
+            Some(DivePlanError::CapacityError)
+        );
+    }
+    assert_eq!(
+        ContingencyTable::new(&DivePlan::new(), &[0], &[0]).err(),
+        Some(DivePlanError::NoLevelError)
+    );
+
+    // Stops every metre and a low GF low give a deep dive more stops than a schedule holds, which is an error
+    // rather than a schedule with its shallowest stops missing.
+    let mut deco = *plan.deco();
+    deco.set_stop_settings(
+        StopSettings::new(1, 3, Duration::from_secs(60), Duration::from_secs(60)).unwrap(),
+    );
+    let mut deep = single_level(60, 30, air);
+    deep.set_deco(deco);
+    deep.set_gradient_factors(10, 90).unwrap();
+    assert!(deep.plan().unwrap().stops().count() > SCHEDULE_STOP_CAPACITY);
+    assert_eq!(
+        ContingencyTable::new(&deep, &[0], &[0]).err(),
+        Some(DivePlanError::CapacityError)
+    );
+
+    // A level longer than 65535 minutes does not fit a schedule, rather than wrapping around.
+    let long = single_level(3, 70_000, air);
+    assert!(long.plan().is_ok());
+    assert_eq!(
+        ContingencyTable::new(&long, &[0], &[0]).err(),
+        Some(DivePlanError::CapacityError)
+    );
+}
+
+#[test]
+fn contingency_schedule_labels_each_stop_with_its_gas() {
+    let mut plan = oxygen_deco_dive();
+    let breaks = AirBreaks::new(
+        Duration::from_secs(20 * 60),
+        Duration::from_secs(5 * 60),
+        1.5,
+    )
+    .unwrap();
+    plan.set_air_breaks(Some(breaks));
+    let table = ContingencyTable::new(&plan, &[0], &[0]).unwrap();
+    let schedule = table.schedule(0, 0).unwrap();
+
+    // Air at 24 m, where EAN50 would be 1.67 bar, then EAN50 from 21 m (1.53 bar) and oxygen at
+    // 6 m (1.59 bar), split by a 5 minute break on air.
+    let stops: Vec<(u16, u16, u8)> = schedule
+        .stops()
+        .iter()
+        .map(|t| (t.depth(), t.time(), t.o2()))
+        .collect();
+    assert_eq!(
+        stops,
+        [
+            (24, 1, 21),
+            (21, 2, 50),
+            (18, 3, 50),
+            (15, 4, 50),
+            (12, 6, 50),
+            (9, 11, 50),
+            (6, 20, 100),
+            (6, 5, 21),
+            (6, 7, 100)
+        ]
+    );
+    assert!(schedule.stops().iter().all(|t| t.he() == 0));
+}
+
+/// Returns a trimix dive to 60 m that needs deco, with no deco gases.
+fn trimix_dive() -> DivePlan {
+    single_level(60, 20, gas!(18, 45))
+}
+
+#[test]
+fn gas_selection_follows_the_rule_of_fifths() {
+    let plan = trimix_dive();
+    let candidates = [gas!(50, 0), gas!(35, 25), gas!(100, 0)];
+
+    // Without the rule, EAN50 and oxygen give the shortest ascent.
+    let free = GasConstraints::new(0.16, 6.2, false);
+    let selection = select_deco_gases(&plan, &candidates, 2, Objective::Tts, &free).unwrap();
+    assert!(selection.gases() == [gas!(50, 0), gas!(100, 0)]);
+    let bottom_only = plan.plan().unwrap();
+    assert!(selection.tts() < bottom_only.tts());
+
+    // Switching from 18/45 to EAN50 raises nitrogen by 13 for a helium drop of 45, so 35/25 is
+    // used instead.
+    let icd = GasConstraints::default();
+    let selection = select_deco_gases(&plan, &candidates, 2, Objective::Tts, &icd).unwrap();
+    assert!(selection.gases() == [gas!(35, 25), gas!(100, 0)]);
+
+    // The selection is the plan with those deco gases.
+    let mut chosen = plan;
+    for gas in selection.gases() {
+        chosen.add_deco_gas(*gas).unwrap();
+    }
+    let result = chosen.plan().unwrap();
+    assert_eq!(selection.tts(), result.tts());
+    let consumed: usize = result.segments().map(|t| t.gas_consumed()).sum();
+    assert_eq!(selection.gas_consumed(), consumed);
+}
+
+#[test]
+fn gas_selection_respects_density_and_gas_count() {
+    let plan = trimix_dive();
+    let candidates = [gas!(50, 0), gas!(100, 0)];
+    let free = GasConstraints::new(0.16, 6.2, false);
+
+    // Air at the surface is 1.2 g L^-1, so no deco gas can be switched to.
+    let thin = GasConstraints::new(0.16, 1.0, false);
+    let selection = select_deco_gases(&plan, &candidates, 2, Objective::Tts, &thin).unwrap();
+    assert!(selection.gases().is_empty());
+    assert_eq!(selection.tts(), plan.plan().unwrap().tts());
+
+    let one = select_deco_gases(&plan, &candidates, 1, Objective::Tts, &free).unwrap();
+    let two = select_deco_gases(&plan, &candidates, 2, Objective::Tts, &free).unwrap();
+    assert_eq!(one.gases().len(), 1);
+    assert!(two.tts() < one.tts());
+    let gas = select_deco_gases(&plan, &candidates, 2, Objective::GasConsumed, &free).unwrap();
+    assert!(gas.gas_consumed() <= two.gas_consumed());
+
+    let too_many = [gas!(50, 0); CANDIDATE_CAPACITY + 1];
+    assert_eq!(
+        select_deco_gases(&plan, &too_many, 1, Objective::Tts, &free).err(),
+        Some(DivePlanError::CapacityError)
+    );
+    assert_eq!(
+        select_deco_gases(
+            &plan,
+            &candidates,
+            DECO_GAS_CAPACITY + 1,
+            Objective::Tts,
+            &free
+        )
+        .err(),
+        Some(DivePlanError::CapacityError)
+    );
+    assert_eq!(
+        select_deco_gases(&DivePlan::new(), &candidates, 1, Objective::Tts, &free).err(),
+        Some(DivePlanError::NoLevelError)
+    );
+}
+
+#[test]
+fn gas_selection_only_checks_switches_onto_deco_gases() {
+    // Moving from 18/45 to air for a shallower level breaks the rule of fifths, but is part of
+    // the plan rather than a choice of deco gas.
+    let mut plan = trimix_dive();
+    plan.add_level(Level::new(21, Duration::from_secs(5 * 60), gas!(21, 0)))
+        .unwrap();
+    let candidates = [gas!(50, 0), gas!(100, 0)];
+    let selection = select_deco_gases(
+        &plan,
+        &candidates,
+        2,
+        Objective::Tts,
+        &GasConstraints::default(),
+    )
+    .unwrap();
+    assert!(selection.gases() == [gas!(50, 0), gas!(100, 0)]);
+}
+
+/// Returns a segment between two depths, or at one depth if they are the same.
+fn segment(start: usize, end: usize, seconds: u64) -> DiveSegment {
+    let segment_type = if start == end {
+        SegmentType::DiveSegment
+    } else {
+        SegmentType::AscDesc
+    };
+    DiveSegment::new(
+        segment_type,
+        start,
+        end,
+        Duration::from_secs(seconds),
+        -18,
+        30,
+    )
+    .unwrap()
+}
+
+/// Returns the findings of a lint as (segment, severity, kind) triples.
+fn findings(
+    segments: &[(DiveSegment, Gas)],
+    settings: &LintSettings,
+) -> Vec<(usize, Severity, FindingKind)> {
+    lint(segments.iter().copied(), settings)
+        .findings()
+        .iter()
+        .map(|t| (t.segment(), t.severity(), t.kind()))
+        .collect()
+}
+
+#[test]
+fn lint_checks_pp_o2_at_the_density_of_the_water() {
+    let ean32 = gas!(32, 0);
+    let dive = [(segment(0, 34, 68), ean32), (segment(34, 34, 60), ean32)];
+
+    // At 10 m per bar, EAN32 at 34 m is 1.408 bar, over the working limit of 1.4.
+    let lint_10 = lint(dive.iter().copied(), &LintSettings::new());
+    let high: Vec<_> = lint_10
+        .findings()
+        .iter()
+        .filter(|t| t.kind() == FindingKind::PpO2High)
+        .collect();
+    assert_eq!(high.len(), 2);
+    assert!((high[0].value() - 1.408).abs() < 1e-4);
+    assert!((high[0].limit() - 1.4).abs() < 1e-6);
+
+    // Fresh water takes 10.228 m per bar, so the same depth is only 1.384 bar.
+    let mut fresh = LintSettings::new();
+    fresh.set_deco(surface_deco(), WaterType::Fresh.metres_per_bar());
+    assert!(findings(&dive, &fresh)
+        .iter()
+        .all(|t| t.2 != FindingKind::PpO2High));
+
+    // 10/70 is hypoxic at the surface (0.10 bar) but not from 6 m (0.16 bar).
+    let hypoxic = gas!(10, 70);
+    let dive = [(segment(0, 6, 12), hypoxic), (segment(6, 6, 60), hypoxic)];
+    assert_eq!(
+        findings(&dive, &LintSettings::new()),
+        [(0, Severity::Error, FindingKind::PpO2Low)]
+    );
+}
+
+#[test]
+fn lint_checks_deep_stops_against_the_deco_limit() {
+    let air = gas!(21, 0);
+    let ean50 = gas!(50, 0);
+    let stop = |depth, segment_type| {
+        let stop = DiveSegment::new(segment_type, depth, depth, Duration::from_secs(60), -9, 18);
+        (stop.unwrap(), ean50)
+    };
+    let dive = |last: (DiveSegment, Gas)| {
+        let depth = last.0.start_depth();
+        [
+            (segment(0, 45, 90), air),
+            (segment(45, 45, 60), air),
+            (segment(45, depth, 160), air),
+            last,
+        ]
+    };
+    let high = |dive: &[(DiveSegment, Gas)]| {
+        lint(dive.iter().copied(), &LintSettings::new())
+            .findings()
+            .iter()
+            .filter(|t| t.kind() == FindingKind::PpO2High)
+            .map(|t| (t.segment(), t.limit()))
+            .collect::<Vec<_>>()
+    };
+
+    // EAN50 at 21 m is 1.55 bar: within the deco limit of 1.6 at a deep stop, but over the working
+    // limit of 1.4 at a level.
+    assert!(high(&dive(stop(21, SegmentType::DeepStop))).is_empty());
+    let level = high(&dive(stop(21, SegmentType::DiveSegment)));
+    assert_eq!(level.len(), 1);
+    assert!((level[0].1 - 1.4).abs() < 1e-6);
+
+    // At 24 m it is 1.7 bar, over the deco limit too.
+    let deep = high(&dive(stop(24, SegmentType::DeepStop)));
+    assert_eq!(deep.len(), 1);
+    assert_eq!(deep[0].0, 3);
+    assert!((deep[0].1 - 1.6).abs() < 1e-6);
+}
+
+#[test]
+fn lint_matches_hand_calculated_end_and_density() {
+    let air = gas!(21, 0);
+    let settings = LintSettings::new();
+
+    // Air at 31 m is 5.28 g L^-1, over the warning at 5.2, with an END of 31 m.
+    let lint_31 = lint([(segment(31, 31, 60), air)].iter().copied(), &settings);
+    let found: Vec<_> = lint_31
+        .findings()
+        .iter()
+        .map(|t| (t.kind(), t.severity()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (FindingKind::End, Severity::Warning),
+            (FindingKind::Density, Severity::Warning)
+        ]
+    );
+    assert!((lint_31.findings()[0].value() - 31.0).abs() < 1e-6);
+    assert!((lint_31.findings()[1].value() - 5.282).abs() < 1e-3);
+    assert_eq!(lint_31.worst(), Some(Severity::Warning));
+
+    // At 40 m it is 6.44 g L^-1, over the limit of 6.2.
+    let lint_40 = lint([(segment(40, 40, 60), air)].iter().copied(), &settings);
+    let density = lint_40
+        .findings()
+        .iter()
+        .find(|t| t.kind() == FindingKind::Density)
+        .unwrap();
+    assert_eq!(density.severity(), Severity::Error);
+    assert!((density.value() - 6.442).abs() < 1e-3);
+    assert_eq!(lint_40.worst(), Some(Severity::Error));
+
+    // 21/35 at 60 m has an END of 35.5 m, and air at 30 m is within every limit.
+    let trimix = [(segment(60, 60, 60), gas!(21, 35))];
+    assert!(findings(&trimix, &settings).contains(&(0, Severity::Warning, FindingKind::End)));
+    let end = |settings: &LintSettings| {
+        lint(trimix.iter().copied(), settings)
+            .findings()
+            .iter()
+            .find(|t| t.kind() == FindingKind::End)
+            .map(|t| t.value())
+    };
+    assert!((end(&settings).unwrap() - 35.5).abs() < 1e-3);
+    // Fresh water takes 10.228 m per bar, so the same mix has an END of 35.42 m.
+    let mut fresh = LintSettings::new();
+    fresh.set_deco(surface_deco(), WaterType::Fresh.metres_per_bar());
+    assert!((end(&fresh).unwrap() - 35.420).abs() < 1e-3);
+    assert!(
+        lint([(segment(30, 30, 60), air)].iter().copied(), &settings)
+            .findings()
+            .is_empty()
+    );
+}
+
+#[test]
+fn lint_reports_ascent_rate_toxicity_and_ceiling() {
+    let air = gas!(21, 0);
+    let settings = LintSettings::new();
+
+    // 30 m in a minute is 30 m min^-1 against the default of 18.
+    let lint_fast = lint([(segment(30, 0, 60), air)].iter().copied(), &settings);
+    let fast = lint_fast.findings()[0];
+    assert_eq!((fast.segment(), fast.kind()), (0, FindingKind::AscentRate));
+    assert!((fast.value() - 30.0).abs() < 1e-4 && (fast.limit() - 18.0).abs() < 1e-4);
+    assert!(findings(&[(segment(30, 0, 100), air)], &settings).is_empty());
+
+    // Oxygen at 6 m is 1.6 bar, which NOAA allows for 45 minutes: 60 minutes is 133%, reported
+    // once when the total first goes over.
+    let o2 = gas!(100, 0);
+    let dive = [
+        (segment(0, 6, 12), o2),
+        (segment(6, 6, 30 * 60), o2),
+        (segment(6, 6, 30 * 60), o2),
+        (segment(6, 6, 10 * 60), o2),
+    ];
+    let lint_o2 = lint(dive.iter().copied(), &settings);
+    let cns: Vec<_> = lint_o2
+        .findings()
+        .iter()
+        .filter(|t| t.kind() == FindingKind::Cns)
+        .collect();
+    assert_eq!(cns.len(), 1);
+    assert_eq!(cns[0].segment(), 2);
+    assert!((cns[0].value() - 133.5).abs() < 0.5);
+
+    // Surfacing straight from a decompression dive leaves the diver above the ceiling.
+    let dive = [
+        (segment(0, 40, 80), air),
+        (segment(40, 40, 30 * 60), air),
+        (segment(40, 0, 150), air),
+    ];
+    let ceiling: Vec<_> = findings(&dive, &settings)
+        .into_iter()
+        .filter(|t| t.2 == FindingKind::Ceiling)
+        .collect();
+    assert_eq!(ceiling, [(2, Severity::Error, FindingKind::Ceiling)]);
+}
+
+#[test]
+fn lint_counts_findings_past_its_capacity() {
+    let air = gas!(21, 0);
+    let dive = [(segment(40, 40, 60), air); FINDING_CAPACITY];
+    // Every segment has an END and a density finding.
+    let lint = lint(dive.iter().copied(), &LintSettings::new());
+    assert_eq!(lint.findings().len(), FINDING_CAPACITY);
+    assert_eq!(lint.total(), 2 * FINDING_CAPACITY);
+}
+
+/// Returns an air dive to 45 m in fresh water with EAN50 and oxygen, and a last stop at 6 m.
+fn oxygen_deco_dive() -> DivePlan {
+    let mut plan = single_level(45, 50, gas!(21, 0));
+    plan.set_water(WaterType::Fresh);
+    let mut deco = *plan.deco();
+    let one_minute = Duration::from_secs(60);
+    deco.set_stop_settings(StopSettings::new(3, 6, one_minute, one_minute).unwrap());
+    plan.set_deco(deco);
+    plan.add_deco_gas(gas!(50, 0)).unwrap();
+    plan.add_deco_gas(gas!(100, 0)).unwrap();
+    plan
+}
+
+/// Returns the gas and length in minutes of each part of the stop at a depth.
+fn stops_at(result: &PlanResult, depth: usize) -> Vec<(usize, u64)> {
+    result
+        .segments()
+        .filter(|t| t.segment().start_depth() == depth && t.segment().end_depth() == depth)
+        .map(|t| (t.gas().o2(), t.segment().time().as_secs() / 60))
+        .collect()
+}
+
+#[test]
+fn air_breaks_interrupt_oxygen_stops() {
+    let plan = oxygen_deco_dive();
+    let straight = plan.plan().unwrap();
+    let o2_time: u64 = stops_at(&straight, 6).iter().map(|t| t.1).sum();
+    assert_eq!(stops_at(&straight, 6), [(100, o2_time)]);
+    assert!(o2_time > 20);
+
+    let mut with_breaks = plan;
+    let breaks = AirBreaks::new(
+        Duration::from_secs(20 * 60),
+        Duration::from_secs(5 * 60),
+        1.5,
+    )
+    .unwrap();
+    with_breaks.set_air_breaks(Some(breaks));
+    let result = with_breaks.plan().unwrap();
+
+    // 20 minutes on oxygen, 5 on air, then oxygen until the stop clears. Some nitrogen still
+    // leaves during the break, so the stop needs a little less oxygen but lasts longer in all.
+    let stops = stops_at(&result, 6);
+    assert_eq!(stops.len(), 3);
+    assert_eq!((stops[0], stops[1]), ((100, 20), (21, 5)));
+    assert_eq!(stops[2].0, 100);
+    let broken_o2_time = stops[0].1 + stops[2].1;
+    assert!(broken_o2_time <= o2_time && broken_o2_time + 5 > o2_time);
+    assert!(result.runtime() > straight.runtime());
+
+    // Oxygen at 6 m in fresh water is 1.587 bar: 1.82% CNS and 1.91 OTU a minute. Air is 0.33
+    // bar during the breaks, which adds neither. The rest of the dive is the same.
+    let change = broken_o2_time as Float - o2_time as Float;
+    assert!((result.cns() - straight.cns() - change * 100.0 / 55.05).abs() < 0.05);
+    assert!((result.otu() - straight.otu() - change * 1.9095).abs() < 0.05);
+
+    // Oxygen at 6 m is below a threshold of 1.6 bar, so no break is taken.
+    let mut above = with_breaks;
+    let lenient = AirBreaks::new(
+        Duration::from_secs(20 * 60),
+        Duration::from_secs(5 * 60),
+        1.6,
+    )
+    .unwrap();
+    above.set_air_breaks(Some(lenient));
+    assert_same_dive(&above.plan().unwrap(), &straight);
+}
+
+#[test]
+fn long_stops_on_leaner_gases_count_as_air_breaks() {
+    // EAN50 is 1.53 bar at 21 m, 1.09 at 12 m and 0.94 at 9 m in fresh water.
+    let with_threshold = |pp_o2| {
+        let mut plan = oxygen_deco_dive();
+        let breaks = AirBreaks::new(
+            Duration::from_secs(20 * 60),
+            Duration::from_secs(5 * 60),
+            pp_o2,
+        )
+        .unwrap();
+        plan.set_air_breaks(Some(breaks));
+        plan.plan().unwrap()
+    };
+
+    // Above 1.0 bar, the 15 minutes on EAN50 from 21 m to 12 m are followed by 11 minutes at
+    // 9 m below the threshold, so the oxygen stop starts with a full 20 minutes.
+    let lean = with_threshold(1.0);
+    assert_eq!(stops_at(&lean, 9), [(50, 11)]);
+    assert_eq!(stops_at(&lean, 6)[..2], [(100, 20), (21, 5)]);
+
+    // Above 0.9 bar, the stop at 9 m counts too, and the break is due 5 minutes into it.
+    let rich = with_threshold(0.9);
+    assert_eq!(stops_at(&rich, 9)[..2], [(50, 5), (21, 5)]);
+}
+
+#[test]
+fn air_breaks_are_validated() {
+    let minutes = |t: u64| Duration::from_secs(t * 60);
+    assert!(AirBreaks::new(minutes(20), minutes(5), 1.5).is_ok());
+    for &(interval, break_time) in [(0, 5), (20, 0)].iter() {
+        assert!(
+            AirBreaks::new(minutes(interval), minutes(break_time), 1.5).err()
+                == Some(DivePlanError::AirBreakError)
+        );
+    }
+}
+
+/// Returns the depth and length in seconds of every segment at a constant depth after the bottom,
+/// with whether it is a deco stop.
+fn ascent_stops(result: &PlanResult, bottom_segments: usize) -> Vec<(usize, u64, bool)> {
+    result
+        .segments()
+        .skip(bottom_segments)
+        .filter(|t| t.segment().start_depth() == t.segment().end_depth())
+        .map(|t| {
+            let segment = t.segment();
+            let deep = segment.segment_type() == SegmentType::DeepStop;
+            (segment.start_depth(), segment.time().as_secs(), deep)
+        })
+        .collect()
+}
+
+/// Returns the depths of the deep stops of an ascent.
+fn deep_stop_depths(stops: &[(usize, u64, bool)]) -> Vec<usize> {
+    stops.iter().filter(|t| t.2).map(|t| t.0).collect()
+}
+
+#[test]
+fn pyle_stops_are_made_at_midpoints_to_the_first_stop() {
+    let air = gas!(21, 0);
+    let plan = single_level(45, 30, air);
+    let straight = ascent_stops(&plan.plan().unwrap(), 2);
+    assert_eq!(straight[0], (21, 60, false));
+
+    let mut pyle = plan;
+    let stop_time = Duration::from_secs(120);
+    pyle.set_deep_stops(Some(DeepStops::Pyle { stop_time }))
+        .unwrap();
+    let result = pyle.plan().unwrap();
+    let stops = ascent_stops(&result, 2);
+
+    // Midway from 45 m to the first stop at 21 m is 33 m. Then 27 m and 24 m, made at 24 m. By
+    // then the first stop has moved up to 18 m, so 21 m is a deep stop too, and 19.5 m rounds to
+    // 21 m again.
+    assert_eq!(deep_stop_depths(&stops), [33, 27, 24, 21]);
+    assert!(stops.iter().filter(|t| t.2).all(|t| t.1 == 120));
+    assert_eq!(stops.iter().find(|t| !t.2).unwrap().0, 18);
+
+    // Deep stops are stops of the ascent, so the slate lists them ahead of the deco stops.
+    assert_eq!(result.stops().count(), stops.len());
+    let table = ContingencyTable::new(&pyle, &[0], &[0]).unwrap();
+    let slate: Vec<(u16, u16, bool)> = table
+        .schedule(0, 0)
+        .unwrap()
+        .stops()
+        .iter()
+        .map(|t| (t.depth(), t.time(), t.is_deep()))
+        .collect();
+    assert_eq!(
+        &slate[..5],
+        [
+            (33, 2, true),
+            (27, 2, true),
+            (24, 2, true),
+            (21, 2, true),
+            (18, 1, false)
+        ]
+    );
+    assert_eq!(slate.len(), stops.len());
+
+    // The rest of the ascent is calculated from the loading the deep stops leave: the same as
+    // staying at each deep stop as a level, with longer shallow stops than without them.
+    let mut levels = plan;
+    for &depth in [33, 27, 24, 21].iter() {
+        levels.add_level(Level::new(depth, stop_time, air)).unwrap();
+    }
+    let as_levels = ascent_stops(&levels.plan().unwrap(), 2);
+    assert_eq!(stops.len(), as_levels.len());
+    for (a, b) in stops.iter().zip(as_levels.iter()) {
+        assert_eq!((a.0, a.1), (b.0, b.1));
+    }
+    let last = |stops: &[(usize, u64, bool)]| stops.last().unwrap().1;
+    assert!(last(&stops) > last(&straight));
+    assert!(result.runtime() > plan.plan().unwrap().runtime());
+}
+
+#[test]
+fn ratio_stops_are_made_every_stop_interval_to_the_first_stop() {
+    let air = gas!(21, 0);
+    let mut plan = single_level(45, 30, air);
+    let stop_time = Duration::from_secs(60);
+    plan.set_deep_stops(Some(DeepStops::Ratio {
+        ratio: 0.75,
+        stop_time,
+    }))
+    .unwrap();
+    let stops = ascent_stops(&plan.plan().unwrap(), 2);
+
+    // 75% of 45 m is 33.75 m, made at 36 m, then a stop every 3 m up to the first stop at 18 m.
+    assert_eq!(deep_stop_depths(&stops), [36, 33, 30, 27, 24, 21]);
+    assert!(stops.iter().filter(|t| t.2).all(|t| t.1 == 60));
+    assert_eq!(stops.iter().find(|t| !t.2).unwrap().0, 18);
+
+    // A dive without deco has no first stop to make deep stops on the way to.
+    let mut no_deco = single_level(18, 30, air);
+    let straight = no_deco.plan().unwrap();
+    no_deco
+        .set_deep_stops(Some(DeepStops::Pyle { stop_time }))
+        .unwrap();
+    assert_same_dive(&no_deco.plan().unwrap(), &straight);
+}
+
+#[test]
+fn deep_stops_are_validated() {
+    let mut plan = DivePlan::new();
+    let minute = Duration::from_secs(60);
+    let none = Duration::from_secs(0);
+    for &deep_stops in [
+        DeepStops::Pyle { stop_time: none },
+        DeepStops::Ratio {
+            ratio: 0.5,
+            stop_time: none,
+        },
+        DeepStops::Ratio {
+            ratio: 0.0,
+            stop_time: minute,
+        },
+        DeepStops::Ratio {
+            ratio: 1.0,
+            stop_time: minute,
+        },
+    ]
+    .iter()
+    {
+        assert_eq!(
+            plan.set_deep_stops(Some(deep_stops)).err(),
+            Some(DivePlanError::DeepStopError)
+        );
+        assert!(plan.deep_stops().is_none());
+    }
+    assert!(plan
+        .set_deep_stops(Some(DeepStops::Pyle { stop_time: minute }))
+        .is_ok());
+    assert!(plan.set_deep_stops(None).is_ok());
+    assert!(plan.deep_stops().is_none());
+}
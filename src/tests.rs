@@ -0,0 +1,780 @@
+use crate::common::ascent::{AscentRates, ASCENT_BAND_CAPACITY};
+use crate::common::water::WaterType;
+use crate::common::{DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE};
+use crate::deco::zhl16::{DecoResult, IncrementalPlanner, ZHL16};
+use crate::*;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+/// Returns a deco model set up through the C API.
+fn deco() -> ZHL16 {
+    let mut deco = MaybeUninit::<ZHL16>::uninit();
+    unsafe {
+        assert_eq!(
+            initialise(deco.as_mut_ptr(), mem::size_of::<ZHL16>()),
+            CStatus::Success
+        );
+        deco.assume_init()
+    }
+}
+
+/// Returns a gas made through the C API.
+fn gas(o2: usize, he: usize) -> Gas {
+    let mut gas = MaybeUninit::<Gas>::uninit();
+    unsafe {
+        assert_eq!(
+            gas_new(o2, he, 100 - o2 - he, gas.as_mut_ptr()),
+            CStatus::Success
+        );
+        gas.assume_init()
+    }
+}
+
+/// Stay at a depth for a number of minutes through the C API.
+fn stay(deco: &mut ZHL16, gas: &Gas, depth: usize, minutes: u64) {
+    unsafe {
+        assert_eq!(
+            tick_segment(deco, gas, depth, minutes * 60, 10.0),
+            CStatus::Success
+        );
+    }
+}
+
+#[test]
+fn planner_runs_to_completion_over_several_calls() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    stay(&mut deco, &air, 45, 25);
+
+    let mut planner = MaybeUninit::<IncrementalPlanner>::uninit();
+    let planner = planner.as_mut_ptr();
+    let mut result = MaybeUninit::<DecoResult>::uninit();
+    let mut complete = false;
+    unsafe {
+        assert_eq!(
+            planner_initialise(planner, planner_size() - 1, &deco, &air),
+            CStatus::SizeError
+        );
+        assert_eq!(
+            planner_initialise(planner, planner_size(), &deco, &air),
+            CStatus::Success
+        );
+        assert_eq!(
+            planner_result(planner, result.as_mut_ptr()),
+            CStatus::NoResultError
+        );
+        assert_eq!(
+            planner_begin(
+                planner,
+                &deco,
+                &air,
+                DEFAULT_ASCENT_RATE,
+                DEFAULT_DESCENT_RATE,
+                10.0
+            ),
+            CStatus::Success
+        );
+
+        let mut calls = 0;
+        while !complete {
+            assert_eq!(planner_step(planner, 1, &mut complete), CStatus::Success);
+            calls += 1;
+        }
+        assert!(calls > 1);
+        assert_eq!(
+            planner_result(planner, result.as_mut_ptr()),
+            CStatus::Success
+        );
+        let result = result.assume_init();
+
+        let stop = deco
+            .next_stop(DEFAULT_ASCENT_RATE, DEFAULT_DESCENT_RATE, &air, 10.0)
+            .unwrap();
+        assert_eq!(result.next_stop().start_depth(), stop.start_depth());
+        assert_eq!(result.next_stop().time(), stop.time());
+
+        assert_eq!(
+            planner_begin(planner, &deco, &air, 9, DEFAULT_DESCENT_RATE, 10.0),
+            CStatus::RateError
+        );
+        assert_eq!(
+            planner_step(planner, 1, ptr::null_mut()),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            planner_begin(ptr::null_mut(), &deco, &air, -9, 18, 10.0),
+            CStatus::NullPointerError
+        );
+    }
+}
+
+#[test]
+fn planner_flags_a_stop_that_never_clears() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    unsafe {
+        assert_eq!(set_gradient_factors(&mut deco, 10, 10), CStatus::Success);
+        assert_eq!(set_stop_settings(&mut deco, 3, 6, 60, 60), CStatus::Success);
+    }
+    stay(&mut deco, &air, 40, 60);
+
+    let mut planner = MaybeUninit::<IncrementalPlanner>::uninit();
+    let planner = planner.as_mut_ptr();
+    let mut result = MaybeUninit::<DecoResult>::uninit();
+    let mut complete = false;
+    unsafe {
+        assert_eq!(
+            planner_initialise(planner, planner_size(), &deco, &air),
+            CStatus::Success
+        );
+        assert_eq!(
+            planner_begin(
+                planner,
+                &deco,
+                &air,
+                DEFAULT_ASCENT_RATE,
+                DEFAULT_DESCENT_RATE,
+                10.0
+            ),
+            CStatus::Success
+        );
+        assert_eq!(
+            planner_step(planner, usize::MAX, &mut complete),
+            CStatus::Success
+        );
+        assert!(complete);
+        assert_eq!(
+            planner_result(planner, result.as_mut_ptr()),
+            CStatus::Success
+        );
+        assert!(result.assume_init().capped());
+    }
+}
+
+#[test]
+fn ascent_rate_is_read_from_the_bands() {
+    let mut rates = AscentRates::new(-9).unwrap();
+    rates.add_band(6, -3).unwrap();
+    rates.add_band(3, -1).unwrap();
+    let bands = rates.bands();
+    let mut rate = 0;
+    unsafe {
+        for &(depth, expected) in &[(30, -9), (7, -9), (6, -3), (4, -3), (3, -1), (0, -1)] {
+            assert_eq!(
+                ascent_rate_at(-9, bands.as_ptr(), bands.len(), depth, &mut rate),
+                CStatus::Success
+            );
+            assert_eq!(rate, expected);
+        }
+        assert_eq!(
+            ascent_rate_at(-9, ptr::null(), 0, 30, &mut rate),
+            CStatus::Success
+        );
+        assert_eq!(rate, -9);
+        assert_eq!(
+            ascent_rate_at(-9, ptr::null(), 1, 30, &mut rate),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            ascent_rate_at(9, bands.as_ptr(), bands.len(), 30, &mut rate),
+            CStatus::RateError
+        );
+        assert_eq!(
+            ascent_rate_at(-9, bands.as_ptr(), ASCENT_BAND_CAPACITY + 1, 30, &mut rate),
+            CStatus::SizeError
+        );
+    }
+}
+
+#[test]
+fn null_pointers_are_rejected() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    let mut depth = 7;
+    let mut ndl = 7;
+    let mut compartment = 7;
+    let mut segment = MaybeUninit::<CDiveSegment>::uninit();
+    unsafe {
+        assert_eq!(
+            initialise(ptr::null_mut(), deco_size()),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            deco_depth(ptr::null(), &mut depth),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            deco_depth(&deco, ptr::null_mut()),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            get_ndl(ptr::null(), &air, 10.0, &mut ndl, &mut compartment),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            get_ndl(&deco, &air, 10.0, ptr::null_mut(), &mut compartment),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            tick_segment(&mut deco, ptr::null(), 10, 60, 10.0),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            get_next_stop(&deco, &air, -9, 18, 10.0, ptr::null_mut()),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            get_next_stop(&deco, ptr::null(), -9, 18, 10.0, segment.as_mut_ptr()),
+            CStatus::NullPointerError
+        );
+    }
+    assert_eq!((depth, ndl, compartment), (7, 7, 7));
+}
+
+#[test]
+fn storage_must_be_large_enough_and_aligned() {
+    let mut storage = vec![0u64; deco_size() / 8 + 2];
+    let base = storage.as_mut_ptr() as *mut u8;
+    unsafe {
+        let misaligned = base.add(1) as *mut ZHL16;
+        assert_eq!(initialise(misaligned, deco_size()), CStatus::AlignmentError);
+        let aligned = base as *mut ZHL16;
+        assert_eq!(initialise(aligned, deco_size() - 1), CStatus::SizeError);
+        assert_eq!(initialise(aligned, deco_size()), CStatus::Success);
+
+        let mut depth = 7;
+        assert_eq!(deco_depth(aligned, &mut depth), CStatus::Success);
+        assert_eq!(depth, 0);
+    }
+    assert!(deco_align() >= mem::align_of::<u64>());
+}
+
+#[test]
+fn invalid_arguments_return_a_status_and_leave_outputs() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    let mut gas_out = air;
+    let mut time = 7;
+    let mut bar = 7.0;
+    unsafe {
+        assert_eq!(gas_new(50, 50, 50, &mut gas_out), CStatus::GasFractionError);
+        assert_eq!(
+            gas_new(usize::MAX, 2, 0, &mut gas_out),
+            CStatus::GasFractionError
+        );
+        assert!(gas_out == air);
+
+        assert_eq!(
+            tick_segment(&mut deco, &air, MAX_DEPTH + 1, 60, 10.0),
+            CStatus::DepthError
+        );
+        assert_eq!(
+            tick_segment(&mut deco, &air, 10, 0, 10.0),
+            CStatus::DurationError
+        );
+        assert_eq!(deco.depth(), 0);
+
+        for &(ascent, descent) in [(0, 18), (-MAX_RATE - 1, 18), (-9, 0), (-9, MAX_RATE + 1)].iter()
+        {
+            let mut segment = MaybeUninit::<CDiveSegment>::uninit();
+            assert_eq!(
+                get_next_stop(&deco, &air, ascent, descent, 10.0, segment.as_mut_ptr()),
+                CStatus::RateError
+            );
+        }
+        let mut segment = MaybeUninit::<CDiveSegment>::uninit();
+        assert_eq!(
+            get_next_stop(&deco, &air, -MAX_RATE, MAX_RATE, 10.0, segment.as_mut_ptr()),
+            CStatus::Success
+        );
+
+        assert_eq!(
+            set_stop_settings(&mut deco, 3, MAX_DEPTH + 1, 60, 60),
+            CStatus::DepthError
+        );
+        assert_eq!(
+            set_stop_settings(&mut deco, 0, 3, 60, 60),
+            CStatus::StopSettingsError
+        );
+
+        assert_eq!(time_taken(0, 0, 10, &mut time), CStatus::RateError);
+        assert_eq!(
+            time_taken(-9, 0, MAX_DEPTH + 1, &mut time),
+            CStatus::DepthError
+        );
+        assert_eq!(time, 7);
+
+        for &metres_per_bar in [0.0, -10.0, Float::NAN, Float::INFINITY].iter() {
+            assert_eq!(
+                mtr_bar(10.0, metres_per_bar, &mut bar),
+                CStatus::MetresPerBarError
+            );
+        }
+        assert_eq!(bar, 7.0);
+    }
+}
+
+#[test]
+fn next_stop_is_the_ndl_or_a_deco_stop() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    let mut segment = MaybeUninit::<CDiveSegment>::uninit();
+    unsafe {
+        stay(&mut deco, &air, 30, 10);
+        assert_eq!(
+            get_next_stop(&deco, &air, -9, 18, 10.0, segment.as_mut_ptr()),
+            CStatus::Success
+        );
+        let ndl = segment.assume_init_read();
+        assert!(ndl.segment_type == SegmentType::NoDeco);
+        assert_eq!(ndl.start_depth, 30);
+        assert!(ndl.time > 0);
+
+        stay(&mut deco, &air, 30, 30);
+        assert_eq!(
+            get_next_stop(&deco, &air, -9, 18, 10.0, segment.as_mut_ptr()),
+            CStatus::Success
+        );
+        let stop = segment.assume_init_read();
+        assert!(stop.segment_type == SegmentType::DecoStop);
+        assert!(stop.start_depth > 0 && stop.start_depth < 30);
+        assert_eq!(stop.time % 60000, 0);
+    }
+}
+
+#[test]
+fn water_density_is_taken_from_the_caller() {
+    let fresh = WaterType::Fresh.metres_per_bar();
+    let air = gas(21, 0);
+    let oxygen = gas(100, 0);
+    let mut deco = deco();
+    let mut rust = deco;
+    unsafe {
+        assert_eq!(
+            tick_segment(&mut deco, &air, 40, 30 * 60, fresh),
+            CStatus::Success
+        );
+    }
+    let segment = DiveSegment::constant(
+        SegmentType::DiveSegment,
+        40,
+        Duration::from_secs(30 * 60),
+        -5,
+        5,
+    );
+    rust.add_segment(&segment, &air, fresh);
+    assert!(deco.tissue().p_n2 == rust.tissue().p_n2);
+
+    let mut stop = MaybeUninit::<CDiveSegment>::uninit();
+    let mut supersaturation = MaybeUninit::<Supersaturation>::uninit();
+    let mut planner = MaybeUninit::<IncrementalPlanner>::uninit();
+    let planner = planner.as_mut_ptr();
+    let mut result = MaybeUninit::<DecoResult>::uninit();
+    let mut complete = false;
+    let (mut end, mut otu_out) = (0.0, 0.0);
+    let c_stop = CDiveSegment {
+        segment_type: SegmentType::DecoStop,
+        start_depth: 6,
+        end_depth: 6,
+        time: 20 * 60000,
+        ascent_rate: -9,
+        descent_rate: 18,
+    };
+    unsafe {
+        let expected = rust.next_stop(-9, 18, &air, fresh).unwrap();
+        assert_eq!(
+            get_next_stop(&deco, &air, -9, 18, fresh, stop.as_mut_ptr()),
+            CStatus::Success
+        );
+        let c_next = stop.assume_init_read();
+        assert_eq!(c_next.start_depth, expected.start_depth());
+        assert_eq!(c_next.time, expected.time().as_millis() as u64);
+
+        assert_eq!(
+            deco_supersaturation(&deco, fresh, supersaturation.as_mut_ptr()),
+            CStatus::Success
+        );
+        assert!(supersaturation.assume_init() == rust.supersaturation(fresh));
+        assert!(supersaturation.assume_init() != rust.supersaturation(10.0));
+
+        assert_eq!(
+            planner_initialise(planner, planner_size(), &deco, &air),
+            CStatus::Success
+        );
+        assert_eq!(
+            planner_begin(planner, &deco, &air, -9, 18, fresh),
+            CStatus::Success
+        );
+        assert_eq!(
+            planner_step(planner, usize::MAX, &mut complete),
+            CStatus::Success
+        );
+        assert_eq!(
+            planner_result(planner, result.as_mut_ptr()),
+            CStatus::Success
+        );
+        let next = result.assume_init().next_stop();
+        assert_eq!(next.start_depth(), expected.start_depth());
+        assert_eq!(next.time(), expected.time());
+
+        // Trimix 21/35 at 40 m: (40 + 10.228) x 0.65 - 10.228 = 22.42 m.
+        assert_eq!(gas_end(&gas(21, 35), 40, fresh, &mut end), CStatus::Success);
+        assert!((end - 22.42).abs() < 0.01, "{}", end);
+
+        // Oxygen at 6 m is 1.587 bar: 20 minutes at 1.91 OTUs per minute.
+        assert_eq!(otu(&c_stop, &oxygen, fresh, &mut otu_out), CStatus::Success);
+        assert!((otu_out - 38.2).abs() < 0.1, "{}", otu_out);
+
+        // Every function rejects a density that is not a positive number.
+        assert_eq!(
+            tick_segment(&mut deco, &air, 40, 60, 0.0),
+            CStatus::MetresPerBarError
+        );
+        assert_eq!(
+            get_next_stop(&deco, &air, -9, 18, -10.0, stop.as_mut_ptr()),
+            CStatus::MetresPerBarError
+        );
+        assert_eq!(
+            deco_supersaturation(&deco, Float::NAN, supersaturation.as_mut_ptr()),
+            CStatus::MetresPerBarError
+        );
+        assert_eq!(
+            planner_begin(planner, &deco, &air, -9, 18, 0.0),
+            CStatus::MetresPerBarError
+        );
+        assert_eq!(
+            planner_begin_with_rates(planner, &deco, &air, -9, ptr::null(), 0, 18, 0.0),
+            CStatus::MetresPerBarError
+        );
+        assert_eq!(
+            gas_end(&air, 40, Float::INFINITY, &mut end),
+            CStatus::MetresPerBarError
+        );
+        assert_eq!(
+            otu(&c_stop, &oxygen, 0.0, &mut otu_out),
+            CStatus::MetresPerBarError
+        );
+    }
+    assert!(deco.tissue().p_n2 == rust.tissue().p_n2);
+}
+
+#[test]
+fn headers_match_the_abi_version() {
+    assert_eq!(abi_version(), ABI_VERSION);
+    let define = format!("#define ABI_VERSION {}\n", ABI_VERSION);
+    assert!(include_str!("../capra_c.h").contains(&define));
+    let constant = format!("static const uint32_t ABI_VERSION = {};\n", ABI_VERSION);
+    assert!(include_str!("../capra.h").contains(&constant));
+}
+
+#[test]
+fn opaque_handles_report_their_layout() {
+    assert_eq!(deco_size(), mem::size_of::<ZHL16>());
+    assert_eq!(deco_align(), mem::align_of::<ZHL16>());
+    assert_eq!(planner_size(), mem::size_of::<IncrementalPlanner>());
+    assert_eq!(planner_align(), mem::align_of::<IncrementalPlanner>());
+}
+
+#[test]
+fn tissue_is_copied_out_of_the_model() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    stay(&mut deco, &air, 30, 20);
+    let mut p_n2 = [0.0; TISSUE_COUNT + 1];
+    let mut p_he = [0.0; TISSUE_COUNT + 1];
+    unsafe {
+        assert_eq!(
+            deco_tissue(
+                &deco,
+                p_n2.as_mut_ptr(),
+                p_he.as_mut_ptr(),
+                TISSUE_COUNT - 1
+            ),
+            CStatus::SizeError
+        );
+        assert_eq!(
+            deco_tissue(&deco, p_n2.as_mut_ptr(), ptr::null_mut(), TISSUE_COUNT),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            deco_tissue(
+                &deco,
+                p_n2.as_mut_ptr(),
+                p_he.as_mut_ptr(),
+                TISSUE_COUNT + 1
+            ),
+            CStatus::Success
+        );
+    }
+    let tissue = deco.tissue();
+    assert_eq!(&p_n2[..TISSUE_COUNT], &tissue.p_n2[..]);
+    assert_eq!(&p_he[..TISSUE_COUNT], &tissue.p_he[..]);
+    assert_eq!(p_n2[TISSUE_COUNT], 0.0);
+    // The fastest compartment has loaded well above the surface.
+    assert!(p_n2[0] > 0.7405 * 2.0);
+}
+
+#[test]
+fn gas_utilities_match_known_values() {
+    let nitrox = gas(32, 0);
+    let trimix = gas(21, 35);
+    let (mut pp_o2, mut pp_he, mut pp_n2, mut end) = (0.0, 0.0, 0.0, 0.0);
+    unsafe {
+        assert_eq!(gas_pp_o2(&nitrox, 30, 10.0, &mut pp_o2), CStatus::Success);
+        assert!((pp_o2 - 1.28).abs() < 1e-6);
+        assert_eq!(gas_pp_he(&trimix, 40, 10.0, &mut pp_he), CStatus::Success);
+        assert!((pp_he - 1.75).abs() < 1e-6);
+        assert_eq!(gas_pp_n2(&trimix, 40, 10.0, &mut pp_n2), CStatus::Success);
+        assert!((pp_n2 - 2.2).abs() < 1e-6);
+        assert_eq!(gas_end(&trimix, 40, 10.0, &mut end), CStatus::Success);
+        assert!((end - 22.5).abs() < 1e-6);
+        assert_eq!(gas_end(&nitrox, 30, 10.0, &mut end), CStatus::Success);
+        assert!((end - 30.0).abs() < 1e-6);
+        assert_eq!(
+            gas_pp_o2(&nitrox, MAX_DEPTH + 1, 10.0, &mut pp_o2),
+            CStatus::DepthError
+        );
+    }
+}
+
+#[test]
+fn segment_utilities_match_known_values() {
+    let oxygen = gas(100, 0);
+    let (mut time, mut bar, mut mtr, mut otu_out, mut consumed) = (0, 0.0, 0.0, 0.0, 0);
+    let stop = CDiveSegment {
+        segment_type: SegmentType::DecoStop,
+        start_depth: 6,
+        end_depth: 6,
+        time: 20 * 60000,
+        ascent_rate: -9,
+        descent_rate: 18,
+    };
+    unsafe {
+        assert_eq!(time_taken(-9, 30, 0, &mut time), CStatus::Success);
+        assert_eq!(time, 200_000);
+        assert_eq!(mtr_bar(30.0, 10.0, &mut bar), CStatus::Success);
+        assert!((bar - 4.0).abs() < 1e-6);
+        assert_eq!(bar_mtr(4.0, 10.3, &mut mtr), CStatus::Success);
+        assert!((mtr - 30.9).abs() < 1e-4);
+
+        // 20 minutes at 1.6 bar, at 1.92 OTUs per minute.
+        assert_eq!(otu(&stop, &oxygen, 10.0, &mut otu_out), CStatus::Success);
+        assert!((otu_out - 38.5).abs() < 0.1);
+        // 20 minutes at 1.6 bar with a SAC rate of 20.
+        assert_eq!(
+            gas_consumed(&stop, 20, 10.0, &mut consumed),
+            CStatus::Success
+        );
+        assert_eq!(consumed, 640);
+
+        let broken = CDiveSegment {
+            end_depth: 9,
+            ..stop
+        };
+        assert_eq!(
+            otu(&broken, &oxygen, 10.0, &mut otu_out),
+            CStatus::SegmentError
+        );
+        assert!((otu_out - 38.5).abs() < 0.1);
+    }
+}
+
+#[test]
+fn conservatism_levels_are_validated() {
+    let mut deco = deco();
+    let (mut gf_low, mut gf_high) = (0, 0);
+    unsafe {
+        for &(level, low, high) in [
+            (Conservatism::Low, 45, 95),
+            (Conservatism::Medium, 40, 85),
+            (Conservatism::High, 35, 75),
+        ]
+        .iter()
+        {
+            assert_eq!(set_conservatism(&mut deco, level as u8), CStatus::Success);
+            assert_eq!(
+                get_gradient_factors(&deco, &mut gf_low, &mut gf_high),
+                CStatus::Success
+            );
+            assert_eq!((gf_low, gf_high), (low, high));
+        }
+
+        // Values C can hold in the enum that are not levels leave the model untouched.
+        for &level in [3, 0xff].iter() {
+            assert_eq!(set_conservatism(&mut deco, level), CStatus::InvalidArgument);
+        }
+        assert_eq!(deco.gradient_factors(), (35, 75));
+        assert_eq!(
+            set_conservatism(ptr::null_mut(), 0),
+            CStatus::NullPointerError
+        );
+    }
+}
+
+#[test]
+fn gradient_factors_are_validated() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    unsafe {
+        assert_eq!(set_gradient_factors(&mut deco, 30, 70), CStatus::Success);
+        for &(low, high) in [(0, 70), (30, 101), (80, 70)].iter() {
+            assert_eq!(
+                set_gradient_factors(&mut deco, low, high),
+                CStatus::GradientFactorError
+            );
+        }
+        assert_eq!(deco.gradient_factors(), (30, 70));
+
+        // Lower gradient factors shorten the NDL.
+        stay(&mut deco, &air, 30, 5);
+        let (mut conservative, mut liberal, mut compartment) = (0, 0, 0);
+        assert_eq!(
+            get_ndl(&deco, &air, 10.0, &mut conservative, &mut compartment),
+            CStatus::Success
+        );
+        assert_eq!(set_gradient_factors(&mut deco, 100, 100), CStatus::Success);
+        assert_eq!(
+            get_ndl(&deco, &air, 10.0, &mut liberal, &mut compartment),
+            CStatus::Success
+        );
+        assert!(conservative < liberal);
+    }
+}
+
+#[test]
+fn gradient_factor_profiles_are_validated() {
+    let mut deco = deco();
+    let depths = [0, 10, 30];
+    let gfs = [80, 60, 40];
+    unsafe {
+        assert_eq!(set_gf_profile_high_only(&mut deco), CStatus::Success);
+        assert!(deco.gf_profile() == GradientFactorProfile::HighOnly);
+        assert_eq!(
+            set_gf_profile_table(&mut deco, depths.as_ptr(), gfs.as_ptr(), 3),
+            CStatus::Success
+        );
+        assert!(
+            deco.gf_profile()
+                == GradientFactorProfile::table(&[(0, 80), (10, 60), (30, 40)]).unwrap()
+        );
+        assert_eq!(
+            set_gf_profile_per_stop(&mut deco, gfs.as_ptr(), 3),
+            CStatus::Success
+        );
+        assert!(deco.gf_profile() == GradientFactorProfile::per_stop(&gfs).unwrap());
+
+        // Rejected profiles leave the model untouched.
+        let unordered = [30, 10, 0];
+        let too_high = [80, 101];
+        for &status in [
+            set_gf_profile_table(&mut deco, depths.as_ptr(), gfs.as_ptr(), 0),
+            set_gf_profile_table(
+                &mut deco,
+                depths.as_ptr(),
+                gfs.as_ptr(),
+                GF_TABLE_CAPACITY + 1,
+            ),
+            set_gf_profile_per_stop(&mut deco, gfs.as_ptr(), 0),
+            set_gf_profile_per_stop(&mut deco, gfs.as_ptr(), GF_STOP_CAPACITY + 1),
+        ]
+        .iter()
+        {
+            assert_eq!(status, CStatus::SizeError);
+        }
+        for &status in [
+            set_gf_profile_table(&mut deco, unordered.as_ptr(), gfs.as_ptr(), 3),
+            set_gf_profile_per_stop(&mut deco, too_high.as_ptr(), 2),
+        ]
+        .iter()
+        {
+            assert_eq!(status, CStatus::GradientFactorError);
+        }
+        for &status in [
+            set_gf_profile_table(&mut deco, ptr::null(), gfs.as_ptr(), 3),
+            set_gf_profile_table(&mut deco, depths.as_ptr(), ptr::null(), 3),
+            set_gf_profile_per_stop(&mut deco, ptr::null(), 3),
+            set_gf_profile_linear(ptr::null_mut()),
+        ]
+        .iter()
+        {
+            assert_eq!(status, CStatus::NullPointerError);
+        }
+        assert!(deco.gf_profile() == GradientFactorProfile::per_stop(&gfs).unwrap());
+
+        assert_eq!(set_gf_profile_linear(&mut deco), CStatus::Success);
+        assert!(deco.gf_profile() == GradientFactorProfile::Linear);
+    }
+}
+
+#[test]
+fn supersaturation_is_copied_out_of_the_model() {
+    let air = gas(21, 0);
+    let mut deco = deco();
+    let mut result = MaybeUninit::<Supersaturation>::uninit();
+    unsafe {
+        stay(&mut deco, &air, 40, 30);
+        assert_eq!(
+            deco_supersaturation(&deco, 10.0, result.as_mut_ptr()),
+            CStatus::Success
+        );
+        assert!(result.assume_init() == deco.supersaturation(10.0));
+        assert!(result.assume_init().surface_gf > 100.0);
+        assert_eq!(
+            deco_supersaturation(ptr::null(), 10.0, result.as_mut_ptr()),
+            CStatus::NullPointerError
+        );
+        assert_eq!(
+            deco_supersaturation(&deco, 10.0, ptr::null_mut()),
+            CStatus::NullPointerError
+        );
+    }
+}
+
+#[test]
+fn ndl_table_is_written_to_the_caller_buffer() {
+    let deco = deco();
+    let depths = [12, 18, 30];
+    let gases = [gas(21, 0), gas(32, 0)];
+    let mut entries = [MaybeUninit::<CNdlEntry>::uninit(); 6];
+    let out = entries.as_mut_ptr() as *mut CNdlEntry;
+    unsafe {
+        let table = |depth_count, gas_count, capacity| {
+            ndl_table(
+                &deco,
+                depths.as_ptr(),
+                depth_count,
+                gases.as_ptr(),
+                gas_count,
+                0,
+                0,
+                DEFAULT_DESCENT_RATE,
+                10.0,
+                out,
+                capacity,
+            )
+        };
+        assert_eq!(table(3, 2, 5), CStatus::SizeError);
+        assert_eq!(table(0, 2, 6), CStatus::SizeError);
+        // A count that overflows when multiplied is rejected before anything is read.
+        assert_eq!(table(usize::MAX, 2, 6), CStatus::SizeError);
+        assert_eq!(table(3, 2, 6), CStatus::Success);
+
+        let expected = crate::plan::NdlTable::for_depths(
+            &deco,
+            &depths,
+            &gases,
+            Duration::from_secs(0),
+            0,
+            DEFAULT_DESCENT_RATE,
+            10.0,
+        )
+        .unwrap();
+        for (entry, t) in entries.iter().zip(expected.entries()) {
+            let entry = entry.assume_init_read();
+            assert_eq!(entry.depth, t.depth());
+            assert!(entry.gas == *t.gas());
+            assert_eq!(entry.ndl, t.ndl().as_millis() as u64);
+            assert!(entry.ndl > 0);
+        }
+    }
+}